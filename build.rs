@@ -0,0 +1,24 @@
+use std::process::Command;
+
+// Records the commit the binary is built from for info, unless the packager
+// set UPDATE_ALTERNATIVES_COMMIT already, as builds from a tarball must.
+fn main() {
+    println!("cargo:rerun-if-env-changed=UPDATE_ALTERNATIVES_COMMIT");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    if std::env::var_os("UPDATE_ALTERNATIVES_COMMIT").is_some() {
+        return;
+    }
+
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok());
+
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=UPDATE_ALTERNATIVES_COMMIT={}", commit.trim());
+    }
+}
@@ -26,27 +26,114 @@
 
 extern crate std;
 
-#[derive(Serialize, Deserialize)]
+use super::path_serde;
+
+// Priorities are kept to the integers that JSON consumers such as the web
+// interface can represent exactly.
+pub const MAX_PRIORITY: i64 = (1 << 53) - 1;
+pub const MIN_PRIORITY: i64 = -MAX_PRIORITY;
+
+pub fn check_priority(priority: i64) -> Result<i64, String> {
+    if (MIN_PRIORITY..=MAX_PRIORITY).contains(&priority) {
+        Ok(priority)
+    } else {
+        Err(format!("priority {} is not in {}..={}", priority, MIN_PRIORITY,
+                    MAX_PRIORITY))
+    }
+}
+
+// A link that follows the one of the group, such as javac or the manual page
+// of java, and the path it points at while the alternative is selected.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Follower {
+    #[serde(with = "path_serde")]
+    pub link: std::path::PathBuf,
+    #[serde(with = "path_serde")]
+    pub target: std::path::PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Alternative {
+    #[serde(with = "path_serde")]
     target: std::path::PathBuf,
-    priority: i32,
+    priority: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    followers: Vec<Follower>,
 }
 
 impl Alternative {
     pub fn from_parts<P: std::convert::AsRef<std::path::Path>>(
-        target: P, priority: i32
+        target: P, priority: i64
     ) -> Alternative {
         Alternative{ target: std::path::PathBuf::from(target.as_ref()),
-                     priority }
+                     priority, package: None, followers: Vec::new() }
+    }
+
+    pub fn with_followers(self, followers: Vec<Follower>) -> Alternative {
+        Alternative{ followers, ..self }
     }
 
-    pub fn priority(&self) -> i32 {
+    pub fn priority(&self) -> i64 {
         self.priority
     }
 
+    pub fn set_priority(&mut self, priority: i64) {
+        self.priority = priority;
+    }
+
     pub fn target(&self) -> &std::path::Path {
         &self.target
     }
+
+    // The package that owned the target when it was registered.
+    pub fn package(&self) -> Option<&str> {
+        self.package.as_deref()
+    }
+
+    pub fn set_package(&mut self, package: Option<String>) {
+        self.package = package;
+    }
+
+    pub fn followers(&self) -> &[Follower] {
+        &self.followers
+    }
+
+    // The path that link follows while this alternative is selected.
+    pub fn follower(&self, link: &std::path::Path) -> Option<&std::path::Path> {
+        self.followers.iter()
+                      .find(|f| f.link == link)
+                      .map(|f| f.target.as_path())
+    }
+
+    // Points the alternative at another path. The package is forgotten, since
+    // the path may belong to another one.
+    pub fn set_target<P: std::convert::AsRef<std::path::Path>>(&mut self,
+                                                              target: P) {
+        self.target = std::path::PathBuf::from(target.as_ref());
+        self.package = None;
+    }
+
+    // Takes the priority of other, and its package and followers if it has
+    // them. Returns whether anything changed.
+    pub fn update(&mut self, other: Alternative) -> bool {
+        let package = other.package.or_else(|| self.package.clone());
+        let followers = if other.followers.is_empty() {
+            self.followers.clone()
+        } else {
+            other.followers
+        };
+        let changed = self.priority != other.priority
+                      || self.package != package
+                      || self.followers != followers;
+
+        self.priority = other.priority;
+        self.package = package;
+        self.followers = followers;
+
+        changed
+    }
 }
 
 impl std::fmt::Display for Alternative {
@@ -54,3 +141,69 @@ impl std::fmt::Display for Alternative {
         write!(formatter, "{}: {}", self.target.display(), self.priority)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Alternative, Follower};
+
+    fn follower(link: &str, target: &str) -> Follower {
+        Follower{ link: std::path::PathBuf::from(link),
+                  target: std::path::PathBuf::from(target) }
+    }
+
+    #[test]
+    fn update_takes_the_new_priority() {
+        let mut alternative = Alternative::from_parts("/usr/bin/gcc", 10);
+
+        assert!(alternative.update(Alternative::from_parts("/usr/bin/gcc",
+                                                           20)));
+        assert_eq!(alternative.priority(), 20);
+    }
+
+    #[test]
+    fn update_without_changes_reports_none() {
+        let mut alternative = Alternative::from_parts("/usr/bin/gcc", 10);
+        alternative.set_package(Some(String::from("gcc")));
+
+        assert!(!alternative.update(Alternative::from_parts("/usr/bin/gcc",
+                                                            10)));
+        assert_eq!(alternative.package(), Some("gcc"));
+    }
+
+    #[test]
+    fn update_replaces_or_keeps_the_package() {
+        let mut alternative = Alternative::from_parts("/usr/bin/gcc", 10);
+        alternative.set_package(Some(String::from("gcc")));
+
+        let mut other = Alternative::from_parts("/usr/bin/gcc", 10);
+        other.set_package(Some(String::from("gcc-14")));
+
+        assert!(alternative.update(other));
+        assert_eq!(alternative.package(), Some("gcc-14"));
+
+        assert!(!alternative.update(Alternative::from_parts("/usr/bin/gcc",
+                                                            10)));
+        assert_eq!(alternative.package(), Some("gcc-14"));
+    }
+
+    #[test]
+    fn update_replaces_or_keeps_the_followers() {
+        let mut alternative = Alternative::from_parts("/usr/bin/java", 1)
+            .with_followers(vec![follower("/usr/local/bin/javac",
+                                          "/usr/bin/javac")]);
+
+        assert!(!alternative.update(Alternative::from_parts("/usr/bin/java",
+                                                            1)));
+        assert_eq!(alternative.followers().len(), 1);
+
+        let other = Alternative::from_parts("/usr/bin/java", 1)
+            .with_followers(vec![follower("/usr/local/bin/jar",
+                                          "/usr/bin/jar")]);
+
+        assert!(alternative.update(other));
+        assert_eq!(alternative.follower("/usr/local/bin/jar".as_ref()),
+                   Some("/usr/bin/jar".as_ref()));
+        assert_eq!(alternative.follower("/usr/local/bin/javac".as_ref()),
+                   None);
+    }
+}
@@ -28,17 +28,188 @@ extern crate std;
 
 extern crate serde_json;
 
-use super::alternative::Alternative;
-use super::alternative_list::AlternativeList;
+use super::alternative::{Alternative, check_priority};
+use super::alternative_list::{AlternativeList, Kind, Weights};
+use super::errors;
 use super::filesystem;
+use super::lock;
+use super::package::Provider;
+use super::timings;
 
 type AlternativeTable = std::collections::HashMap<String, AlternativeList>;
 
+const ENTRY_MODE: u32 = 0o644;
+
+pub static DB_FOLDER: &str = "/etc/alternatives";
+
+pub static LINK_DIR: &str = "/usr/local/bin";
+
+// Where the links of library groups go by default; ld.so.conf lists it on
+// most distributions.
+pub static LIBRARY_DIR: &str = "/usr/local/lib";
+
+// Groups named NAME@TRIPLET, such as cc@aarch64-linux-gnu, select a tool of
+// a cross toolchain, and link it as NAME in TRIPLET_PREFIX/TRIPLET/bin, like
+// binutils does in /usr/TRIPLET/bin.
+pub static TRIPLET_PREFIX: &str = "/usr/local";
+
+// Lists the entries of a commit that is moving them into place.
+pub static JOURNAL_PATH: &str = "/var/lib/update-alternatives/journal";
+
+// Links are written by at most this many threads at once.
+const LINK_WRITERS: usize = 8;
+
+// The longest suffix added to a group name to form the name of its entry.
+const ENTRY_SUFFIX: &str = ".json.new";
+
+// Group names become the names of their entries and, by default, of their
+// links, so they must be valid file names with room for the suffix.
+pub fn check_name(name: &str) -> Result<(), String> {
+    let longest = filesystem::NAME_MAX - ENTRY_SUFFIX.len();
+
+    if name.is_empty() || name == "." || name == ".." {
+        Err(format!("'{}' is not a valid name", name))
+    } else if name.contains(['/', '\0']) {
+        Err(format!("name {} must not contain '/'", name))
+    } else if name.len() > longest {
+        Err(format!("name {} is longer than {} bytes", name, longest))
+    } else {
+        match split_triplet(name) {
+            ("", Some(_)) => Err(format!("name {} has no name before '@'",
+                                         name)),
+            (_, Some(t)) if !is_triplet(t) => {
+                Err(format!("name {}: {} is not a target triplet such as \
+                            aarch64-linux-gnu", name, t))
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+// The name of a group without its triplet, and the triplet.
+pub fn split_triplet(name: &str) -> (&str, Option<&str>) {
+    match name.rsplit_once('@') {
+        Some((base, triplet)) => (base, Some(triplet)),
+        None => (name, None),
+    }
+}
+
+// Such as x86_64-w64-mingw32 or arm-linux-gnueabihf.
+fn is_triplet(triplet: &str) -> bool {
+    triplet.contains('-')
+        && !triplet.starts_with(['-', '.'])
+        && triplet.chars().all(|c| c.is_ascii_alphanumeric()
+                                   || matches!(c, '-' | '_' | '.'))
+}
+
+// The file name is NAME.json also for names with dots in them, such as
+// libblas.so.3.
+pub fn entry_path<P: std::convert::AsRef<std::path::Path>>(folder: P,
+                                                           name: &str)
+-> std::path::PathBuf {
+    folder.as_ref().join(format!("{}.json", name))
+}
+
+// Where the link of a new group is placed unless another one is given.
+pub fn default_link(name: &str) -> std::path::PathBuf {
+    match split_triplet(name) {
+        (base, Some(triplet)) => std::path::Path::new(TRIPLET_PREFIX)
+                                           .join(triplet).join("bin")
+                                           .join(base),
+        (_, None) => std::path::Path::new(LINK_DIR).join(name),
+    }
+}
+
+// Everything add checks before changing anything, for the interfaces that
+// take their arguments from elsewhere than the command line.
+pub fn check_addition(name: &str, target: &std::path::Path,
+                      priority: i64) -> Result<(), errors::Error> {
+    check_name(name)
+        .and_then(|_| filesystem::check_path(target))
+        .and_then(|_| check_priority(priority).map(|_| ()))
+        .map_err(|e| errors::Error::new(&errors::BAD_ARGUMENT, e))
+}
+
+// Where dpkg keeps the administrative files of its own alternatives.
+pub static DPKG_ADMIN_DIR: &str = "/var/lib/dpkg/alternatives";
+
+// An entry of the database folder that this tool did not write, such as the
+// symlinks dpkg keeps in /etc/alternatives. Foreign entries are never read,
+// overwritten, or removed.
+pub struct ForeignEntry {
+    pub path: std::path::PathBuf,
+    pub kind: ForeignKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ForeignKind {
+    Dpkg,
+    Symlink,
+    Directory,
+    File,
+}
+
+impl std::fmt::Display for ForeignKind {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str(match *self {
+            ForeignKind::Dpkg => "dpkg alternative",
+            ForeignKind::Symlink => "symlink",
+            ForeignKind::Directory => "directory",
+            ForeignKind::File => "file",
+        })
+    }
+}
+
+// A link that was rewritten; old is None if the link did not exist before.
+pub struct LinkChange {
+    pub name: String,
+    pub link: std::path::PathBuf,
+    pub old: Option<std::path::PathBuf>,
+    pub new: std::path::PathBuf,
+}
+
 pub struct AlternativeDb {
     table: AlternativeTable,
+    root: std::path::PathBuf,
+    foreign: Vec<std::path::PathBuf>,
+    // The groups a commit writes, or None for every group.
+    scope: Option<std::collections::BTreeSet<String>>,
+}
+
+// An empty database of the running system.
+impl Default for AlternativeDb {
+    fn default() -> AlternativeDb {
+        AlternativeDb{ table: AlternativeTable::new(),
+                       root: std::path::PathBuf::from("/"),
+                       foreign: Vec::new(), scope: None }
+    }
 }
 
 impl AlternativeDb {
+    pub fn open<P: std::convert::AsRef<std::path::Path>>(root: P)
+        -> std::io::Result<AlternativeDb> {
+        recover(root.as_ref());
+
+        let mut db = AlternativeDb::from_folder(
+            filesystem::under(root.as_ref(), DB_FOLDER)
+        )?;
+
+        db.set_root(root);
+
+        Ok(db)
+    }
+
+    // Checks the targets for existence below root, for a database read with
+    // from_folder from a root other than /.
+    pub fn set_root<P: std::convert::AsRef<std::path::Path>>(&mut self,
+                                                             root: P) {
+        self.root = std::path::PathBuf::from(root.as_ref());
+
+        for list in self.table.values_mut() {
+            list.set_root(root.as_ref());
+        }
+    }
+
     pub fn from_folder<P: std::convert::AsRef<std::path::Path>>(folder: P)
         -> std::io::Result<AlternativeDb> {
         let folder_path = folder.as_ref();
@@ -46,7 +217,7 @@ impl AlternativeDb {
             Ok(c) => c,
             Err(e) => {
                 if e.kind() == std::io::ErrorKind::NotFound {
-                    return Ok(AlternativeDb{ table: AlternativeTable::new() });
+                    return Ok(AlternativeDb::default());
                 }
 
                 return Err(e);
@@ -55,56 +226,146 @@ impl AlternativeDb {
 
         let to_reserve = estimate_size(&children);
         let mut table = AlternativeTable::with_capacity(to_reserve);
+        let mut foreign = Vec::new();
 
         for child in children {
             let entry = match child {
                 Ok(c) => c,
                 Err(e) => {
-                    eprintln!("update-alternatives: unable to read entry of \
-                              directory {}: {}", folder_path.display(), e);
+                    error!(BAD_DATABASE, "unable to read entry of directory \
+                                         {}: {}", folder_path.display(), e);
 
                     continue;
                 },
             };
 
-            let path = entry.path();
+            match read_entry(&entry) {
+                Entry::Group(name, list) => {
+                    println!("update-alternatives: loading alternative for {} \
+                             with {} entries...", name, list.num_links());
+                    table.insert(name, *list);
+                },
+                Entry::Foreign(path) => foreign.push(path),
+                Entry::Skipped => (),
+            }
+        }
+
+        foreign.sort();
 
-            let name = String::from(match path.file_stem() {
-                Some(s) => s.to_string_lossy(),
-                None => {
-                    println!("update-alternatives: skipping entry {}...",
-                             path.display());
+        Ok(AlternativeDb{ table, root: std::path::PathBuf::from("/"),
+                          foreign, scope: None })
+    }
 
-                    continue;
-                },
-            });
+    // Reads the groups whose names keep selects one at a time, in name order,
+    // and hands each to f, so that listing a huge database never holds more
+    // than one group.
+    pub fn each_group<P, K, F>(root: P, keep: K, mut f: F)
+    -> std::io::Result<()>
+    where P: std::convert::AsRef<std::path::Path>,
+          K: Fn(&str) -> bool,
+          F: FnMut(&str, &AlternativeList) {
+        recover(root.as_ref());
 
-            let contents = match filesystem::read(&path) {
-                Ok(c) => c,
-                Err(e) => {
-                    eprintln!("update-alternatives: could not read file {}: {}",
-                              path.display(), e);
+        let folder = filesystem::under(root.as_ref(), DB_FOLDER);
+        let children = match folder.read_dir() {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(());
+            },
+            Err(e) => return Err(e),
+        };
 
-                    continue;
-                }
-            };
+        let mut entries: Vec<(std::ffi::OsString, std::fs::DirEntry)> =
+            children.filter_map(Result::ok)
+                    .filter_map(|e| Some((e.path().file_stem()?.to_owned(), e)))
+                    .filter(|(stem, _)| keep(&stem.to_string_lossy()))
+                    .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
 
-            let list: AlternativeList = match serde_json::from_str(&contents) {
-                Ok(l) => l,
-                Err(e) => {
-                    eprintln!("update-alternatives: unable to \
-                             deserialize {}: {}", path.display(), e);
+        for (_, entry) in entries {
+            if let Entry::Group(name, mut list) = read_entry(&entry) {
+                list.set_root(root.as_ref());
+                f(&name, &list);
+            }
+        }
 
-                    continue;
-                }
+        Ok(())
+    }
+
+    // The entries of the database folder that are not ours, sorted by kind
+    // and then by path.
+    pub fn foreign_entries(&self) -> Vec<ForeignEntry> {
+        let dpkg = dpkg_names(&self.root);
+
+        let mut entries: Vec<ForeignEntry> = self.foreign.iter().map(|path| {
+            let metadata = std::fs::symlink_metadata(path);
+            let kind = match metadata.map(|m| m.file_type()) {
+                Ok(t) if t.is_symlink() => {
+                    let name = path.file_name().and_then(|n| n.to_str());
+
+                    match name {
+                        Some(n) if dpkg.contains(n) => ForeignKind::Dpkg,
+                        _ => ForeignKind::Symlink,
+                    }
+                },
+                Ok(t) if t.is_dir() => ForeignKind::Directory,
+                _ => ForeignKind::File,
             };
 
-            println!("update-alternatives: loading alternative for {} with {} \
-                     entries...", name, list.num_links());
-            table.insert(name, list);
-        }
+            ForeignEntry{ path: path.clone(), kind }
+        }).collect();
+
+        entries.sort_by(|a, b| (a.kind, &a.path).cmp(&(b.kind, &b.path)));
+
+        entries
+    }
+
+    // Reads the entry of name again after another process changed it, and
+    // forgets the group if the entry is gone.
+    pub fn reload(&mut self, name: &str) -> std::io::Result<()> {
+        let path = entry_path(self.folder(), name);
+
+        let contents = match filesystem::read(&path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.table.remove(name);
+
+                return Ok(());
+            },
+            Err(e) => return Err(e),
+        };
 
-        Ok(AlternativeDb{ table })
+        let mut list: AlternativeList = serde_json::from_str(&contents)?;
+        list.dedup();
+        list.set_root(&self.root);
+
+        self.table.insert(name.to_string(), list);
+
+        Ok(())
+    }
+
+    // Limits commits to the groups names, for a process that holds only their
+    // locks: the other groups may be changed by other processes meanwhile, so
+    // what was read of them is not written back.
+    pub fn limit_to(&mut self, names: &[&str]) {
+        self.scope = Some(names.iter().map(|n| n.to_string()).collect());
+    }
+
+    // Whether a commit writes the entry, link, and profile of name.
+    pub fn writes(&self, name: &str) -> bool {
+        self.scope.as_ref().is_none_or(|s| s.contains(name))
+    }
+
+    fn written(&self) -> impl Iterator<Item = (&String, &AlternativeList)> {
+        self.table.iter().filter(move |(name, _)| self.writes(name))
+    }
+
+    pub fn root(&self) -> &std::path::Path {
+        &self.root
+    }
+
+    pub fn folder(&self) -> std::path::PathBuf {
+        filesystem::under(&self.root, DB_FOLDER)
     }
 
     pub fn num_alternatives(&self) -> usize {
@@ -127,17 +388,121 @@ impl AlternativeDb {
         Some(&self.table[name])
     }
 
-    pub fn add_alternative(&mut self, name: &str,
-                           to_add: Alternative) -> bool {
-        if !self.has_alternatives(&name) {
-            let path = format!("/usr/local/bin/{}", name);
+    pub fn add_alternative(&mut self, name: &str, to_add: Alternative)
+    -> Result<bool, errors::Error> {
+        self.add_alternative_with_link(name, default_link(name), to_add)
+    }
+
+    // The link is only used if the group does not exist yet. Targets that are
+    // or resolve to the group's own link are refused, since the link would
+    // point at itself. Returns whether anything changed.
+    pub fn add_alternative_with_link<P: std::convert::AsRef<std::path::Path>>(
+        &mut self, name: &str, link: P, to_add: Alternative
+    ) -> Result<bool, errors::Error> {
+        let own_link = match self.alternatives(name) {
+            Some(list) => list.path(),
+            None => link.as_ref(),
+        };
 
-            self.table.insert(name.to_string(), AlternativeList::new(path));
+        if filesystem::resolves_to(&self.root, to_add.target(), own_link) {
+            return Err(errors::Error::new(&errors::LOOP, format!(
+                "target {} is or resolves through the link {} of {}",
+                to_add.target().display(), own_link.display(), name
+            )));
         }
 
+        self.add_group(name, link);
+
         let list = self.table.get_mut(name).unwrap();
 
-        list.add_alternative(to_add)
+        Ok(list.add_alternative(to_add))
+    }
+
+    // Adds the group name with link and no alternatives. Returns false if
+    // it exists already.
+    pub fn add_group<P: std::convert::AsRef<std::path::Path>>(
+        &mut self, name: &str, link: P
+    ) -> bool {
+        if self.has_alternatives(name) {
+            return false;
+        }
+
+        let mut list = AlternativeList::new(link);
+        list.set_root(&self.root);
+
+        self.table.insert(name.to_string(), list);
+
+        true
+    }
+
+    // Adds the group new_name with the alternatives of name and link. Returns
+    // false if there is no group name or new_name exists already.
+    pub fn copy<P: std::convert::AsRef<std::path::Path>>(
+        &mut self, name: &str, new_name: &str, link: P
+    ) -> bool {
+        if self.has_alternatives(new_name) {
+            return false;
+        }
+
+        let copy = match self.alternatives(name) {
+            Some(list) => list.copy_to(link),
+            None => return false,
+        };
+
+        self.table.insert(new_name.to_string(), copy);
+
+        true
+    }
+
+    pub fn renumber(&mut self, name: &str, step: i64)
+    -> Option<Vec<(std::path::PathBuf, i64, i64)>> {
+        self.table.get_mut(name)?.renumber(step)
+    }
+
+    pub fn set_environment(&mut self, name: &str, variable: &str,
+                           template: Option<&str>) -> bool {
+        self.table.get_mut(name)
+                  .is_some_and(|l| l.set_environment(variable, template))
+    }
+
+    pub fn set_tag(&mut self, name: &str, tag: &str, tagged: bool) -> bool {
+        self.table.get_mut(name).is_some_and(|l| l.set_tag(tag, tagged))
+    }
+
+    pub fn set_weights(&mut self, name: &str, weights: Weights) -> bool {
+        match self.table.get_mut(name) {
+            Some(list) if list.weights() != weights => {
+                list.set_weights(weights);
+
+                true
+            },
+            _ => false,
+        }
+    }
+
+    pub fn set_kind(&mut self, name: &str, kind: Kind) -> bool {
+        match self.table.get_mut(name) {
+            Some(list) if *list.kind() != kind => {
+                list.set_kind(kind);
+
+                true
+            },
+            _ => false,
+        }
+    }
+
+    // Remembers the owning package of targets registered without one, so that
+    // they can be pruned once the package is gone.
+    pub fn record_packages(&mut self, provider: &dyn Provider) {
+        for list in self.table.values_mut() {
+            for alternative in list.links_mut() {
+                if alternative.package().is_none() {
+                    let owner = provider.owner(alternative.target());
+
+                    alternative.set_package(owner.map(|p| p.name));
+                }
+            }
+        }
     }
 
     pub fn remove_alternative<P: std::convert::AsRef<std::path::Path>>(
@@ -152,40 +517,162 @@ impl AlternativeDb {
         list.remove_alternative(target)
     }
 
+    pub fn archive_alternative(&mut self, name: &str,
+                               target: &std::path::Path) -> bool {
+        self.table.get_mut(name)
+                  .is_some_and(|l| l.archive_alternative(target))
+    }
+
+    pub fn restore_alternative(&mut self, name: &str,
+                               target: &std::path::Path)
+    -> Option<&Alternative> {
+        self.table.get_mut(name)?.restore_alternative(target)
+    }
+
+    // What set checks before selecting target, for the interfaces that take
+    // their arguments from elsewhere than the command line.
+    pub fn check_selection(&self, name: &str, target: &std::path::Path)
+    -> Result<(), errors::Error> {
+        let list = self.alternatives(name).ok_or_else(|| {
+            errors::Error::new(&errors::NOT_FOUND,
+                               format!("no alternatives found for {}", name))
+        })?;
+
+        if !list.links().iter().any(|a| a.target() == target) {
+            return Err(errors::Error::new(&errors::NOT_MEMBER, format!(
+                "{} is not an alternative for {}", target.display(), name
+            )));
+        }
+
+        Ok(())
+    }
+
+    // Selects target of the group name by hand; see AlternativeList::select.
+    pub fn select<P: std::convert::AsRef<std::path::Path>>(
+        &mut self, name: &str, target: P,
+        until: Option<std::time::SystemTime>
+    ) -> bool {
+        match self.table.get_mut(name) {
+            Some(list) => list.select(target, until),
+            None => false,
+        }
+    }
+
+    // Returns the group name to automatic selection, and returns the target
+    // that was selected by hand if there was one.
+    pub fn clear_manual(&mut self, name: &str) -> Option<std::path::PathBuf> {
+        let manual = self.table.get_mut(name)?.clear_manual()?;
+
+        Some(manual.target().to_path_buf())
+    }
+
+    // Returns the groups whose manual selection expired before now to
+    // automatic selection, and returns their names and the targets that were
+    // selected, in name order.
+    pub fn expire(&mut self, now: std::time::SystemTime)
+    -> Vec<(String, std::path::PathBuf)> {
+        let mut expired: Vec<(String, std::path::PathBuf)> =
+            self.table.iter_mut()
+                .filter(|(_, list)| list.manual()
+                                        .is_some_and(|m| m.is_expired(now)))
+                .filter_map(|(name, list)| {
+                    let manual = list.clear_manual()?;
+
+                    Some((name.clone(), manual.target().to_path_buf()))
+                })
+                .collect();
+        expired.sort();
+
+        expired
+    }
+
+    // Moves the alternative for old to new in every group that has one, and
+    // returns the names of those groups in order.
+    pub fn retarget(&mut self, old: &std::path::Path, new: &std::path::Path)
+    -> Vec<String> {
+        let mut names: Vec<String> = self.table.iter_mut()
+            .filter_map(|(name, list)| {
+                list.retarget(old, new).then(|| name.clone())
+            })
+            .collect();
+        names.sort();
+
+        names
+    }
+
+    // The lists that differ from what is stored in folder.
+    pub fn changed_lists<P: std::convert::AsRef<std::path::Path>>(
+        &self, folder: P
+    ) -> Vec<(&str, &AlternativeList)> {
+        let mut changed: Vec<(&str, &AlternativeList)> = self.written()
+            .filter(|(name, list)| {
+                let db_file = entry_path(&folder, name);
+
+                serde_json::to_string(list).ok()
+                    != filesystem::read(&db_file).ok()
+            })
+            .map(|(name, list)| (name.as_str(), list))
+            .collect();
+
+        changed.sort_by_key(|(name, _)| *name);
+
+        changed
+    }
+
     pub fn write_out<P: std::convert::AsRef<std::path::Path>>(&self, folder: P)
         -> std::io::Result<usize> {
         let folder_path = folder.as_ref();
 
         if !folder_path.exists() {
-            if let Err(e) = filesystem::create_dir(folder_path) {
-                return Err(e);
-            }
+            filesystem::create_dir(folder_path)?;
         } else if !folder_path.is_dir() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::AlreadyExists, "path is not a directory"
             ));
         }
 
-        let mut written: usize = 0;
+        // Other processes that hold the locks of other groups commit at the
+        // same time, but never write the journal at the same time.
+        let _commit = lock::acquire_commit(&self.root)?;
 
-        for (name, list) in self.table.iter() {
-            let db_file = folder_path.join(name).with_extension("json");
+        AlternativeDb::finish_interrupted(&self.root)?;
 
-            if let Err(e) = AlternativeDb::rename_existing(&db_file) {
-                eprintln!("update-alternatives: could not rename file {}: {}",
-                          db_file.display(), e);
-                continue;
+        // Nothing that is already there is replaced unless it is ours.
+        for (name, _) in self.written() {
+            let db_file = entry_path(folder_path, name);
+
+            if self.foreign.contains(&db_file) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("{} is not an entry of update-alternatives",
+                            db_file.display())
+                ));
             }
+        }
+
+        let mut written: usize = 0;
+        let mut staged = Vec::new();
+
+        // Every entry is written in full next to its final name first, so
+        // that none is ever left half-written.
+        for (name, list) in self.written() {
+            let db_file = entry_path(folder_path, name);
+            let staged_file = db_file.with_extension("json.new");
+            let started = timings::start();
+            let result = AlternativeDb::write_list(list, &staged_file);
+
+            timings::group("serialize", name, started);
 
-            match AlternativeDb::write_list(list, &db_file) {
+            match result {
                 Ok(n) => {
                     written += n;
-
-                    AlternativeDb::cleanup(&db_file);
+                    staged.push((name.as_str(), staged_file, db_file));
                 },
                 Err(e) => {
-                    if let Err(e) = AlternativeDb::recover(&db_file) {
-                        return Err(e);
+                    let _ = std::fs::remove_file(&staged_file);
+
+                    for (_, staged_file, _) in staged {
+                        let _ = std::fs::remove_file(staged_file);
                     }
 
                     return Err(e);
@@ -193,27 +680,202 @@ impl AlternativeDb {
             }
         }
 
+        // Once the journal exists, the commit is completed even if this
+        // process dies before moving every entry into place.
+        let journal = filesystem::under(&self.root, JOURNAL_PATH);
+        let names: String = staged.iter().map(|(n, _, _)| format!("{}\n", n))
+                                  .collect();
+
+        AlternativeDb::write_synced(names, &journal)?;
+
+        for (_, staged_file, db_file) in staged {
+            filesystem::replace_file(staged_file, db_file)?;
+        }
+
+        std::fs::File::open(folder_path)?.sync_all()?;
+        std::fs::remove_file(&journal)?;
+
         Ok(written)
     }
 
-    pub fn write_links(&self) -> std::io::Result<()> {
-        for list in self.table.values() {
-            if let Err(e) = list.make_symlink() {
-                return Err(e);
+    // Moves the staged entries of a commit that was interrupted after writing
+    // its journal into place, and removes those of one interrupted before.
+    // Returns whether there was anything to clean up.
+    pub fn finish_interrupted<P: std::convert::AsRef<std::path::Path>>(
+        root: P
+    ) -> std::io::Result<bool> {
+        filesystem::check_writable()?;
+
+        let folder = filesystem::under(root.as_ref(), DB_FOLDER);
+        let journal = filesystem::under(root.as_ref(), JOURNAL_PATH);
+        let mut found = false;
+
+        match filesystem::read(&journal) {
+            Ok(names) => {
+                for name in names.lines() {
+                    let db_file = entry_path(&folder, name);
+                    let staged_file = db_file.with_extension("json.new");
+
+                    if staged_file.exists() {
+                        filesystem::replace_file(staged_file, db_file)?;
+                    }
+                }
+
+                std::fs::remove_file(&journal)?;
+                found = true;
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e),
+        }
+
+        let children = match folder.read_dir() {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(found);
+            },
+            Err(e) => return Err(e),
+        };
+
+        for child in children {
+            let child = child?;
+            let path = child.path();
+            let file_name = path.file_name().map(|n| n.to_string_lossy())
+                                .unwrap_or_default();
+
+            if !child.file_type()?.is_file() {
+                continue;
+            }
+
+            if file_name.ends_with(".json.new") {
+                std::fs::remove_file(&path)?;
+                found = true;
+            } else if file_name.ends_with(".json.old") {
+                // Left behind by versions that renamed the old entry aside.
+                let db_file = path.with_extension("");
+
+                if db_file.exists() {
+                    std::fs::remove_file(&path)?;
+                } else {
+                    filesystem::replace_file(&path, db_file)?;
+                }
+
+                found = true;
             }
         }
 
-        Ok(())
+        Ok(found)
+    }
+
+    // The links that write_links would change, without touching them.
+    pub fn pending_links(&self) -> Vec<LinkChange> {
+        let mut changes: Vec<LinkChange> = self.written()
+            .filter_map(|(name, list)| {
+                let new = list.current_target()?;
+                let old = list.linked_target();
+
+                if old.as_deref() == Some(new) {
+                    return None;
+                }
+
+                Some(LinkChange{ name: name.clone(),
+                                 link: list.path().to_path_buf(), old,
+                                 new: new.to_path_buf() })
+            })
+            .collect();
+
+        changes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        changes
+    }
+
+    pub fn write_links(&self) -> std::io::Result<Vec<LinkChange>> {
+        let (changes, errors) = self.write_links_each(|_| true, |_| ());
+
+        self.print_changes(&changes);
+
+        match errors.into_iter().next() {
+            Some((_, e)) => Err(e),
+            None => Ok(changes),
+        }
     }
 
-    fn rename_existing(link: &std::path::Path) -> std::io::Result<()> {
-        if !link.exists() {
-            return Ok(());
+    // Writes the links of the groups keep selects, several at once, since the
+    // work is spent waiting for system calls, and calls done with the name of
+    // every group as it is finished. A group is only ever written by one
+    // thread. Changes and errors are collected in name order rather than
+    // stopping at the first error, and nothing is printed.
+    pub fn write_links_each<K, F>(&self, keep: K, mut done: F)
+    -> (Vec<LinkChange>, Vec<(String, std::io::Error)>)
+    where K: Fn(&AlternativeList) -> bool,
+          F: FnMut(&str) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut groups: Vec<_> = self.written()
+                                     .filter(|(_, list)| keep(list))
+                                     .collect();
+        groups.sort_by_key(|(name, _)| *name);
+
+        let workers = std::thread::available_parallelism()
+                                  .map_or(1, |n| n.get())
+                                  .clamp(1, LINK_WRITERS)
+                                  .min(groups.len());
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        let mut results: Vec<_> = std::thread::scope(|scope| {
+            for _ in 0..workers {
+                let (groups, next, sender) = (&groups, &next, sender.clone());
+
+                scope.spawn(move || {
+                    while let Some(&(name, list)) =
+                        groups.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let started = timings::start();
+                        let old = list.linked_target();
+                        let written = list.write_symlink();
+
+                        timings::group("links", name, started);
+
+                        if sender.send((name, list, old, written)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+
+            drop(sender);
+
+            receiver.iter().inspect(|(name, _, _, _)| done(name)).collect()
+        });
+
+        results.sort_by_key(|(name, _, _, _)| *name);
+
+        let mut changes = Vec::new();
+        let mut errors = Vec::new();
+
+        for (name, list, old, written) in results {
+            match written {
+                Ok(true) => changes.push(LinkChange{
+                    name: name.clone(),
+                    link: list.path().to_path_buf(),
+                    old,
+                    new: list.current_target().map(|t| t.to_path_buf())
+                                              .unwrap_or_default(),
+                }),
+                Ok(false) => (),
+                Err(e) => errors.push((name.clone(), e)),
+            }
         }
 
-        let new_link = link.with_extension("json.old");
+        (changes, errors)
+    }
 
-        std::fs::rename(link, &new_link)
+    // Says which links write_links_each changed.
+    pub fn print_changes(&self, changes: &[LinkChange]) {
+        for change in changes {
+            if let Some(list) = self.alternatives(&change.name) {
+                list.print_symlink();
+            }
+        }
     }
 
     fn write_list(list: &AlternativeList,
@@ -223,46 +885,155 @@ impl AlternativeDb {
             Err(e) => return Err(std::io::Error::from(e)),
         };
 
-        filesystem::write(to_write, path)
+        let written = AlternativeDb::write_synced(to_write, path)?;
+        filesystem::set_mode(path, ENTRY_MODE)?;
+
+        Ok(written)
     }
 
-    fn cleanup(link: &std::path::Path) {
-        if let Err(e) = AlternativeDb::remove_renamed(&link) {
-            eprintln!("update-alternatives: could not remove {}.old: {}",
-                      link.display(), e);
+    fn write_synced(contents: String, path: &std::path::Path)
+    -> std::io::Result<usize> {
+        use std::io::Write;
+
+        filesystem::check_writable()?;
+
+        if let Some(parent) = path.parent().filter(|p| !p.exists()) {
+            filesystem::create_dir(parent)?;
         }
+
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+
+        Ok(contents.len())
     }
+}
 
-    fn recover(link: &std::path::Path) -> std::io::Result<()> {
-        if let Err(e) = AlternativeDb::recover_backup(link) {
-            eprintln!("update-alternatives: could not recover {}.old: {}",
-                      link.display(), e);
+// Another instance holding the commit lock is in the middle of a commit.
+fn recover(root: &std::path::Path) {
+    // Finishing a commit is left to the next instance that may write.
+    if filesystem::is_read_only() {
+        return;
+    }
 
-            return Err(e);
+    if let Some(_lock) = lock::try_acquire_commit(root) {
+        match AlternativeDb::finish_interrupted(root) {
+            Ok(true) => eprintln!("update-alternatives: cleaned up after an \
+                                  interrupted commit"),
+            Ok(false) => (),
+            Err(e) => error!(COMMIT, "could not clean up after \
+                                     an interrupted commit: {}", e),
         }
+    }
+}
 
-        Ok(())
+enum Entry {
+    Group(String, Box<AlternativeList>),
+    Foreign(std::path::PathBuf),
+    Skipped,
+}
+
+// Reads an entry of the database folder, telling groups from the entries of
+// other tools.
+fn read_entry(entry: &std::fs::DirEntry) -> Entry {
+    let path = entry.path();
+
+    // Staged entries are ours but not part of the database yet.
+    if is_staged(&path) {
+        return Entry::Skipped;
+    }
+
+    // Entries are always regular files, never symlinks.
+    if path.extension().is_none_or(|e| e != "json")
+       || !entry.file_type().is_ok_and(|t| t.is_file()) {
+        return Entry::Foreign(path);
     }
 
-    fn remove_renamed(link: &std::path::Path) -> std::io::Result<()> {
-        let to_remove = link.with_extension("json.old");
+    // Group names end up in messages, tables, and D-Bus replies, so
+    // unlike targets they must be UTF-8.
+    let name = String::from(match path.file_stem() {
+        Some(s) if s.to_str().is_none() => {
+            eprintln!("update-alternatives: skipping entry {}, whose \
+                      name is not valid UTF-8", path.display());
+
+            return Entry::Skipped;
+        },
+        Some(s) => s.to_string_lossy(),
+        None => {
+            println!("update-alternatives: skipping entry {}...",
+                     path.display());
 
-        if !to_remove.exists() {
-            return Ok(());
+            return Entry::Skipped;
+        },
+    });
+
+    let started = timings::start();
+    let contents = match filesystem::read(&path) {
+        Ok(c) => c,
+        Err(e) => {
+            error!(BAD_DATABASE, "could not read file {}: {}",
+                   path.display(), e);
+
+            return Entry::Skipped;
         }
+    };
 
-        std::fs::remove_file(to_remove)
-    }
+    let parsed = serde_json::from_str::<AlternativeList>(&contents);
+    let mut list = match parsed {
+        Ok(l) => l,
+        Err(e) => {
+            error!(BAD_DATABASE, "unable to \
+                                deserialize {}: {}", path.display(), e);
+
+            return Entry::Foreign(path);
+        }
+    };
 
-    fn recover_backup(link: &std::path::Path) -> std::io::Result<()> {
-        let renamed = link.with_extension("json");
+    let merged = list.dedup();
 
-        std::fs::rename(&renamed, link)
+    if merged > 0 {
+        eprintln!("update-alternatives: warning: merged {} duplicate \
+                  entries of {}", merged, name);
     }
+
+    timings::group("load", &name, started);
+
+    Entry::Group(name, Box::new(list))
 }
 
 fn estimate_size<I: std::iter::Iterator>(iter: &I) -> usize {
     let (lower_bound, upper_bound) = iter.size_hint();
 
-    upper_bound.unwrap_or_else(|| lower_bound)
+    upper_bound.unwrap_or(lower_bound)
+}
+
+fn is_staged(path: &std::path::Path) -> bool {
+    path.file_name().map(|n| n.to_string_lossy())
+        .is_some_and(|n| n.ends_with(".json.new") || n.ends_with(".json.old"))
+}
+
+// The names dpkg uses in /etc/alternatives: those of its master links, which
+// have an administrative file each, and those of their slave links, which are
+// listed in pairs with their paths after the mode and master link.
+fn dpkg_names(root: &std::path::Path) -> std::collections::HashSet<String> {
+    let mut names = std::collections::HashSet::new();
+    let admin_files = match filesystem::under(root, DPKG_ADMIN_DIR).read_dir() {
+        Ok(f) => f,
+        Err(_) => return names,
+    };
+
+    for admin_file in admin_files.filter_map(Result::ok) {
+        let name = admin_file.file_name().to_string_lossy().into_owned();
+        let contents = filesystem::read(admin_file.path()).unwrap_or_default();
+        let mut lines = contents.lines().skip(2);
+
+        while let Some(slave) = lines.next().filter(|l| !l.is_empty()) {
+            names.insert(slave.to_string());
+            lines.next();
+        }
+
+        names.insert(name);
+    }
+
+    names
 }
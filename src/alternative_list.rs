@@ -28,20 +28,272 @@ extern crate std;
 
 extern crate serde_json;
 
-use super::alternative::Alternative;
+use super::alternative::{Alternative, Follower, check_priority};
+use super::audit;
 use super::filesystem;
+use super::path_serde;
+use super::trash;
+use super::xdg;
+
+// What the link of a group is.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    // A symlink to the selected target.
+    #[default]
+    Symlink,
+    // A symlink to the selected shared library, after whose change the
+    // dynamic linker cache is rebuilt.
+    Library,
+    // A symlink to the selected systemd unit, such as display-manager.service
+    // to gdm.service, after whose change systemd reloads its units.
+    Unit,
+    // A mimeapps.list, in which the desktop file of the selected target is
+    // made the default application for the mime types.
+    Xdg{ mime_types: Vec<String> },
+}
+
+impl Kind {
+    pub fn is_symlink(&self) -> bool {
+        *self == Kind::Symlink
+    }
+}
+
+// How add derives the priority of a new alternative when none is given.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Weights {
+    // It is not; the priority must be given.
+    #[default]
+    Explicit,
+    // From the version in the path of the target or the one it reports.
+    Version,
+    // The same priority for every new alternative.
+    Fixed(i64),
+    // The given step above the highest priority of the group.
+    Step(i64),
+}
+
+impl Weights {
+    pub fn is_explicit(&self) -> bool {
+        *self == Weights::Explicit
+    }
+}
+
+impl std::fmt::Display for Weights {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Weights::Explicit => formatter.write_str("explicit"),
+            Weights::Version => formatter.write_str("version"),
+            Weights::Fixed(priority) => write!(formatter, "fixed:{}", priority),
+            Weights::Step(step) => write!(formatter, "step:{}", step),
+        }
+    }
+}
+
+// A target chosen by hand, which wins over the priorities until it expires.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Manual {
+    #[serde(with = "path_serde")]
+    target: std::path::PathBuf,
+    // Seconds since the epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    until: Option<u64>,
+    // The same end on the clock of the boot the selection was made in, which
+    // setting the wall clock does not move.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    boot: Option<BootDeadline>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct BootDeadline {
+    id: String,
+    // Seconds since the boot, including suspend.
+    uptime: u64,
+}
+
+// The kernel's name for this boot, and for how long it has been up.
+static BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+static UPTIME_PATH: &str = "/proc/uptime";
+
+// The current boot and its uptime in seconds. None where the kernel does not
+// tell, as on wasm32-wasi.
+fn boot_clock() -> Option<(&'static str, u64)> {
+    static ID: std::sync::OnceLock<Option<String>> = std::sync::OnceLock::new();
+
+    let id = ID.get_or_init(|| {
+        filesystem::read(BOOT_ID_PATH).ok().map(|i| i.trim().to_string())
+    }).as_deref()?;
+    let uptime = filesystem::read(UPTIME_PATH).ok()?;
+
+    Some((id, uptime.split(['.', ' ']).next()?.parse().ok()?))
+}
+
+impl Manual {
+    pub fn target(&self) -> &std::path::Path {
+        &self.target
+    }
+
+    pub fn until(&self) -> Option<std::time::SystemTime> {
+        self.until.map(|s| std::time::UNIX_EPOCH
+                           + std::time::Duration::from_secs(s))
+    }
+
+    // Within the boot the selection was made in, its time is measured by the
+    // boot clock, so that a wall clock set forward or back by NTP or by hand
+    // ends it neither early nor late. Only later boots go by the wall clock.
+    pub fn is_expired(&self, now: std::time::SystemTime) -> bool {
+        let until = match self.until() {
+            Some(u) => u,
+            None => return false,
+        };
+
+        match (&self.boot, boot_clock()) {
+            (Some(b), Some((id, uptime))) if b.id == id => uptime >= b.uptime,
+            _ => until <= now,
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct AlternativeList {
+    #[serde(with = "path_serde")]
     path: std::path::PathBuf,
     links: Vec<Alternative>,
+    // Removed with remove --archive; never selected, but kept for restore.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    archived: Vec<Alternative>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    manual: Option<Manual>,
+    #[serde(default, skip_serializing_if = "Kind::is_symlink")]
+    kind: Kind,
+    // Variables exported in profile.d, as templates of the selected target.
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    environment: std::collections::BTreeMap<String, String>,
+    // Categories such as toolchain, for acting on several groups at once.
+    #[serde(default, skip_serializing_if = "std::collections::BTreeSet::is_empty")]
+    tags: std::collections::BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Weights::is_explicit")]
+    weights: Weights,
+    #[serde(skip, default = "default_root")]
+    root: std::path::PathBuf,
+    // Follower links of removed alternatives that no other one has, which
+    // are removed with the next write.
+    #[serde(skip)]
+    orphans: Vec<Follower>,
+}
+
+fn default_root() -> std::path::PathBuf {
+    std::path::PathBuf::from("/")
 }
 
 impl AlternativeList {
     pub fn new<P: std::convert::AsRef<std::path::Path>>(path: P)
         -> AlternativeList {
         AlternativeList{ path: std::path::PathBuf::from(path.as_ref()),
-                         links: Vec::new() }
+                         links: Vec::new(), archived: Vec::new(),
+                         manual: None,
+                         kind: Kind::Symlink,
+                         environment: std::collections::BTreeMap::new(),
+                         tags: std::collections::BTreeSet::new(),
+                         weights: Weights::Explicit,
+                         root: default_root(), orphans: Vec::new() }
+    }
+
+    pub fn set_root<P: std::convert::AsRef<std::path::Path>>(&mut self,
+                                                             root: P) {
+        self.root = std::path::PathBuf::from(root.as_ref());
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    // The same alternatives and selection, managing another link. Follower
+    // links stay with this list, since no two lists may manage one.
+    pub fn copy_to<P: std::convert::AsRef<std::path::Path>>(&self, path: P)
+        -> AlternativeList {
+        let without_followers = |links: &[Alternative]| -> Vec<Alternative> {
+            links.iter()
+                 .map(|a| a.clone().with_followers(Vec::new()))
+                 .collect()
+        };
+
+        AlternativeList{ path: std::path::PathBuf::from(path.as_ref()),
+                         links: without_followers(&self.links),
+                         archived: without_followers(&self.archived),
+                         manual: self.manual.clone(),
+                         kind: self.kind.clone(),
+                         environment: self.environment.clone(),
+                         tags: self.tags.clone(),
+                         weights: self.weights,
+                         root: self.root.clone(), orphans: Vec::new() }
+    }
+
+    pub fn kind(&self) -> &Kind {
+        &self.kind
+    }
+
+    pub fn set_kind(&mut self, kind: Kind) {
+        self.kind = kind;
+    }
+
+    pub fn environment(&self) -> &std::collections::BTreeMap<String, String> {
+        &self.environment
+    }
+
+    // Sets or, with None, unsets variable; returns whether that changed it.
+    pub fn set_environment(&mut self, variable: &str,
+                           template: Option<&str>) -> bool {
+        match template {
+            Some(t) => self.environment.insert(variable.to_string(),
+                                               t.to_string())
+                                       .is_none_or(|old| old != t),
+            None => self.environment.remove(variable).is_some(),
+        }
+    }
+
+    pub fn tags(&self) -> &std::collections::BTreeSet<String> {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    // Adds or, with false, removes tag; returns whether that changed the
+    // tags.
+    pub fn set_tag(&mut self, tag: &str, tagged: bool) -> bool {
+        if tagged {
+            self.tags.insert(tag.to_string())
+        } else {
+            self.tags.remove(tag)
+        }
+    }
+
+    pub fn weights(&self) -> Weights {
+        self.weights
+    }
+
+    pub fn set_weights(&mut self, weights: Weights) {
+        self.weights = weights;
+    }
+
+    // The priority that the weight policy gives a new alternative, or None if
+    // it is given or derived from the version.
+    pub fn next_priority(&self) -> Option<Result<i64, String>> {
+        match self.weights {
+            Weights::Explicit | Weights::Version => None,
+            Weights::Fixed(priority) => Some(check_priority(priority)),
+            Weights::Step(step) => {
+                let highest = self.links.iter()
+                                        .map(|a| a.priority())
+                                        .max()
+                                        .unwrap_or(0);
+
+                Some(check_priority(highest.saturating_add(step)))
+            },
+        }
     }
 
     pub fn num_links(&self) -> usize {
@@ -49,63 +301,403 @@ impl AlternativeList {
     }
 
     pub fn current_target(&self) -> Option<&std::path::Path> {
-        self.links.iter().max_by_key(|l| l.priority()).map(|l| l.target())
+        self.selected().map(|l| l.target())
+    }
+
+    // The manual selection if it is still in effect, or else the best
+    // alternative.
+    pub fn selected(&self) -> Option<&Alternative> {
+        self.manual_alternative().or_else(|| self.best())
+    }
+
+    pub fn manual(&self) -> Option<&Manual> {
+        self.manual.as_ref()
+    }
+
+    // manual while a target selected by hand is in effect, auto otherwise.
+    pub fn mode(&self) -> &'static str {
+        if self.manual_alternative().is_some() { "manual" } else { "auto" }
+    }
+
+    // Selects target, which must be listed, by hand until the given time or
+    // for good. Returns whether anything changed.
+    pub fn select<P: std::convert::AsRef<std::path::Path>>(
+        &mut self, target: P, until: Option<std::time::SystemTime>
+    ) -> bool {
+        let boot = until.zip(boot_clock()).map(|(u, (id, uptime))| {
+            let left = u.duration_since(std::time::SystemTime::now())
+                        .map_or(0, |d| d.as_secs());
+
+            BootDeadline{ id: id.to_string(), uptime: uptime + left }
+        });
+        let until = until.map(|u| {
+            u.duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs())
+        });
+        let manual = Some(Manual{ target: target.as_ref().to_path_buf(),
+                                  until, boot });
+        let changed = self.manual != manual;
+
+        self.manual = manual;
+
+        changed
+    }
+
+    // Returns to automatic selection, and returns the manual one if there
+    // was one.
+    pub fn clear_manual(&mut self) -> Option<Manual> {
+        self.manual.take()
+    }
+
+    fn manual_alternative(&self) -> Option<&Alternative> {
+        let manual = self.manual.as_ref()
+                         .filter(|m| !m.is_expired(std::time::SystemTime::now()))?;
+
+        self.links.iter().find(|a| a.target() == manual.target())
+    }
+
+    pub fn best(&self) -> Option<&Alternative> {
+        self.links.iter().max_by_key(|l| l.priority())
+    }
+
+    // What a repair points the link at: the manual selection if it is still
+    // in effect, or else the best alternative whose target exists, so that a
+    // link is not left dangling while another target is there.
+    pub fn repaired(&self) -> Option<&Alternative> {
+        self.manual_alternative().or_else(|| {
+            self.links.iter()
+                      .filter(|l| filesystem::under(&self.root, l.target())
+                                              .exists())
+                      .max_by_key(|l| l.priority())
+                      .or_else(|| self.best())
+        })
+    }
+
+    // The target the link points to on disk, which may differ from the best
+    // alternative if the link was changed by hand.
+    pub fn linked_target(&self) -> Option<std::path::PathBuf> {
+        let link = filesystem::under(&self.root, &self.path);
+
+        match self.kind {
+            Kind::Symlink | Kind::Library | Kind::Unit => {
+                link.read_link().ok()
+            },
+            Kind::Xdg{ ref mime_types } => {
+                let id = xdg::default_application(&link, mime_types.first()?)?;
+
+                Some(self.links.iter()
+                               .map(|a| a.target())
+                               .find(|t| xdg::desktop_id(t) == Some(&id))
+                               .map(std::path::Path::to_path_buf)
+                               .unwrap_or_else(|| std::path::PathBuf::from(id)))
+            },
+        }
+    }
+
+    pub fn selection(&self, linked: Option<&std::path::Path>) -> &'static str {
+        match linked {
+            None => "no link",
+            Some(l) if Some(l) == self.manual_alternative()
+                                      .map(|a| a.target()) => "manual",
+            Some(l) if Some(l) == self.best().map(|a| a.target()) => "auto",
+            Some(l) if self.links.iter().any(|a| a.target() == l) => "manual",
+            Some(_) => "unregistered target",
+        }
+    }
+
+    // Why the target of alternative cannot be run, if it cannot.
+    pub fn problem(&self, alternative: &Alternative) -> Option<&'static str> {
+        let target = filesystem::under(&self.root, alternative.target());
+
+        match std::fs::metadata(&target) {
+            Err(_) => Some("missing"),
+            Ok(ref m) if !m.is_file() => Some("not a file"),
+            // Libraries, units, and desktop files are not run themselves.
+            Ok(_) if !self.kind.is_symlink() => None,
+            Ok(_) => match filesystem::mode(&target) {
+                Ok(mode) if mode & 0o111 == 0 => Some("not executable"),
+                _ => None,
+            },
+        }
     }
 
     pub fn links(&self) -> &[Alternative] {
         &self.links
     }
 
+    pub fn links_mut(&mut self) -> &mut [Alternative] {
+        &mut self.links
+    }
+
     pub fn make_symlink(&self) -> std::io::Result<bool> {
-        let (target, priority) = match self.links
-                                           .iter()
-                                           .max_by_key(|l| l.priority()) {
-            Some(l) => (l.target(), l.priority()),
-            None => return Ok(false),
-        };
+        let made = self.write_symlink()?;
 
-        if self.path.exists() {
-            if self.path.is_file() {
-                if let Ok(p) = self.path.read_link() {
-                    if p == target {
-                        return Ok(false);
-                    }
-                }
+        if made {
+            self.print_symlink();
+        }
+
+        Ok(made)
+    }
+
+    // As make_symlink, but to the repaired alternative.
+    pub fn repair_symlink(&self) -> std::io::Result<bool> {
+        let made = self.write_links(self.repaired())?;
+
+        if made {
+            self.print_links(self.repaired());
+        }
+
+        Ok(made)
+    }
+
+    // The follower links of the alternatives, each once and in order.
+    pub fn follower_links(&self) -> std::collections::BTreeSet<&std::path::Path> {
+        self.links.iter()
+                  .flat_map(|a| a.followers())
+                  .map(|f| f.link.as_path())
+                  .collect()
+    }
+
+    // Points the follower links at the paths the selected alternative gives
+    // them, and removes those it has none for, and those of removed
+    // alternatives, where they are symlinks. Returns whether any changed.
+    fn write_followers(&self, selected: Option<&Alternative>)
+    -> std::io::Result<bool> {
+        let mut changed = false;
+
+        for link in self.follower_links() {
+            let path = filesystem::under(&self.root, link);
+            let current = path.read_link().ok();
+
+            match selected.and_then(|a| a.follower(link)) {
+                Some(target) if current.as_deref() != Some(target) => {
+                    trash::displace(&self.root, link, &self.path, target)?;
+                    filesystem::replace_symlink(target, &path)?;
+                    changed = true;
+                },
+                Some(_) => (),
+                None if current.is_some() => {
+                    filesystem::remove(&path)?;
+                    changed = true;
+                },
+                None => (),
             }
+        }
 
-            if let Err(e) = filesystem::remove(&self.path) {
-                return Err(e);
+        for orphan in self.orphans.iter() {
+            let path = filesystem::under(&self.root, &orphan.link);
+
+            if path.read_link().ok().as_deref() == Some(orphan.target.as_path()) {
+                filesystem::remove(&path)?;
+                changed = true;
             }
         }
 
-        if let Err(e) = filesystem::symlink(target, &self.path) {
-            return Err(e);
+        Ok(changed)
+    }
+
+    // Points the link and its followers at the best target, without saying
+    // so. The followers go first, so that once the link has switched, they
+    // have as well.
+    pub fn write_symlink(&self) -> std::io::Result<bool> {
+        self.write_links(self.selected())
+    }
+
+    fn write_links(&self, selected: Option<&Alternative>)
+    -> std::io::Result<bool> {
+        let followers = self.write_followers(selected)?;
+
+        let target = match selected {
+            Some(l) => l.target(),
+            None => return Ok(followers),
+        };
+
+        let link = filesystem::under(&self.root, &self.path);
+
+        if let Kind::Xdg{ ref mime_types } = self.kind {
+            let id = xdg::desktop_id(target).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                                    format!("{} is not a desktop file",
+                                            target.display()))
+            })?;
+
+            return xdg::set_default_application(&link, mime_types, id);
+        }
+
+        if let Ok(p) = link.read_link() {
+            if p == target {
+                return Ok(followers);
+            }
         }
 
-        println!("update-alternatives: created symlink from {} to {} with \
-                 priority {}", self.path.display(), target.display(), priority);
+        trash::displace(&self.root, &self.path, &self.path, target)?;
+        filesystem::replace_symlink(target, &link)?;
+
         Ok(true)
     }
 
-    pub fn add_alternative(&mut self, to_add: Alternative) -> bool {
-        let target = to_add.target().to_path_buf();
+    pub fn print_symlink(&self) {
+        self.print_links(self.selected());
+    }
+
+    fn print_links(&self, selected: Option<&Alternative>) {
+        let selected = match selected {
+            Some(s) => s,
+            None => return,
+        };
+        let link = filesystem::under(&self.root, &self.path);
+
+        match self.kind {
+            Kind::Symlink | Kind::Library | Kind::Unit => {
+                println!("update-alternatives: created symlink from {} to {} \
+                         with priority {}", link.display(),
+                         selected.target().display(), selected.priority());
 
-        match self.links.iter().position(|a| a.target() == target) {
-            Some(i) => {
-                if self.links[i].priority() == to_add.priority() {
-                    return false;
+                for follower in selected.followers() {
+                    println!("update-alternatives: created symlink from {} to \
+                             {}, following {}",
+                             filesystem::under(&self.root, &follower.link)
+                                        .display(),
+                             follower.target.display(), self.path.display());
                 }
-                
-                self.links[i] = to_add;
+            },
+            Kind::Xdg{ ref mime_types } => {
+                println!("update-alternatives: made {} the default application \
+                         for {} in {} with priority {}",
+                         selected.target().display(), mime_types.join(", "),
+                         link.display(), selected.priority());
+            },
+        }
+    }
 
-                true
-            }
+    // Targets are unique within a list; adding one that is already there
+    // updates it in place, and adding an archived one forgets the archived.
+    // Follower links it no longer has, and no other alternative has either,
+    // are removed as for a removed alternative.
+    pub fn add_alternative(&mut self, to_add: Alternative) -> bool {
+        self.archived.retain(|a| a.target() != to_add.target());
+
+        let target = to_add.target().to_path_buf();
+        let existing = match self.links.iter_mut()
+                                       .find(|a| a.target() == target) {
+            Some(e) => e,
             None => {
                 self.links.push(to_add);
 
-                true
+                return true;
+            }
+        };
+        let previous = existing.followers().to_vec();
+        let changed = existing.update(to_add);
+        let kept = self.follower_links();
+        let orphans: Vec<Follower> = previous.into_iter()
+            .filter(|f| !kept.contains(f.link.as_path()))
+            .collect();
+
+        self.orphans.extend(orphans);
+
+        changed
+    }
+
+    // Merges entries for the same target, which lists written by hand or by
+    // older versions may contain, into the first of them. Later entries win,
+    // as they would have when added. Returns how many entries were merged.
+    pub fn dedup(&mut self) -> usize {
+        let links = std::mem::take(&mut self.links);
+        let count = links.len();
+
+        for alternative in links {
+            self.add_alternative(alternative);
+        }
+
+        count - self.links.len()
+    }
+
+    // Moves the alternative for old to new, keeping its priority and its place
+    // in the list. If new is listed already, the two are merged as adding it
+    // again would. Returns whether old was listed.
+    pub fn retarget(&mut self, old: &std::path::Path,
+                    new: &std::path::Path) -> bool {
+        let index = match self.links.iter().position(|a| a.target() == old) {
+            Some(i) => i,
+            None => return false,
+        };
+
+        if let Some(ref mut manual) = self.manual {
+            if manual.target == old {
+                manual.target = new.to_path_buf();
             }
         }
+
+        if self.links.iter().any(|a| a.target() == new) {
+            let mut moved = self.links.remove(index);
+            moved.set_target(new);
+            self.add_alternative(moved);
+        } else {
+            self.links[index].set_target(new);
+        }
+
+        true
+    }
+
+    // Gives the alternatives the priorities step, 2 * step, and so on, in
+    // the order of their current ones. Equal priorities stay equal, so that
+    // the same alternative wins. Returns the targets whose priority changed,
+    // with the old and new one, or None if the highest would be too high.
+    pub fn renumber(&mut self, step: i64)
+    -> Option<Vec<(std::path::PathBuf, i64, i64)>> {
+        let mut priorities: Vec<i64> = self.links.iter()
+                                                 .map(|a| a.priority())
+                                                 .collect();
+        priorities.sort();
+        priorities.dedup();
+
+        let highest = step.checked_mul(priorities.len() as i64)?;
+        check_priority(highest).ok()?;
+
+        let mut changed = Vec::new();
+
+        for alternative in self.links.iter_mut() {
+            let old = alternative.priority();
+            let rank = priorities.binary_search(&old).unwrap() as i64;
+            let new = step * (rank + 1);
+
+            if new != old {
+                alternative.set_priority(new);
+                changed.push((alternative.target().to_path_buf(), old, new));
+            }
+        }
+
+        Some(changed)
+    }
+
+    pub fn archived(&self) -> &[Alternative] {
+        &self.archived
+    }
+
+    // Removes the alternative for target like remove_alternative, but keeps
+    // it, with its priority and package, for restore_alternative.
+    pub fn archive_alternative(&mut self, target: &std::path::Path) -> bool {
+        let index = match self.links.iter().position(|a| a.target() == target) {
+            Some(i) => i,
+            None => return false,
+        };
+        let archived = self.links[index].clone();
+
+        self.remove_alternative(target);
+        self.archived.push(archived);
+
+        true
+    }
+
+    // Returns the archived alternative for target to the list, unless it was
+    // not archived.
+    pub fn restore_alternative(&mut self, target: &std::path::Path)
+    -> Option<&Alternative> {
+        let index = self.archived.iter().position(|a| a.target() == target)?;
+        let restored = self.archived.remove(index);
+
+        self.add_alternative(restored);
+        self.links.iter().find(|a| a.target() == target)
     }
 
     pub fn remove_alternative<P: std::convert::AsRef<std::path::Path>>(
@@ -116,7 +708,18 @@ impl AlternativeList {
         if let Some(p) = self.links
                              .iter()
                              .position(|a| a.target() == target_path) {
-            self.links.remove(p);
+            let removed = self.links.remove(p);
+            let kept = self.follower_links();
+            let orphans: Vec<Follower> = removed.followers().iter()
+                .filter(|f| !kept.contains(f.link.as_path()))
+                .cloned()
+                .collect();
+
+            self.orphans.extend(orphans);
+
+            if self.manual.as_ref().is_some_and(|m| m.target == target_path) {
+                self.manual = None;
+            }
 
             return true;
         }
@@ -127,17 +730,114 @@ impl AlternativeList {
 
 impl std::fmt::Display for AlternativeList {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if let Err(e) = writeln!(formatter, "alternatives for {}:",
-                                 self.path.display()) {
-            return Err(e);
-        }
+        let linked = self.linked_target();
+
+        let until = match self.manual.as_ref().and_then(|m| m.until()) {
+            Some(u) if self.manual_alternative().is_some() => {
+                format!(" until {}", audit::timestamp(u))
+            },
+            _ => String::new(),
+        };
+
+        writeln!(formatter, "alternatives for {} ({}{}):", self.path.display(),
+                 self.selection(linked.as_deref()), until)?;
 
         for alternative in self.links.iter() {
-            if let Err(e) = writeln!(formatter, "    {}", alternative) {
-                return Err(e);
+            let mark = if linked.as_deref() == Some(alternative.target()) {
+                '*'
+            } else {
+                ' '
+            };
+
+            match self.problem(alternative) {
+                Some(p) => writeln!(formatter, "  {} {} [{}]", mark,
+                                    alternative, p)?,
+                None => writeln!(formatter, "  {} {}", mark, alternative)?,
+            }
+
+            for follower in alternative.followers() {
+                writeln!(formatter, "      {} -> {}", follower.link.display(),
+                         follower.target.display())?;
             }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::AlternativeList;
+    use alternative::{Alternative, Follower};
+
+    fn follower(link: &str, target: &str) -> Follower {
+        Follower{ link: std::path::PathBuf::from(link),
+                  target: std::path::PathBuf::from(target) }
+    }
+
+    #[test]
+    fn adding_a_target_twice_keeps_one_entry() {
+        let mut list = AlternativeList::new("/usr/local/bin/cc");
+
+        assert!(list.add_alternative(Alternative::from_parts("/usr/bin/gcc",
+                                                             10)));
+        assert!(list.add_alternative(Alternative::from_parts("/usr/bin/gcc",
+                                                             20)));
+        assert_eq!(list.links().len(), 1);
+        assert_eq!(list.links()[0].priority(), 20);
+    }
+
+    #[test]
+    fn adding_the_same_entry_again_changes_nothing() {
+        let mut list = AlternativeList::new("/usr/local/bin/cc");
+        let mut gcc = Alternative::from_parts("/usr/bin/gcc", 10);
+        gcc.set_package(Some(String::from("gcc")));
+
+        assert!(list.add_alternative(gcc.clone()));
+        assert!(!list.add_alternative(gcc));
+        assert!(!list.add_alternative(Alternative::from_parts("/usr/bin/gcc",
+                                                              10)));
+        assert_eq!(list.links().len(), 1);
+        assert_eq!(list.links()[0].package(), Some("gcc"));
+    }
+
+    #[test]
+    fn adding_updates_the_package() {
+        let mut list = AlternativeList::new("/usr/local/bin/cc");
+        let mut gcc = Alternative::from_parts("/usr/bin/gcc", 10);
+
+        assert!(list.add_alternative(gcc.clone()));
+
+        gcc.set_package(Some(String::from("gcc-14")));
+
+        assert!(list.add_alternative(gcc));
+        assert_eq!(list.links()[0].package(), Some("gcc-14"));
+    }
+
+    #[test]
+    fn adding_an_archived_target_forgets_the_archived() {
+        let mut list = AlternativeList::new("/usr/local/bin/cc");
+
+        list.add_alternative(Alternative::from_parts("/usr/bin/gcc", 10));
+        assert!(list.archive_alternative("/usr/bin/gcc".as_ref()));
+        assert!(list.add_alternative(Alternative::from_parts("/usr/bin/gcc",
+                                                             30)));
+        assert!(list.archived().is_empty());
+        assert_eq!(list.links()[0].priority(), 30);
+    }
+
+    #[test]
+    fn replaced_followers_are_orphaned() {
+        let mut list = AlternativeList::new("/usr/local/bin/java");
+        let javac = follower("/usr/local/bin/javac", "/usr/bin/javac");
+        let jar = follower("/usr/local/bin/jar", "/usr/bin/jar");
+
+        list.add_alternative(Alternative::from_parts("/usr/bin/java", 1)
+                                 .with_followers(vec![javac.clone(),
+                                                      jar.clone()]));
+        assert!(list.add_alternative(Alternative::from_parts("/usr/bin/java",
+                                                             1)
+                                         .with_followers(vec![jar])));
+        assert!(list.orphans == vec![javac]);
+    }
+}
@@ -0,0 +1,489 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+// The manifests that apply reads: the groups a system should have, written by
+// hand or generated, and checked as a whole before anything is changed, so
+// that every problem in them is reported at once and where it is.
+
+extern crate std;
+
+extern crate serde_json;
+extern crate toml;
+
+use super::alternative::{self, Follower};
+use super::alternative_db::{self, AlternativeDb};
+
+// A manifest such as
+//
+//     [[group]]
+//     name = "java"
+//     selected = "/usr/lib/jvm/java-21/bin/java"
+//
+//     [[group.alternative]]
+//     target = "/usr/lib/jvm/java-21/bin/java"
+//     priority = 2100
+//     followers = { "/usr/local/bin/javac" = "/usr/lib/jvm/java-21/bin/javac" }
+pub struct Manifest {
+    pub groups: Vec<Group>,
+}
+
+pub struct Group {
+    pub name: String,
+    // The link of the group if it is new; the one add would make otherwise.
+    pub link: Option<std::path::PathBuf>,
+    // The alternative selected by hand, or None for automatic selection.
+    pub selected: Option<std::path::PathBuf>,
+    pub alternatives: Vec<Entry>,
+}
+
+pub struct Entry {
+    pub target: std::path::PathBuf,
+    pub priority: i64,
+    pub package: Option<String>,
+    // Replace the followers of the alternative unless empty, as with add.
+    pub followers: Vec<Follower>,
+}
+
+// Something wrong with a manifest, and where in it.
+pub struct Problem {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+type Table<'i> = toml::de::DeTable<'i>;
+type Value<'i> = toml::Spanned<toml::de::DeValue<'i>>;
+
+static MANIFEST_FIELDS: &[&str] = &["group"];
+static GROUP_FIELDS: &[&str] = &["name", "link", "selected", "alternative"];
+static ENTRY_FIELDS: &[&str] = &["target", "priority", "package", "followers"];
+
+// Reads a manifest, or returns all of its problems in the order they appear.
+pub fn parse(text: &str) -> Result<Manifest, Vec<Problem>> {
+    let (document, errors) = toml::de::DeTable::parse_recoverable(text);
+    let mut checker = Checker{ text, problems: Vec::new() };
+
+    for error in errors {
+        let at = error.span().map(|s| s.start).unwrap_or(0);
+
+        checker.report(at, error.message().trim_end());
+    }
+
+    let manifest = checker.manifest(document.get_ref());
+
+    if checker.problems.is_empty() {
+        return Ok(manifest);
+    }
+
+    let mut problems = checker.problems;
+    problems.sort_by_key(|&(at, _)| at);
+
+    Err(problems.into_iter()
+                .map(|(at, message)| {
+                    let (line, column) = location(text, at);
+
+                    Problem{ line, column, message }
+                })
+                .collect())
+}
+
+// The line and column of the byte offset at, both counted from 1.
+fn location(text: &str, at: usize) -> (usize, usize) {
+    let before = text.get(..at).unwrap_or(text);
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+    (before.matches('\n').count() + 1,
+     before[line_start..].chars().count() + 1)
+}
+
+// Collects problems by byte offset while the manifest is read.
+struct Checker<'a> {
+    text: &'a str,
+    problems: Vec<(usize, String)>,
+}
+
+impl<'a> Checker<'a> {
+    fn report<S: std::convert::Into<String>>(&mut self, at: usize,
+                                              message: S) {
+        self.problems.push((at, message.into()));
+    }
+
+    fn manifest(&mut self, document: &Table) -> Manifest {
+        self.known_fields(document, MANIFEST_FIELDS, "the manifest");
+
+        let mut groups: Vec<Group> = Vec::new();
+        let mut seen: Vec<(String, usize)> = Vec::new();
+
+        for (table, at) in self.tables(document, "group") {
+            let group = match self.group(table, at) {
+                Some(g) => g,
+                None => continue,
+            };
+
+            if let Some(&(_, first)) = seen.iter()
+                                           .find(|(n, _)| *n == group.name) {
+                let (line, _) = location(self.text, first);
+
+                self.report(at, format!("group {} is listed twice, first on \
+                                        line {}", group.name, line));
+                continue;
+            }
+
+            seen.push((group.name.clone(), at));
+            groups.push(group);
+        }
+
+        Manifest{ groups }
+    }
+
+    fn group(&mut self, table: &Table, at: usize) -> Option<Group> {
+        self.known_fields(table, GROUP_FIELDS, "a group");
+
+        let name = self.required(table, "name", at)
+                       .and_then(|v| self.string(v));
+        let link = table.get("link").and_then(|v| self.path(v));
+        let selected = table.get("selected").and_then(|v| self.path(v));
+        let mut alternatives: Vec<Entry> = Vec::new();
+        let mut complete = true;
+
+        if let (Some(name), Some(value)) = (&name, table.get("name")) {
+            if let Err(e) = alternative_db::check_name(name) {
+                self.report(value.span().start, e);
+            }
+        }
+
+        for (table, at) in self.tables(table, "alternative") {
+            let entry = match self.entry(table, at) {
+                Some(e) => e,
+                None => {
+                    complete = false;
+                    continue;
+                },
+            };
+
+            if alternatives.iter().any(|a| a.target == entry.target) {
+                self.report(at, format!("{} is listed twice",
+                                        entry.target.display()));
+                continue;
+            }
+
+            alternatives.push(entry);
+        }
+
+        // Only once every alternative could be read.
+        if let (Some(target), Some(value), true) = (&selected,
+                                                    table.get("selected"),
+                                                    complete) {
+            if !alternatives.iter().any(|a| a.target == *target) {
+                self.report(value.span().start, format!(
+                    "{} is not an alternative of the group", target.display()
+                ));
+            }
+        }
+
+        Some(Group{ name: name?, link, selected, alternatives })
+    }
+
+    fn entry(&mut self, table: &Table, at: usize) -> Option<Entry> {
+        self.known_fields(table, ENTRY_FIELDS, "an alternative");
+
+        let target = self.required(table, "target", at)
+                         .and_then(|v| self.path(v));
+        let priority = self.required(table, "priority", at)
+                           .and_then(|v| self.priority(v));
+        let package = table.get("package").and_then(|v| self.string(v));
+        let followers = table.get("followers")
+                             .map(|v| self.followers(v))
+                             .unwrap_or_default();
+
+        Some(Entry{ target: target?, priority: priority?, package, followers })
+    }
+
+    fn followers(&mut self, value: &Value) -> Vec<Follower> {
+        let links = match value.get_ref().as_table() {
+            Some(t) => t,
+            None => {
+                self.mistyped(value, "a table of links and paths");
+
+                return Vec::new();
+            },
+        };
+        let mut followers = Vec::new();
+
+        for (link, target) in links.iter() {
+            let link_path = std::path::PathBuf::from(link.get_ref().as_ref());
+
+            if !link_path.is_absolute() {
+                self.report(link.span().start, format!(
+                    "follower link {} must be an absolute path",
+                    link_path.display()
+                ));
+            }
+
+            if let Some(target) = self.path(target) {
+                followers.push(Follower{ link: link_path, target });
+            }
+        }
+
+        followers
+    }
+
+    // The tables of the array at key and where they are, reporting anything
+    // else found there.
+    fn tables<'t, 'i>(&mut self, table: &'t Table<'i>, key: &str)
+    -> Vec<(&'t Table<'i>, usize)> {
+        let value = match table.get(key) {
+            Some(v) => v,
+            None => return Vec::new(),
+        };
+        let array = match value.get_ref().as_array() {
+            Some(a) => a,
+            None => {
+                self.mistyped(value, "an array of tables");
+
+                return Vec::new();
+            },
+        };
+        let mut tables = Vec::new();
+
+        for item in array.iter() {
+            match item.get_ref().as_table() {
+                Some(t) => tables.push((t, item.span().start)),
+                None => self.mistyped(item, "a table"),
+            }
+        }
+
+        tables
+    }
+
+    fn known_fields(&mut self, table: &Table, known: &[&str], what: &str) {
+        for key in table.keys() {
+            if !known.contains(&key.get_ref().as_ref()) {
+                self.report(key.span().start, format!(
+                    "unknown field `{}` in {}, expected one of `{}`",
+                    key.get_ref(), what, known.join("`, `")
+                ));
+            }
+        }
+    }
+
+    // The value at key, or None after reporting that the table at at lacks
+    // it.
+    fn required<'t, 'i>(&mut self, table: &'t Table<'i>, key: &str,
+                        at: usize) -> Option<&'t Value<'i>> {
+        let value = table.get(key);
+
+        if value.is_none() {
+            self.report(at, format!("missing field `{}`", key));
+        }
+
+        value
+    }
+
+    fn string(&mut self, value: &Value) -> Option<String> {
+        let string = value.get_ref().as_str().map(String::from);
+
+        if string.is_none() {
+            self.mistyped(value, "a string");
+        }
+
+        string
+    }
+
+    fn path(&mut self, value: &Value) -> Option<std::path::PathBuf> {
+        let path = std::path::PathBuf::from(self.string(value)?);
+
+        if !path.is_absolute() {
+            self.report(value.span().start,
+                        format!("{} must be an absolute path",
+                                path.display()));
+
+            return None;
+        }
+
+        Some(path)
+    }
+
+    fn priority(&mut self, value: &Value) -> Option<i64> {
+        let parsed = value.get_ref().as_integer().map(|i| {
+            i64::from_str_radix(i.as_str(), i.radix())
+        });
+
+        let priority = match parsed {
+            Some(Ok(n)) => n,
+            Some(Err(_)) => {
+                self.report(value.span().start, "priority is out of range");
+
+                return None;
+            },
+            None => {
+                self.mistyped(value, "an integer");
+
+                return None;
+            },
+        };
+
+        match alternative::check_priority(priority) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                self.report(value.span().start, e);
+
+                None
+            },
+        }
+    }
+
+    fn mistyped(&mut self, value: &Value, expected: &str) {
+        self.report(value.span().start,
+                    format!("expected {}, found {}", expected,
+                            value.get_ref().type_str()));
+    }
+}
+
+// A manifest as export writes it, in the format parse reads.
+#[derive(Serialize)]
+struct Exported<'a> {
+    group: Vec<ExportedGroup<'a>>,
+}
+
+#[derive(Serialize)]
+struct ExportedGroup<'a> {
+    name: &'a str,
+    link: &'a std::path::Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    selected: Option<&'a std::path::Path>,
+    alternative: Vec<ExportedEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct ExportedEntry<'a> {
+    target: &'a std::path::Path,
+    priority: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<&'a str>,
+    #[serde(skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    followers: std::collections::BTreeMap<&'a std::path::Path,
+                                          &'a std::path::Path>,
+}
+
+// The groups of db, or those tagged tag, in name order, as a manifest that
+// apply makes another database match. Kinds, environments, tags and weight
+// policies are not part of manifests and are left out.
+pub fn export(db: &AlternativeDb, tag: Option<&str>)
+-> Result<String, toml::ser::Error> {
+    let tagged = db.iter().filter(|(_, list)| {
+        tag.is_none_or(|t| list.has_tag(t))
+    });
+    let mut groups: Vec<ExportedGroup> = tagged.map(|(name, list)| {
+        let selected = match list.mode() {
+            "manual" => list.selected().map(|a| a.target()),
+            _ => None,
+        };
+        let alternative = list.links().iter().map(|a| ExportedEntry{
+            target: a.target(),
+            priority: a.priority(),
+            package: a.package(),
+            followers: a.followers().iter()
+                        .map(|f| (f.link.as_path(), f.target.as_path()))
+                        .collect(),
+        }).collect();
+
+        ExportedGroup{ name, link: list.path(), selected, alternative }
+    }).collect();
+    groups.sort_by_key(|g| g.name);
+
+    toml::to_string(&Exported{ group: groups })
+}
+
+// A JSON Schema of manifests, for editors and linters that read TOML through
+// one, such as taplo.
+pub fn schema() -> serde_json::Value {
+    let path = serde_json::json!({ "type": "string", "pattern": "^/" });
+
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "update-alternatives manifest",
+        "description": "The groups of alternatives that \
+                        'update-alternatives apply' makes the database \
+                        match.",
+        "type": "object",
+        "additionalProperties": false,
+        "properties": {
+            "group": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "required": ["name"],
+                    "properties": {
+                        "name": {
+                            "type": "string",
+                            "pattern": "^[^/]+$",
+                            "description": "The name of the group",
+                        },
+                        "link": {
+                            "allOf": [path],
+                            "description": "The link of a new group",
+                        },
+                        "selected": {
+                            "allOf": [path],
+                            "description": "The target selected by hand; \
+                                            automatic selection if absent",
+                        },
+                        "alternative": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "additionalProperties": false,
+                                "required": ["target", "priority"],
+                                "properties": {
+                                    "target": path,
+                                    "priority": {
+                                        "type": "integer",
+                                        "minimum": alternative::MIN_PRIORITY,
+                                        "maximum": alternative::MAX_PRIORITY,
+                                    },
+                                    "package": { "type": "string" },
+                                    "followers": {
+                                        "type": "object",
+                                        "propertyNames": path,
+                                        "additionalProperties": path,
+                                    },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+        },
+    })
+}
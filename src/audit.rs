@@ -0,0 +1,277 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+#[cfg(unix)]
+extern crate nix;
+
+use std::convert::TryFrom;
+use std::io::Write;
+
+use super::filesystem;
+use super::lock;
+
+pub static LOG_PATH: &str = "/var/log/update-alternatives.log";
+
+// The number of the last entry and the latest time written to the log, which
+// outlast its rotation.
+pub static SEQUENCE_PATH: &str = "/var/lib/update-alternatives/sequence";
+
+pub struct AuditLog {
+    path: std::path::PathBuf,
+    user: String,
+    pending: Vec<String>,
+    sequence: std::path::PathBuf,
+}
+
+impl AuditLog {
+    pub fn new<P: std::convert::AsRef<std::path::Path>>(path: P) -> AuditLog {
+        AuditLog::for_user(path, invoking_user())
+    }
+
+    pub fn for_user<P: std::convert::AsRef<std::path::Path>>(path: P,
+                                                        user: String)
+    -> AuditLog {
+        AuditLog{ path: std::path::PathBuf::from(path.as_ref()), user,
+                  pending: Vec::new(),
+                  sequence: std::path::PathBuf::from(SEQUENCE_PATH) }
+    }
+
+    pub fn set_root<P: std::convert::AsRef<std::path::Path>>(&mut self,
+                                                          root: P) {
+        self.sequence = filesystem::under(root, SEQUENCE_PATH);
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    pub fn record<S: std::convert::Into<String>>(&mut self, message: S) {
+        self.pending.push(message.into());
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        // Nothing is written in read-only mode, and nothing happened either.
+        if self.pending.is_empty() || filesystem::is_read_only() {
+            self.pending.clear();
+
+            return Ok(());
+        }
+
+        let file = std::fs::OpenOptions::new().create(true)
+                                              .append(true)
+                                              .open(&self.path)?;
+
+        // Instances that change different groups flush at the same time, and
+        // would otherwise take the same numbers.
+        let mut file = lock::exclusive(file)?;
+        let now = std::time::SystemTime::now();
+        let (last, latest) = read_sequence(&self.sequence);
+
+        // Entries are ordered by their numbers, which only ever grow, so a
+        // clock set back does not reorder them.
+        if let Some(behind) = latest.and_then(|l| l.duration_since(now).ok())
+                                    .filter(|b| b.as_secs() > 0) {
+            eprintln!("update-alternatives: warning: the clock is {}s behind \
+                      the latest entry of the audit log", behind.as_secs());
+        }
+
+        let next = last + 1;
+        let count = self.pending.len() as u64;
+        let latest = latest.map_or(now, |l| l.max(now));
+
+        // Numbers are taken before they are used, so that none is used twice
+        // even if writing the entries fails.
+        if let Some(parent) = self.sequence.parent() {
+            if !parent.exists() {
+                filesystem::create_dir(parent)?;
+            }
+        }
+
+        filesystem::write_atomic(format!("{} {}\n", last + count,
+                                         timestamp(latest)),
+                                 &self.sequence)?;
+
+        let now = timestamp(now);
+
+        for (number, message) in (next..).zip(self.pending.drain(..)) {
+            writeln!(file, "{} #{} {}: {}", now, number, self.user, message)?;
+        }
+
+        Ok(())
+    }
+}
+
+// The number of the last entry and the latest time written, or 0 and None
+// before the first entry.
+fn read_sequence(path: &std::path::Path)
+-> (u64, Option<std::time::SystemTime>) {
+    let contents = filesystem::read(path).unwrap_or_default();
+    let mut fields = contents.split_whitespace();
+    let last = fields.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+
+    (last, fields.next().and_then(parse_timestamp))
+}
+
+// A line of the log, as written by flush.
+pub struct Entry<'a> {
+    pub time: &'a str,
+    // None for entries written before they were numbered.
+    pub sequence: Option<u64>,
+    pub user: &'a str,
+    // Without the interface, such as "dbus", that made the change.
+    pub message: &'a str,
+}
+
+pub fn parse_entry(line: &str) -> Option<Entry<'_>> {
+    let (time, rest) = line.split_once(' ')?;
+    let numbered = rest.split_once(' ').and_then(|(n, r)| {
+        Some((n.strip_prefix('#')?.parse::<u64>().ok()?, r))
+    });
+    let (sequence, rest) = match numbered {
+        Some((n, r)) => (Some(n), r),
+        None => (None, rest),
+    };
+    let (user, message) = rest.split_once(": ")?;
+    let message = ["dbus: ", "serve: "].iter()
+                                       .find_map(|p| message.strip_prefix(p))
+                                       .unwrap_or(message);
+
+    Some(Entry{ time, sequence, user, message })
+}
+
+// The entries of a log in the order they were numbered. Several writers
+// may append out of order; unnumbered entries are older than any numbered
+// one and keep their place in the file.
+pub fn entries(contents: &str) -> Vec<Entry<'_>> {
+    let mut entries: Vec<Entry> = contents.lines()
+                                          .filter_map(parse_entry)
+                                          .collect();
+
+    entries.sort_by_key(|e| e.sequence);
+    entries
+}
+
+pub fn invoking_user() -> String {
+    if let Ok(user) = std::env::var("SUDO_USER") {
+        if !user.is_empty() {
+            return user;
+        }
+    }
+
+    current_user()
+}
+
+#[cfg(unix)]
+fn current_user() -> String {
+    let uid = match std::env::var("PKEXEC_UID").ok()
+                                               .and_then(|u| u.parse().ok()) {
+        Some(u) => nix::unistd::Uid::from_raw(u),
+        None => nix::unistd::getuid(),
+    };
+
+    user_name(uid)
+}
+
+// Without unix users, as on wasm32-wasi, the environment is all there is.
+#[cfg(not(unix))]
+fn current_user() -> String {
+    std::env::var("USER").unwrap_or_else(|_| String::from("unknown"))
+}
+
+#[cfg(unix)]
+pub fn user_name(uid: nix::unistd::Uid) -> String {
+    match nix::unistd::User::from_uid(uid) {
+        Ok(Some(user)) => user.name,
+        _ => format!("uid {}", uid),
+    }
+}
+
+pub fn timestamp(time: std::time::SystemTime) -> String {
+    let seconds = time.duration_since(std::time::UNIX_EPOCH)
+                      .map(|d| d.as_secs())
+                      .unwrap_or(0);
+    let (days, rem) = (seconds / 86_400, seconds % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day,
+            rem / 3600, (rem % 3600) / 60, rem % 60)
+}
+
+// Reads a time written by timestamp, with or without its seconds, or a date
+// alone, which stands for its midnight. Times are in UTC.
+pub fn parse_timestamp(text: &str) -> Option<std::time::SystemTime> {
+    let (date, time) = match text.strip_suffix('Z') {
+        Some(t) => t.split_once('T')?,
+        None => (text, "00:00:00"),
+    };
+
+    let mut date = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+
+    let mut time = time.splitn(3, ':').map(|p| p.parse::<u64>().ok());
+    let (hour, minute) = (time.next()??, time.next()??);
+    let second = time.next().unwrap_or(Some(0))?;
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23
+       || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let days = u64::try_from(days_from_civil(year, month as u32,
+                                             day as u32)).ok()?;
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second;
+
+    Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds))
+}
+
+// Howard Hinnant's days-to-civil algorithm and its inverse, valid for the
+// proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
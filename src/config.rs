@@ -0,0 +1,217 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+extern crate nix;
+extern crate toml;
+
+use super::filesystem;
+
+pub static CONFIG_PATH: &str = "/etc/update-alternatives.toml";
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Like --read-only for every invocation.
+    pub read_only: bool,
+    // Like --strict for every invocation.
+    pub strict: bool,
+    pub snapper: Snapper,
+    pub switch: Switch,
+    pub events: Events,
+    pub probe: Probe,
+    pub retry: Retry,
+    pub link_dirs: LinkDirs,
+    // Named roots for --on, such as bullseye = "/srv/chroots/bullseye".
+    pub roots: std::collections::BTreeMap<String, std::path::PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LinkDirs {
+    // User and group names, or numeric IDs.
+    pub owner: String,
+    pub group: String,
+}
+
+impl Default for LinkDirs {
+    fn default() -> LinkDirs {
+        LinkDirs{ owner: String::from("root"), group: String::from("root") }
+    }
+}
+
+impl LinkDirs {
+    pub fn ids(&self) -> std::io::Result<(nix::unistd::Uid, nix::unistd::Gid)> {
+        let uid = match self.owner.parse() {
+            Ok(id) => nix::unistd::Uid::from_raw(id),
+            Err(_) => nix::unistd::User::from_name(&self.owner)?
+                .map(|u| u.uid)
+                .ok_or_else(|| unknown("user", &self.owner))?,
+        };
+        let gid = match self.group.parse() {
+            Ok(id) => nix::unistd::Gid::from_raw(id),
+            Err(_) => nix::unistd::Group::from_name(&self.group)?
+                .map(|g| g.gid)
+                .ok_or_else(|| unknown("group", &self.group))?,
+        };
+
+        Ok((uid, gid))
+    }
+}
+
+fn unknown(kind: &str, name: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::NotFound,
+                        format!("no {} named {}", kind, name))
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Retry {
+    pub attempts: u32,
+    // In seconds, before the first retry; doubled for every further one.
+    pub delay: f64,
+}
+
+impl Default for Retry {
+    fn default() -> Retry {
+        Retry{ attempts: 3, delay: 0.05 }
+    }
+}
+
+impl Retry {
+    pub fn delay(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64(self.delay.max(0.0))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Probe {
+    // In seconds.
+    pub timeout: f64,
+    // Arguments per group, instead of --version.
+    pub arguments: std::collections::HashMap<String, Vec<String>>,
+}
+
+impl Default for Probe {
+    fn default() -> Probe {
+        Probe{ timeout: 2.0, arguments: std::collections::HashMap::new() }
+    }
+}
+
+impl Probe {
+    pub fn arguments(&self, group: &str) -> Vec<String> {
+        self.arguments.get(group).cloned()
+            .unwrap_or_else(|| vec![String::from("--version")])
+    }
+
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64(self.timeout.max(0.0))
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Events {
+    pub file: Option<std::path::PathBuf>,
+    pub fd: Option<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Switch {
+    pub report_processes: bool,
+    // Runs mandb -q after a link in a man directory changed.
+    pub refresh_mandb: bool,
+    // Reminds to run hash -r after links were created.
+    pub hash_hint: bool,
+}
+
+impl Default for Switch {
+    fn default() -> Switch {
+        Switch{ report_processes: false, refresh_mandb: false,
+                hash_hint: true }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Snapper {
+    pub enabled: bool,
+    pub config: String,
+    pub cleanup_algorithm: String,
+}
+
+impl Default for Snapper {
+    fn default() -> Snapper {
+        Snapper{ enabled: false, config: String::from("root"),
+                 cleanup_algorithm: String::from("number") }
+    }
+}
+
+// How the configuration of a root was found.
+pub enum Origin {
+    File,
+    Missing,
+    Invalid(String),
+}
+
+// The configuration in effect for a root, and where it came from.
+pub struct Resolved {
+    pub path: std::path::PathBuf,
+    pub origin: Origin,
+    // The defaults unless origin is File.
+    pub config: Config,
+}
+
+pub fn resolve<P: std::convert::AsRef<std::path::Path>>(root: P) -> Resolved {
+    let path = filesystem::under(root, CONFIG_PATH);
+
+    let (origin, config) = match Config::load(&path) {
+        Ok(c) if path.exists() => (Origin::File, c),
+        Ok(c) => (Origin::Missing, c),
+        Err(e) => (Origin::Invalid(e.to_string()), Config::default()),
+    };
+
+    Resolved{ path, origin, config }
+}
+
+impl Config {
+    pub fn load<P: std::convert::AsRef<std::path::Path>>(path: P)
+    -> std::io::Result<Config> {
+        let contents = match filesystem::read(path) {
+            Ok(c) => c,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Config::default());
+            },
+            Err(e) => return Err(e),
+        };
+
+        toml::from_str(&contents).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+        })
+    }
+}
@@ -0,0 +1,318 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate nix;
+extern crate zbus;
+
+use self::zbus::blocking::{Connection, MessageIterator};
+use self::zbus::fdo;
+use self::zbus::message::{Header, Message, Type};
+use self::zbus::zvariant::Value;
+
+use super::alternative::Alternative;
+use super::alternative_db::{AlternativeDb, check_addition};
+use super::audit::{self, AuditLog};
+use super::freeze;
+use super::signals;
+
+pub static BUS_NAME: &str = "org.fthomys.UpdateAlternatives";
+
+pub static OBJECT_PATH: &str = "/org/fthomys/UpdateAlternatives";
+
+pub static INTERFACE: &str = "org.fthomys.UpdateAlternatives1";
+
+static INTROSPECTION: &str = r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
+<node>
+  <interface name="org.fthomys.UpdateAlternatives1">
+    <method name="ListGroups">
+      <arg name="names" type="as" direction="out"/>
+    </method>
+    <method name="GetGroup">
+      <arg name="name" type="s" direction="in"/>
+      <arg name="link" type="s" direction="out"/>
+      <arg name="candidates" type="a(sx)" direction="out"/>
+      <arg name="current" type="s" direction="out"/>
+    </method>
+    <method name="Add">
+      <arg name="name" type="s" direction="in"/>
+      <arg name="target" type="s" direction="in"/>
+      <arg name="priority" type="x" direction="in"/>
+      <arg name="changed" type="b" direction="out"/>
+    </method>
+    <method name="Remove">
+      <arg name="name" type="s" direction="in"/>
+      <arg name="target" type="s" direction="in"/>
+      <arg name="changed" type="b" direction="out"/>
+    </method>
+    <method name="SetCurrent">
+      <arg name="name" type="s" direction="in"/>
+      <arg name="target" type="s" direction="in"/>
+      <arg name="changed" type="b" direction="out"/>
+    </method>
+    <method name="Sync"/>
+    <signal name="GroupChanged">
+      <arg name="name" type="s"/>
+    </signal>
+  </interface>
+  <interface name="org.freedesktop.DBus.Introspectable">
+    <method name="Introspect">
+      <arg name="data" type="s" direction="out"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+pub fn run<P: std::convert::AsRef<std::path::Path>,
+           Q: std::convert::AsRef<std::path::Path>>(folder: P, log_path: Q)
+-> zbus::Result<()> {
+    let conn = Connection::system()?;
+    conn.request_name(BUS_NAME)?;
+
+    println!("update-alternatives: serving {} on the system bus", BUS_NAME);
+
+    for message in MessageIterator::from(&conn) {
+        let message = message?;
+        let header = message.header();
+
+        if header.message_type() != Type::MethodCall {
+            continue;
+        }
+
+        let interface = header.interface().map(|i| i.to_string());
+        let member = header.member().map(|m| m.to_string()).unwrap_or_default();
+
+        let result = match interface.as_deref() {
+            Some("org.freedesktop.DBus.Introspectable")
+                if member == "Introspect" => {
+                conn.reply(&header, &INTROSPECTION).map_err(fdo::Error::from)
+            },
+            Some(i) if i == INTERFACE => {
+                dispatch(&conn, folder.as_ref(), log_path.as_ref(), &message,
+                         &header, &member)
+            },
+            None => {
+                dispatch(&conn, folder.as_ref(), log_path.as_ref(), &message,
+                         &header, &member)
+            },
+            Some(i) => Err(fdo::Error::UnknownInterface(i.to_string())),
+        };
+
+        if let Err(e) = result {
+            if let Err(e) = conn.reply_dbus_error(&header, e) {
+                error!(SERVICE, "could not reply to {}: {}", member, e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn dispatch(conn: &Connection, folder: &std::path::Path,
+            log_path: &std::path::Path, message: &Message, header: &Header,
+            member: &str) -> fdo::Result<()> {
+    let body = message.body();
+
+    match member {
+        "ListGroups" => {
+            let db = load(folder)?;
+            let mut names: Vec<&String> = db.iter().map(|(n, _)| n).collect();
+            names.sort();
+
+            conn.reply(header, &names)?;
+        },
+        "GetGroup" => {
+            let name: String = body.deserialize()?;
+            let db = load(folder)?;
+            let list = db.alternatives(&name).ok_or_else(|| {
+                fdo::Error::InvalidArgs(format!("no alternatives for {}",
+                                                name))
+            })?;
+            let candidates: Vec<(String, i64)> = list
+                .links()
+                .iter()
+                .map(|a| (a.target().display().to_string(), a.priority()))
+                .collect();
+            let current = list.current_target()
+                              .map(|t| t.display().to_string())
+                              .unwrap_or_default();
+
+            conn.reply(header, &(list.path().display().to_string(),
+                                 candidates, current))?;
+        },
+        "Add" => {
+            let (name, target, priority): (String, String, i64) =
+                body.deserialize()?;
+
+            check_addition(&name, target.as_ref(), priority)
+                .map_err(|e| fdo::Error::InvalidArgs(e.to_string()))?;
+
+            let mut log = authorize(conn, header, log_path, "add")?;
+            let mut db = load(folder)?;
+
+            let changed = db.add_alternative(
+                &name, Alternative::from_parts(&target, priority)
+            ).map_err(|e| fdo::Error::InvalidArgs(e.to_string()))?;
+
+            if changed {
+                commit(&db)?;
+                log.record(format!("dbus: add {} {} {}", name, target,
+                                   priority));
+                finish(conn, &mut log, &[name]);
+            }
+
+            conn.reply(header, &changed)?;
+        },
+        "Remove" => {
+            let (name, target): (String, String) = body.deserialize()?;
+            let mut log = authorize(conn, header, log_path, "remove")?;
+            let mut db = load(folder)?;
+
+            let changed = db.remove_alternative(&name, &target);
+
+            if changed {
+                commit(&db)?;
+                log.record(format!("dbus: remove {} {}", name, target));
+                finish(conn, &mut log, &[name]);
+            }
+
+            conn.reply(header, &changed)?;
+        },
+        "SetCurrent" => {
+            let (name, target): (String, String) = body.deserialize()?;
+            let mut log = authorize(conn, header, log_path, "set-current")?;
+            let mut db = load(folder)?;
+
+            db.check_selection(&name, target.as_ref())
+              .map_err(|e| fdo::Error::InvalidArgs(e.to_string()))?;
+
+            let changed = db.select(&name, &target, None);
+
+            if changed {
+                commit(&db)?;
+                log.record(format!("dbus: set {} {}", name, target));
+                finish(conn, &mut log, &[name]);
+            }
+
+            conn.reply(header, &changed)?;
+        },
+        "Sync" => {
+            let mut log = authorize(conn, header, log_path, "sync")?;
+            let db = load(folder)?;
+
+            freeze::check(db.root())
+                .map_err(|e| fdo::Error::AccessDenied(e.to_string()))?;
+            let mut changed: Vec<String> = Vec::new();
+
+            for (name, list) in db.iter() {
+                match list.make_symlink() {
+                    Ok(true) => changed.push(name.clone()),
+                    Ok(false) => (),
+                    Err(e) => return Err(fdo::Error::IOError(format!(
+                        "could not write {}: {}", list.path().display(), e
+                    ))),
+                }
+            }
+
+            log.record("dbus: sync");
+            finish(conn, &mut log, &changed);
+
+            conn.reply(header, &())?;
+        },
+        _ => return Err(fdo::Error::UnknownMethod(member.to_string())),
+    }
+
+    Ok(())
+}
+
+fn load(folder: &std::path::Path) -> fdo::Result<AlternativeDb> {
+    AlternativeDb::from_folder(folder).map_err(|e| {
+        fdo::Error::IOError(format!("could not read {}: {}", folder.display(),
+                                    e))
+    })
+}
+
+fn commit(db: &AlternativeDb) -> fdo::Result<()> {
+    super::commit(db).map_err(|e| {
+        fdo::Error::Failed(format!("could not commit changes: {}", e))
+    })
+}
+
+// Checks the polkit action org.fthomys.update-alternatives.<action> for the
+// caller and returns an audit log attributed to the calling user.
+fn authorize(conn: &Connection, header: &Header, log_path: &std::path::Path,
+             action: &str) -> fdo::Result<AuditLog> {
+    let sender = header.sender().ok_or_else(|| {
+        fdo::Error::AccessDenied("method call has no sender".to_string())
+    })?;
+    let action_id = format!("org.fthomys.update-alternatives.{}", action);
+
+    let mut subject_details = std::collections::HashMap::new();
+    subject_details.insert("name", Value::from(sender.as_str()));
+
+    let reply = conn.call_method(
+        Some("org.freedesktop.PolicyKit1"),
+        "/org/freedesktop/PolicyKit1/Authority",
+        Some("org.freedesktop.PolicyKit1.Authority"),
+        "CheckAuthorization",
+        &(("system-bus-name", subject_details), action_id.as_str(),
+          std::collections::HashMap::<&str, &str>::new(), 1u32, ""),
+    )?;
+    let (authorized, _, _): (bool, bool,
+                             std::collections::HashMap<String, String>) =
+        reply.body().deserialize()?;
+
+    if !authorized {
+        return Err(fdo::Error::AccessDenied(format!("not authorized for {}",
+                                                    action_id)));
+    }
+
+    let uid: u32 = conn.call_method(
+        Some("org.freedesktop.DBus"), "/org/freedesktop/DBus",
+        Some("org.freedesktop.DBus"), "GetConnectionUnixUser",
+        &sender.as_str(),
+    )?.body().deserialize()?;
+
+    Ok(AuditLog::for_user(log_path,
+                          audit::user_name(nix::unistd::Uid::from_raw(uid))))
+}
+
+fn finish(conn: &Connection, log: &mut AuditLog, changed: &[String]) {
+    if let Err(e) = log.flush() {
+        error!(AUDIT, "could not write to audit log: {}", e);
+    }
+
+    for name in changed {
+        if let Err(e) = conn.emit_signal(None::<&str>, OBJECT_PATH, INTERFACE,
+                                         "GroupChanged", &name.as_str()) {
+            error!(SERVICE, "could not emit GroupChanged for {}: {}", name, e);
+        }
+    }
+
+    signals::exit_if_pending();
+}
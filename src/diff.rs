@@ -0,0 +1,160 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+use super::alternative_db::AlternativeDb;
+use super::alternative_list::AlternativeList;
+
+// A difference between two states of the database, such as two snapper
+// snapshots or a backup and the running system.
+pub enum Change<'a> {
+    GroupAdded{ name: &'a str, link: &'a std::path::Path },
+    GroupRemoved{ name: &'a str, link: &'a std::path::Path },
+    Added{ name: &'a str, target: &'a std::path::Path, priority: i64 },
+    Removed{ name: &'a str, target: &'a std::path::Path, priority: i64 },
+    Priority{ name: &'a str, target: &'a std::path::Path, old: i64,
+              new: i64 },
+    Selection{ name: &'a str, old: Selection<'a>, new: Selection<'a> },
+}
+
+#[derive(PartialEq)]
+pub struct Selection<'a> {
+    target: Option<&'a std::path::Path>,
+    manual: bool,
+}
+
+impl<'a> Selection<'a> {
+    fn of(list: &'a AlternativeList) -> Selection<'a> {
+        let target = list.current_target();
+        let manual = list.manual().is_some_and(|m| {
+            Some(m.target()) == target
+                && !m.is_expired(std::time::SystemTime::now())
+        });
+
+        Selection{ target, manual }
+    }
+}
+
+impl std::fmt::Display for Selection<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.target {
+            Some(t) => write!(f, "{} ({})", t.display(),
+                              if self.manual { "manual" } else { "auto" }),
+            None => write!(f, "nothing"),
+        }
+    }
+}
+
+impl std::fmt::Display for Change<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Change::GroupAdded{ name, link } => {
+                write!(f, "+ {}: new group, {}", name, link.display())
+            },
+            Change::GroupRemoved{ name, link } => {
+                write!(f, "- {}: removed group, {}", name, link.display())
+            },
+            Change::Added{ name, target, priority } => {
+                write!(f, "+ {}: {} priority {}", name, target.display(),
+                       priority)
+            },
+            Change::Removed{ name, target, priority } => {
+                write!(f, "- {}: {} priority {}", name, target.display(),
+                       priority)
+            },
+            Change::Priority{ name, target, old, new } => {
+                write!(f, "~ {}: {} priority {} → {}", name, target.display(),
+                       old, new)
+            },
+            Change::Selection{ name, ref old, ref new } => {
+                write!(f, "~ {}: selected {} → {}", name, old, new)
+            },
+        }
+    }
+}
+
+// What changed from old to new, by group name.
+pub fn diff<'a>(old: &'a AlternativeDb, new: &'a AlternativeDb)
+-> Vec<Change<'a>> {
+    let mut names: Vec<&String> = old.iter().chain(new.iter())
+                                     .map(|(n, _)| n)
+                                     .collect();
+    names.sort();
+    names.dedup();
+
+    let mut changes = Vec::new();
+
+    for name in names {
+        match (old.alternatives(name), new.alternatives(name)) {
+            (Some(list), None) => {
+                changes.push(Change::GroupRemoved{ name, link: list.path() });
+            },
+            (None, Some(list)) => {
+                changes.push(Change::GroupAdded{ name, link: list.path() });
+                changes.extend(list.links().iter().map(|a| Change::Added{
+                    name, target: a.target(), priority: a.priority(),
+                }));
+            },
+            (Some(before), Some(after)) => diff_group(name, before, after,
+                                                      &mut changes),
+            (None, None) => (),
+        }
+    }
+
+    changes
+}
+
+fn diff_group<'a>(name: &'a str, old: &'a AlternativeList,
+                  new: &'a AlternativeList, changes: &mut Vec<Change<'a>>) {
+    for before in old.links() {
+        match new.links().iter().find(|a| a.target() == before.target()) {
+            None => changes.push(Change::Removed{
+                name, target: before.target(), priority: before.priority(),
+            }),
+            Some(after) if after.priority() != before.priority() => {
+                changes.push(Change::Priority{
+                    name, target: before.target(), old: before.priority(),
+                    new: after.priority(),
+                });
+            },
+            Some(_) => (),
+        }
+    }
+
+    for after in new.links() {
+        if !old.links().iter().any(|a| a.target() == after.target()) {
+            changes.push(Change::Added{
+                name, target: after.target(), priority: after.priority(),
+            });
+        }
+    }
+
+    let (before, after) = (Selection::of(old), Selection::of(new));
+
+    if before != after {
+        changes.push(Change::Selection{ name, old: before, new: after });
+    }
+}
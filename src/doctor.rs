@@ -0,0 +1,271 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use super::alternative_db::{self, AlternativeDb};
+use super::filesystem;
+
+pub fn check_permissions<P: std::convert::AsRef<std::path::Path>>(
+    db: &AlternativeDb, folder: P
+) -> usize {
+    let folder_path = folder.as_ref();
+    let mut problems = flag_world_writable(folder_path);
+    let mut link_dirs: Vec<std::path::PathBuf> = Vec::new();
+
+    // Links and targets are stored as seen from inside the root.
+    for (name, list) in db.iter() {
+        let db_file = alternative_db::entry_path(folder_path, name);
+        problems += flag_world_writable(&db_file);
+
+        if let Some(parent) = list.path().parent() {
+            let parent = filesystem::under(db.root(), parent);
+
+            if !link_dirs.contains(&parent) {
+                link_dirs.push(parent);
+            }
+        }
+
+        for alternative in list.links() {
+            problems += flag_world_writable(
+                &filesystem::under(db.root(), alternative.target())
+            );
+        }
+    }
+
+    for dir in link_dirs {
+        problems += flag_world_writable(&dir);
+    }
+
+    problems
+}
+
+pub enum PathProblem {
+    Shadowed{ link: std::path::PathBuf, by: std::path::PathBuf,
+              source: String },
+    Missing{ dir: std::path::PathBuf, source: String },
+}
+
+// The PATH of systemd services and of most distributions' login shells.
+static DEFAULT_PATH: &str =
+    "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+impl PathProblem {
+    pub fn concerns(&self, link: &std::path::Path) -> bool {
+        match *self {
+            PathProblem::Shadowed{ link: ref l, .. } => l == link,
+            PathProblem::Missing{ ref dir, .. } => link.parent() == Some(dir),
+        }
+    }
+}
+
+impl std::fmt::Display for PathProblem {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            PathProblem::Shadowed{ ref link, ref by, ref source } => {
+                write!(formatter, "{} is shadowed by {} in PATH from {}",
+                       link.display(), by.display(), source)
+            },
+            PathProblem::Missing{ ref dir, ref source } => {
+                write!(formatter, "{} is not in PATH from {}", dir.display(),
+                       source)
+            },
+        }
+    }
+}
+
+// Flags links that a login shell would not find first: their directory is
+// missing from PATH, or an earlier PATH entry has an executable of the same
+// name. The PATH of the current process is ignored for an alternate root.
+pub fn check_path(db: &AlternativeDb) -> Vec<PathProblem> {
+    let mut problems = Vec::new();
+
+    for (source, dirs) in search_paths(db.root()) {
+        let mut missing: Vec<&std::path::Path> = Vec::new();
+
+        for (group, list) in db.iter() {
+            // Cross tools are only looked up by cross builds, which put their
+            // directory on PATH themselves.
+            if alternative_db::split_triplet(group).1.is_some()
+               || !list.kind().is_symlink() {
+                continue;
+            }
+
+            let (dir, name) = match (list.path().parent(),
+                                     list.path().file_name()) {
+                (Some(d), Some(n)) => (d, n),
+                _ => continue,
+            };
+
+            let position = match dirs.iter().position(|d| d == dir) {
+                Some(p) => p,
+                None => {
+                    if !missing.contains(&dir) {
+                        missing.push(dir);
+                        problems.push(PathProblem::Missing{
+                            dir: dir.to_path_buf(), source: source.clone()
+                        });
+                    }
+
+                    continue;
+                },
+            };
+
+            let shadow = dirs[..position].iter().map(|d| d.join(name)).find(|p| {
+                let candidate = filesystem::under(db.root(), p);
+
+                candidate.is_file() && filesystem::mode(&candidate)
+                                                  .is_ok_and(|m| m & 0o111 != 0)
+            });
+
+            if let Some(by) = shadow {
+                problems.push(PathProblem::Shadowed{
+                    link: list.path().to_path_buf(), by, source: source.clone()
+                });
+            }
+        }
+    }
+
+    problems
+}
+
+// Finds groups whose links end up pointing at each other, such as a target
+// of one group being the link of another. Each cycle is reported once, as the
+// names of its groups starting from the first in order and ending with it
+// again.
+pub fn check_cycles(db: &AlternativeDb) -> Vec<Vec<String>> {
+    let links: std::collections::HashMap<&std::path::Path, &str> =
+        db.iter().map(|(name, list)| (list.path(), name.as_str())).collect();
+
+    // The group whose link a group's target runs into first, if any.
+    let mut next: std::collections::BTreeMap<&str, &str> =
+        std::collections::BTreeMap::new();
+
+    for (name, list) in db.iter() {
+        let target = match list.current_target() {
+            Some(t) => t,
+            None => continue,
+        };
+
+        let hit = filesystem::symlink_chain(db.root(), target)
+            .iter()
+            .find_map(|p| links.get(p.as_path()).cloned());
+
+        if let Some(other) = hit {
+            next.insert(name.as_str(), other);
+        }
+    }
+
+    let mut cycles = Vec::new();
+
+    for &start in next.keys() {
+        let mut path = vec![start];
+        let mut current = start;
+
+        while let Some(&following) = next.get(current) {
+            if let Some(i) = path.iter().position(|&p| p == following) {
+                // Only report a cycle from its smallest member.
+                if i == 0 && path.iter().all(|&p| p >= start) {
+                    path.push(start);
+                    cycles.push(path.iter().map(|p| p.to_string()).collect());
+                }
+
+                break;
+            }
+
+            path.push(following);
+            current = following;
+        }
+    }
+
+    cycles
+}
+
+fn search_paths(root: &std::path::Path)
+-> Vec<(String, Vec<std::path::PathBuf>)> {
+    let mut found: Vec<(String, String)> = Vec::new();
+
+    if root == std::path::Path::new("/") {
+        if let Ok(path) = std::env::var("PATH") {
+            found.push((String::from("the environment"), path));
+        }
+    }
+
+    if let Ok(contents) = filesystem::read(filesystem::under(root,
+                                                             "/etc/environment")) {
+        for line in contents.lines() {
+            if let Some(value) = line.trim().strip_prefix("PATH=") {
+                found.push((String::from("/etc/environment"),
+                            value.trim_matches('"').to_string()));
+            }
+        }
+    }
+
+    if let Ok(contents) = filesystem::read(filesystem::under(root,
+                                                             "/etc/login.defs")) {
+        for line in contents.lines() {
+            let mut words = line.split_whitespace();
+
+            if words.next() != Some("ENV_PATH") {
+                continue;
+            }
+
+            if let Some(value) = words.next() {
+                found.push((String::from("ENV_PATH in /etc/login.defs"),
+                            value.trim_start_matches("PATH=").to_string()));
+            }
+        }
+    }
+
+    found.push((String::from("the default PATH"), String::from(DEFAULT_PATH)));
+
+    let mut paths: Vec<(String, Vec<std::path::PathBuf>)> = Vec::new();
+
+    for (source, value) in found {
+        let dirs: Vec<std::path::PathBuf> = value.split(':')
+            .filter(|d| !d.is_empty())
+            .map(std::path::PathBuf::from)
+            .collect();
+
+        if !paths.iter().any(|(_, d)| *d == dirs) {
+            paths.push((source, dirs));
+        }
+    }
+
+    paths
+}
+
+fn flag_world_writable(path: &std::path::Path) -> usize {
+    match filesystem::mode(path) {
+        Ok(mode) if mode & 0o002 != 0 => {
+            println!("update-alternatives: doctor: {} is world-writable \
+                     (mode {:04o})", path.display(), mode);
+
+            1
+        },
+        _ => 0,
+    }
+}
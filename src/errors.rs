@@ -0,0 +1,377 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+// A kind of error, identified by an id that stays the same across releases
+// and translations. Ids are never reused or renumbered; new kinds are added at
+// the end of CODES.
+#[derive(Debug)]
+pub struct Code {
+    pub id: &'static str,
+    pub summary: &'static str,
+    pub description: &'static str,
+    pub fixes: &'static [&'static str],
+}
+
+impl std::fmt::Display for Code {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str(self.id)
+    }
+}
+
+// An error for the interfaces that report errors elsewhere than to standard
+// error, such as JSON-RPC. Displayed the way error! prints it.
+#[derive(Debug)]
+pub struct Error {
+    pub code: &'static Code,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new<S: Into<String>>(code: &'static Code, message: S) -> Error {
+        Error{ code, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "error[{}]: {}", self.code.id, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Prints an error with the id of code, which is one of the statics below,
+// or an Error with the id of its own code:
+//
+//     error!(NOT_FOUND, "no alternatives found for {}", name);
+//     error!(e);
+#[macro_export]
+macro_rules! error {
+    ($code:ident, $($arg:tt)+) => {
+        eprintln!("update-alternatives: error[{}]: {}",
+                  $crate::errors::$code.id, format_args!($($arg)+))
+    };
+    ($error:expr) => {
+        eprintln!("update-alternatives: error[{}]: {}", $error.code.id,
+                  $error.message)
+    };
+}
+
+pub static NOT_FOUND: Code = Code{
+    id: "UA0001",
+    summary: "no such group",
+    description: "The group named on the command line has no alternatives in \
+                  the database. Group names are case-sensitive, and a group \
+                  that lost its last alternative is removed.",
+    fixes: &["list the groups with 'update-alternatives list'",
+             "look for archived alternatives with 'update-alternatives list \
+              NAME --archived'",
+             "check --root, which chooses the database"],
+};
+
+pub static NOT_MEMBER: Code = Code{
+    id: "UA0002",
+    summary: "not an alternative of the group",
+    description: "The target is not one of the alternatives of the group, so \
+                  it cannot be selected, removed or changed.",
+    fixes: &["show the alternatives with 'update-alternatives list NAME'",
+             "add the target first with 'update-alternatives add'",
+             "give the path the group records, not a symlink to it"],
+};
+
+pub static BAD_TARGET: Code = Code{
+    id: "UA0003",
+    summary: "target does not fit the group",
+    description: "The target is not of the kind the group links to: it must \
+                  be an absolute path inside the root, a desktop file for \
+                  groups of default applications, or a unit of the same type \
+                  for systemd unit groups.",
+    fixes: &["give the absolute path of the target",
+             "check the kind of the group with 'update-alternatives display \
+              NAME'"],
+};
+
+pub static LOOP: Code = Code{
+    id: "UA0004",
+    summary: "target resolves through the link",
+    description: "The target is the link of a group, or a path that resolves \
+                  through it, so selecting it would make the link point to \
+                  itself.",
+    fixes: &["give the real program, as 'readlink -f' prints it",
+             "follow the chain with 'update-alternatives display NAME \
+              --resolve'"],
+};
+
+pub static CONFLICT: Code = Code{
+    id: "UA0005",
+    summary: "link or group is taken",
+    description: "A group of that name exists already, another group \
+                  manages the link, or a file is in the way.",
+    fixes: &["find the owner of the link with 'update-alternatives status'",
+             "choose another name, or remove the other group first"],
+};
+
+pub static BAD_ARGUMENT: Code = Code{
+    id: "UA0006",
+    summary: "invalid argument",
+    description: "An argument is not valid: a name, path, priority, pattern, \
+                  column or variable that cannot be used as given.",
+    fixes: &["see 'update-alternatives help COMMAND' for the expected form",
+             "quote patterns, so that the shell does not expand them"],
+};
+
+pub static LOCKED: Code = Code{
+    id: "UA0007",
+    summary: "database is locked",
+    description: "The lock of the database could not be taken, usually \
+                  because another instance is changing it, or because the \
+                  lock file cannot be created.",
+    fixes: &["wait for the other instance to finish",
+             "check that /var/lib/update-alternatives exists and is \
+              writable"],
+};
+
+pub static BAD_DATABASE: Code = Code{
+    id: "UA0008",
+    summary: "database cannot be read",
+    description: "An entry of the database folder cannot be read or parsed. \
+                  It was probably edited by hand or truncated.",
+    fixes: &["run 'update-alternatives doctor' to find the entry",
+             "restore the entry from a backup, or remove it and add its \
+              alternatives again"],
+};
+
+pub static COMMIT: Code = Code{
+    id: "UA0009",
+    summary: "changes could not be committed",
+    description: "Writing the database or the links failed part way. The \
+                  journal lets the next run finish or undo the commit.",
+    fixes: &["check free space and permissions of the database folder and \
+              the link directories",
+             "run 'update-alternatives sync' to rewrite the links"],
+};
+
+pub static PRIVILEGES: Code = Code{
+    id: "UA0010",
+    summary: "not enough privileges",
+    description: "The command changes the system and must be run as root.",
+    fixes: &["run it as root, or without --no-escalate so that it asks \
+              pkexec or sudo",
+             "pass --root to work on a tree you own"],
+};
+
+pub static FROZEN: Code = Code{
+    id: "UA0011",
+    summary: "database is frozen",
+    description: "Someone froze the database, so every change is refused \
+                  until it is thawed.",
+    fixes: &["see who froze it and why with 'update-alternatives status'",
+             "thaw it with 'update-alternatives thaw' once that is safe"],
+};
+
+pub static MANIFEST: Code = Code{
+    id: "UA0012",
+    summary: "database does not match its manifest",
+    description: "The manifest records a checksum of every entry of the \
+                  database, and an entry was changed without updating it, or \
+                  the manifest is missing or cannot be read.",
+    fixes: &["see the differences with 'update-alternatives verify'",
+             "accept the current entries with 'update-alternatives verify \
+              --update'"],
+};
+
+pub static STRICT: Code = Code{
+    id: "UA0013",
+    summary: "strict mode refused the change",
+    description: "Strict mode refuses to commit a database with dangling \
+                  targets, conflicting links or other problems.",
+    fixes: &["fix the problems that 'update-alternatives doctor' lists",
+             "run without --strict, or unset strict in the configuration, \
+              if the problems are expected"],
+};
+
+pub static HOOK: Code = Code{
+    id: "UA0014",
+    summary: "a hook failed",
+    description: "A hook from the configuration or /etc/update-alternatives.d \
+                  exited with an error, or a hook could not be generated. A \
+                  failing pre-commit hook stops the commit.",
+    fixes: &["run the hook by hand to see its output",
+             "fix or remove the hook"],
+};
+
+pub static UNSUPPORTED: Code = Code{
+    id: "UA0015",
+    summary: "option not supported by this command",
+    description: "The command cannot honour an option it was given, such as \
+                  --root or --read-only.",
+    fixes: &["run the command without the option"],
+};
+
+pub static PRIORITY: Code = Code{
+    id: "UA0016",
+    summary: "no priority for the alternative",
+    description: "No priority was given, and none could be derived from the \
+                  weight policy of the group or from the version of the \
+                  target.",
+    fixes: &["give a priority",
+             "pass --weight-from-version with a target whose path or \
+              --version output has a version"],
+};
+
+pub static AUDIT: Code = Code{
+    id: "UA0017",
+    summary: "audit log cannot be written",
+    description: "The change was made, but could not be recorded in the \
+                  audit log.",
+    fixes: &["check free space and permissions of the log and its \
+              directory"],
+};
+
+pub static IO: Code = Code{
+    id: "UA0018",
+    summary: "input or output failed",
+    description: "A file other than the database could not be read or \
+                  written, or a system call failed.",
+    fixes: &["check that the path exists and is writable",
+             "check free space"],
+};
+
+pub static PROGRAM: Code = Code{
+    id: "UA0019",
+    summary: "an external program failed",
+    description: "A program run on behalf of the command, such as mandb, \
+                  ldconfig, systemctl, snapper or zenity, could not be run or \
+                  exited with an error.",
+    fixes: &["install the program, or check its own error message",
+             "run the program by hand to see its output"],
+};
+
+pub static SOURCE: Code = Code{
+    id: "UA0020",
+    summary: "no package manager or import source",
+    description: "The command needs a package manager or a version manager \
+                  to read from, and none was found or it could not be read.",
+    fixes: &["pass the location explicitly, such as --dir or \
+              --brew-prefix"],
+};
+
+pub static SERVICE: Code = Code{
+    id: "UA0021",
+    summary: "a service failed",
+    description: "A long running mode, such as watch, serve, the helper or \
+                  the D-Bus service, failed or could not serve a client.",
+    fixes: &["check the journal of the service",
+             "check that the socket or address is not in use"],
+};
+
+pub static CONFIG: Code = Code{
+    id: "UA0022",
+    summary: "configuration cannot be read",
+    description: "The configuration file, or a file it names such as the API \
+                  token, cannot be read or parsed.",
+    fixes: &["check the syntax of /etc/update-alternatives.toml",
+             "check that the files it names exist and are readable"],
+};
+
+pub static PROTOCOL: Code = Code{
+    id: "UA0023",
+    summary: "malformed request",
+    description: "A request to the JSON-RPC or HTTP interface is not valid \
+                  JSON, names an unknown method, or lacks parameters.",
+    fixes: &["see the README for the methods and their parameters"],
+};
+
+pub static DENIED: Code = Code{
+    id: "UA0024",
+    summary: "not authorized",
+    description: "The client is not allowed to make the change: polkit \
+                  refused it, or the bearer token is missing or wrong.",
+    fixes: &["check the polkit rules for the update-alternatives actions",
+             "send the token from the configuration"],
+};
+
+pub static NOT_RUNNABLE: Code = Code{
+    id: "UA0025",
+    summary: "target cannot be run",
+    description: "The selected target is not a program, could not be \
+                  started, or lies below --root, where programs are not run.",
+    fixes: &["check the selection with 'update-alternatives display NAME'"],
+};
+
+pub static PROBLEMS: Code = Code{
+    id: "UA0026",
+    summary: "checks found problems",
+    description: "A check over the database, or a command run on several \
+                  roots, found problems or failed somewhere; the lines before \
+                  this one list them.",
+    fixes: &["fix the problems listed; 'update-alternatives doctor' \
+              explains most of them"],
+};
+
+pub static NOT_A_ROOT: Code = Code{
+    id: "UA0027",
+    summary: "not a root",
+    description: "The path given as a root or snapshot is not a directory \
+                  holding a root file system or a database folder.",
+    fixes: &["check the path, and that the snapshot is mounted"],
+};
+
+pub static NOT_IN_TRASH: Code = Code{
+    id: "UA0028",
+    summary: "not in the trash",
+    description: "No file in the trash has the id given. Ids are shown by \
+                  'update-alternatives trash list', and a file leaves the \
+                  trash once it is restored.",
+    fixes: &["list the ids with 'update-alternatives trash list'"],
+};
+
+pub static BAD_MANIFEST: Code = Code{
+    id: "UA0029",
+    summary: "manifest to apply is not valid",
+    description: "The manifest given to apply is not valid TOML, has fields \
+                  it should not have or values of the wrong type, lists a \
+                  group or target twice, or gives a relative path. Every \
+                  problem is reported with its line and column.",
+    fixes: &["check it with 'update-alternatives apply --validate-only'",
+             "point your editor at the output of 'update-alternatives \
+              schema'"],
+};
+
+// Every code, in the order of their ids.
+pub static CODES: &[&Code] = &[
+    &NOT_FOUND, &NOT_MEMBER, &BAD_TARGET, &LOOP, &CONFLICT, &BAD_ARGUMENT,
+    &LOCKED, &BAD_DATABASE, &COMMIT, &PRIVILEGES, &FROZEN, &MANIFEST, &STRICT,
+    &HOOK, &UNSUPPORTED, &PRIORITY, &AUDIT, &IO, &PROGRAM, &SOURCE, &SERVICE,
+    &CONFIG, &PROTOCOL, &DENIED, &NOT_RUNNABLE, &PROBLEMS, &NOT_A_ROOT,
+    &NOT_IN_TRASH, &BAD_MANIFEST,
+];
+
+// The code with the given id, in any case.
+pub fn lookup(id: &str) -> Option<&'static Code> {
+    CODES.iter().find(|c| c.id.eq_ignore_ascii_case(id)).copied()
+}
@@ -0,0 +1,149 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+extern crate serde_json;
+
+use std::io::Write;
+
+use super::alternative::Alternative;
+use super::audit;
+use super::config;
+use super::errors;
+use super::filesystem;
+use super::path_serde;
+
+// Process-wide, since errors are reported from every part of the crate.
+static SINK: std::sync::Mutex<Option<std::fs::File>> =
+    std::sync::Mutex::new(None);
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    GroupChanged {
+        group: &'a str,
+        #[serde(serialize_with = "path_serde::serialize")]
+        link: &'a std::path::Path,
+        alternatives: &'a [Alternative],
+    },
+    LinkWritten {
+        group: &'a str,
+        #[serde(serialize_with = "path_serde::serialize")]
+        link: &'a std::path::Path,
+        #[serde(serialize_with = "path_serde::serialize_option")]
+        old: Option<&'a std::path::Path>,
+        #[serde(serialize_with = "path_serde::serialize")]
+        new: &'a std::path::Path,
+    },
+    LinkRepaired {
+        group: &'a str,
+        #[serde(serialize_with = "path_serde::serialize")]
+        link: &'a std::path::Path,
+        #[serde(serialize_with = "path_serde::serialize_option")]
+        old: Option<&'a std::path::Path>,
+        #[serde(serialize_with = "path_serde::serialize")]
+        new: &'a std::path::Path,
+    },
+    Error {
+        id: &'a str,
+        message: &'a str,
+    },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    time: String,
+    user: String,
+    #[serde(flatten)]
+    event: Event<'a>,
+}
+
+// Opens the configured event file, or adopts the configured descriptor.
+pub fn open<P: std::convert::AsRef<std::path::Path>>(root: P,
+                                                    settings: &config::Events)
+-> std::io::Result<()> {
+    let file = if let Some(ref path) = settings.file {
+        std::fs::OpenOptions::new().create(true)
+                                   .append(true)
+                                   .open(filesystem::under(root, path))?
+    } else if let Some(fd) = settings.fd {
+        use std::os::unix::io::FromRawFd;
+
+        // Standard input, output and error belong to the process, and closing
+        // one of them with the stream would let the next file opened take
+        // its place.
+        if fd < 3 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("fd {} is standard input, output or error", fd)
+            ));
+        }
+
+        // Only a descriptor that is open is taken over. F_GETFD asks the
+        // kernel without assuming it is.
+        if unsafe { nix::libc::fcntl(fd, nix::libc::F_GETFD) } == -1 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("fd {} is not open: {}", fd,
+                        std::io::Error::last_os_error())
+            ));
+        }
+
+        // The descriptor is inherited from the parent, which set fd in the
+        // configuration for exactly this purpose; nothing else in the process
+        // owns it.
+        unsafe { std::fs::File::from_raw_fd(fd) }
+    } else {
+        return Ok(());
+    };
+
+    *SINK.lock().unwrap_or_else(|e| e.into_inner()) = Some(file);
+
+    Ok(())
+}
+
+pub fn emit(event: Event) {
+    let mut sink = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    let file = match *sink {
+        Some(ref mut f) => f,
+        None => return,
+    };
+
+    let record = Record{ time: audit::timestamp(std::time::SystemTime::now()),
+                         user: audit::invoking_user(), event };
+
+    let result = serde_json::to_string(&record)
+        .map_err(std::io::Error::from)
+        .and_then(|line| writeln!(file, "{}", line));
+
+    if let Err(e) = result {
+        error!(IO, "could not write event: {}", e);
+    }
+}
+
+pub fn error(code: &errors::Code, message: &str) {
+    emit(Event::Error{ id: code.id, message });
+}
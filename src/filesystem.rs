@@ -26,18 +26,93 @@
 
 extern crate std;
 
+#[cfg(unix)]
+extern crate nix;
+
 use std::io::{Read, Write};
 
+// How often an operation that failed with a transient error is tried again,
+// and how long to wait before the first retry. The wait doubles every time.
+static RETRY: std::sync::Mutex<(u32, std::time::Duration)> =
+    std::sync::Mutex::new((3, std::time::Duration::from_millis(50)));
+
+pub fn set_retry(attempts: u32, delay: std::time::Duration) {
+    *RETRY.lock().unwrap_or_else(|e| e.into_inner()) = (attempts, delay);
+}
+
+// Set for --read-only, after which every function here that would write
+// fails instead.
+static READ_ONLY: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+
+pub fn set_read_only() {
+    READ_ONLY.store(true, std::sync::atomic::Ordering::SeqCst);
+}
+
+// The core never writes on wasm32-wasi, where it is embedded to read.
+pub fn is_read_only() -> bool {
+    cfg!(target_os = "wasi")
+        || READ_ONLY.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+// Fails in read-only mode, before anything is written.
+pub fn check_writable() -> std::io::Result<()> {
+    if is_read_only() {
+        return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied,
+                                       "nothing is written in read-only mode"));
+    }
+
+    Ok(())
+}
+
+// Network and overlay file systems occasionally fail operations that succeed
+// when tried again.
+#[cfg(unix)]
+fn is_transient(error: &std::io::Error) -> bool {
+    matches!(error.raw_os_error().map(nix::errno::Errno::from_raw),
+             Some(nix::errno::Errno::EAGAIN) | Some(nix::errno::Errno::ESTALE)
+             | Some(nix::errno::Errno::EINTR))
+}
+
+#[cfg(not(unix))]
+fn is_transient(_error: &std::io::Error) -> bool {
+    false
+}
+
+fn retry<T, F: FnMut() -> std::io::Result<T>>(mut operation: F)
+-> std::io::Result<T> {
+    let (attempts, mut delay) = *RETRY.lock().unwrap_or_else(|e| e.into_inner());
+    let mut failed = 0;
+
+    loop {
+        match operation() {
+            Err(ref e) if failed < attempts && is_transient(e) => {
+                failed += 1;
+                std::thread::sleep(delay);
+                delay *= 2;
+            },
+            result => return result,
+        }
+    }
+}
+
 pub fn remove<P: std::convert::AsRef<std::path::Path>>(path: P)
 -> std::io::Result<()> {
+    check_writable()?;
+
     let concrete: &std::path::Path = path.as_ref();
+    let metadata = match concrete.symlink_metadata() {
+        Ok(m) => m,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
 
-    if !concrete.exists() {
-        Ok(())
+    if metadata.file_type().is_symlink() {
+        retry(|| std::fs::remove_file(concrete))
     } else if concrete.is_dir() {
-        std::fs::remove_dir_all(concrete)
+        retry(|| std::fs::remove_dir_all(concrete))
     } else if concrete.is_file() {
-        std::fs::remove_file(concrete)
+        retry(|| std::fs::remove_file(concrete))
     } else {
         Err(std::io::Error::new(
             std::io::ErrorKind::AlreadyExists,
@@ -51,7 +126,8 @@ pub fn symlink<P: std::convert::AsRef<std::path::Path>,
                Q: std::convert::AsRef<std::path::Path>>(
     source: P, destination: Q
 ) -> std::io::Result<()> {
-    std::os::unix::fs::symlink(source, destination)
+    check_writable()?;
+    retry(|| std::os::unix::fs::symlink(source.as_ref(), destination.as_ref()))
 }
 
 #[cfg(windows)]
@@ -59,15 +135,176 @@ pub fn symlink<P: std::convert::AsRef<std::path::Path>,
                Q: std::convert::AsRef<std::path::Path>>(
     source: P, destination: Q
 ) -> std::io::Result<()> {
-    std::os::windows::fs::symlink_file(source, destination)
+    check_writable()?;
+    retry(|| std::os::windows::fs::symlink_file(source.as_ref(),
+                                                destination.as_ref()))
+}
+
+#[cfg(target_os = "wasi")]
+pub fn symlink<P: std::convert::AsRef<std::path::Path>,
+               Q: std::convert::AsRef<std::path::Path>>(
+    _source: P, _destination: Q
+) -> std::io::Result<()> {
+    check_writable()
+}
+
+pub fn rename<P: std::convert::AsRef<std::path::Path>,
+              Q: std::convert::AsRef<std::path::Path>>(from: P, to: Q)
+-> std::io::Result<()> {
+    check_writable()?;
+    retry(|| std::fs::rename(from.as_ref(), to.as_ref()))
+}
+
+// A rename that cannot work in this layout, because the destination is a
+// mount point of its own, such as a bind-mounted file, or on another file
+// system.
+#[cfg(unix)]
+fn is_cross_device(error: &std::io::Error) -> bool {
+    matches!(error.raw_os_error().map(nix::errno::Errno::from_raw),
+             Some(nix::errno::Errno::EXDEV) | Some(nix::errno::Errno::EBUSY))
+}
+
+#[cfg(not(unix))]
+fn is_cross_device(_error: &std::io::Error) -> bool {
+    false
+}
+
+// A hidden name in the directory of path for a temporary to be renamed over
+// it, so that both are on the same file system.
+fn sibling_temp(path: &std::path::Path) -> std::path::PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned())
+                   .unwrap_or_default();
+
+    path.with_file_name(format!(".{}.update-alternatives-{}", name,
+                                std::process::id()))
+}
+
+// Moves from over to, falling back to copying the contents into to and
+// syncing them where a rename cannot work.
+pub fn replace_file<P: std::convert::AsRef<std::path::Path>,
+                    Q: std::convert::AsRef<std::path::Path>>(from: P, to: Q)
+-> std::io::Result<()> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+
+    match rename(from, to) {
+        Err(ref e) if is_cross_device(e) => {
+            std::fs::copy(from, to)?;
+            std::fs::OpenOptions::new().write(true).open(to)?.sync_all()?;
+
+            std::fs::remove_file(from)
+        },
+        result => result,
+    }
+}
+
+// Moves the file, directory, or symlink from to the path to, which must not
+// exist, copying it and removing the original where a rename cannot work.
+pub fn move_tree<P: std::convert::AsRef<std::path::Path>,
+                 Q: std::convert::AsRef<std::path::Path>>(from: P, to: Q)
+-> std::io::Result<()> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+
+    match rename(from, to) {
+        Err(ref e) if is_cross_device(e) => {
+            copy_tree(from, to)?;
+            remove(from)
+        },
+        result => result,
+    }
+}
+
+fn copy_tree(from: &std::path::Path, to: &std::path::Path)
+-> std::io::Result<()> {
+    let metadata = from.symlink_metadata()?;
+
+    if metadata.file_type().is_symlink() {
+        symlink(from.read_link()?, to)
+    } else if metadata.is_dir() {
+        std::fs::create_dir(to)?;
+
+        for child in std::fs::read_dir(from)? {
+            let child = child?;
+
+            copy_tree(&child.path(), &to.join(child.file_name()))?;
+        }
+
+        std::fs::set_permissions(to, metadata.permissions())
+    } else {
+        std::fs::copy(from, to)?;
+        std::fs::OpenOptions::new().write(true).open(to)?.sync_all()
+    }
+}
+
+// Writes contents to a temporary next to path and moves it over path, so
+// that path never holds only part of them.
+pub fn write_atomic<S, P: std::convert::AsRef<std::path::Path>>(contents: S,
+                                                                path: P)
+-> std::io::Result<usize> where String: std::convert::From<S> {
+    check_writable()?;
+
+    let path = path.as_ref();
+    let temp = sibling_temp(path);
+    let to_write = String::from(contents);
+
+    let written = std::fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(to_write.as_bytes())?;
+        file.sync_all()
+    }).and_then(|_| replace_file(&temp, path));
+
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+
+    written.map(|_| to_write.len())
+}
+
+// Points link at target without a moment in which link is missing, by
+// renaming a symlink created next to it over it. A directory at link is
+// removed first, and where a rename cannot work the link is replaced in two
+// steps instead.
+pub fn replace_symlink<P: std::convert::AsRef<std::path::Path>,
+                       Q: std::convert::AsRef<std::path::Path>>(target: P,
+                                                                link: Q)
+-> std::io::Result<()> {
+    let (target, link) = (target.as_ref(), link.as_ref());
+
+    if link.symlink_metadata().is_ok_and(|m| m.is_dir()) {
+        remove(link)?;
+    }
+
+    let temp = sibling_temp(link);
+    remove(&temp)?;
+    symlink(target, &temp)?;
+
+    match rename(&temp, link) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp);
+
+            if !is_cross_device(&e) {
+                return Err(e);
+            }
+
+            remove(link)?;
+            symlink(target, link)
+        },
+    }
+}
+
+// Maps an absolute path onto the same path below root. A .. that would climb
+// above root stays at root, as it does at / on the running system, so that a
+// stored path such as /../../etc/passwd cannot leave it.
+pub fn under<P: std::convert::AsRef<std::path::Path>,
+             Q: std::convert::AsRef<std::path::Path>>(root: P, path: Q)
+-> std::path::PathBuf {
+    let path = normalize(path.as_ref().to_path_buf());
+
+    root.as_ref().join(path.strip_prefix("/").unwrap_or(&path))
 }
 
 pub fn read<P: std::convert::AsRef<std::path::Path>>(path: P)
 -> std::io::Result<String> {
-    let mut file = match std::fs::File::open(path) {
-        Ok(f) => f,
-        Err(e) => return Err(e),
-    };
+    let mut file = std::fs::File::open(path)?;
 
     let mut buffer = String::new();
 
@@ -77,23 +314,222 @@ pub fn read<P: std::convert::AsRef<std::path::Path>>(path: P)
     }
 }
 
+// Whether the effective user may write to path, or to the closest existing
+// directory above it, without touching either.
+pub fn is_writable<P: std::convert::AsRef<std::path::Path>>(path: P) -> bool {
+    let mut dir = path.as_ref();
+
+    while !dir.exists() {
+        dir = match dir.parent() {
+            Some(p) => p,
+            None => return false,
+        };
+    }
+
+    may_write(dir)
+}
+
+#[cfg(unix)]
+fn may_write(path: &std::path::Path) -> bool {
+    nix::unistd::faccessat(nix::fcntl::AT_FDCWD, path,
+                           nix::unistd::AccessFlags::W_OK,
+                           nix::fcntl::AtFlags::AT_EACCESS).is_ok()
+}
+
+#[cfg(not(unix))]
+fn may_write(path: &std::path::Path) -> bool {
+    std::fs::metadata(path).map(|m| !m.permissions().readonly())
+                           .unwrap_or(false)
+}
+
 pub fn create_dir<P: std::convert::AsRef<std::path::Path>>(path: P)
 -> std::io::Result<()> {
-    std::fs::create_dir_all(path)
+    check_writable()?;
+    std::fs::create_dir_all(path.as_ref())?;
+
+    set_mode(path, 0o755)
+}
+
+#[cfg(unix)]
+pub fn set_mode<P: std::convert::AsRef<std::path::Path>>(path: P, mode: u32)
+-> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    check_writable()?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+pub fn set_mode<P: std::convert::AsRef<std::path::Path>>(_path: P, _mode: u32)
+-> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn mode<P: std::convert::AsRef<std::path::Path>>(path: P)
+-> std::io::Result<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Ok(std::fs::metadata(path)?.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+pub fn mode<P: std::convert::AsRef<std::path::Path>>(path: P)
+-> std::io::Result<u32> {
+    let readonly = std::fs::metadata(path)?.permissions().readonly();
+
+    Ok(if readonly { 0o444 } else { 0o644 })
 }
 
 pub fn write<S, P: std::convert::AsRef<std::path::Path>>(contents: S, path: P)
 -> std::io::Result<usize> where String: std::convert::From<S> {
+    check_writable()?;
+
     let to_write = String::from(contents);
     let len = to_write.len();
 
-    let mut file = match std::fs::File::create(path) {
-        Ok(f) => f,
-        Err(e) => return Err(e),
-    };
+    let mut file = std::fs::File::create(path)?;
 
     match file.write_all(to_write.as_bytes()) {
         Ok(_) => Ok(len),
         Err(e) => Err(e),
     }
 }
+
+// The limits Linux puts on paths, including their terminating NUL byte, and on
+// each of their components. Longer ones fail with ENAMETOOLONG.
+pub const PATH_MAX: usize = 4096;
+pub const NAME_MAX: usize = 255;
+
+// Rejects paths the kernel would refuse, before anything is changed.
+pub fn check_path<P: std::convert::AsRef<std::path::Path>>(path: P)
+-> Result<(), String> {
+    #[cfg(unix)]
+    use std::os::unix::ffi::OsStrExt;
+    #[cfg(target_os = "wasi")]
+    use std::os::wasi::ffi::OsStrExt;
+
+    let path = path.as_ref();
+
+    let bytes = path.as_os_str().as_bytes();
+
+    if bytes.is_empty() {
+        return Err(String::from("path must not be empty"));
+    }
+
+    if bytes.contains(&0) {
+        return Err(format!("path {} contains a NUL byte", path.display()));
+    }
+
+    if bytes.len() >= PATH_MAX {
+        return Err(format!("path is longer than {} bytes", PATH_MAX - 1));
+    }
+
+    match path.components().find(|c| c.as_os_str().len() > NAME_MAX) {
+        Some(c) => Err(format!("component {} is longer than {} bytes",
+                               std::path::Path::new(c.as_os_str()).display(),
+                               NAME_MAX)),
+        None => Ok(()),
+    }
+}
+
+// The same limit the kernel puts on nested symlinks.
+const MAX_SYMLINK_HOPS: usize = 40;
+
+// Follows path through symlinks below root and returns every path on the way,
+// starting with path itself. Stops at the first path that is not a symlink, or
+// after a path repeats, in which case the repeated path is the last one.
+pub fn symlink_chain<P: std::convert::AsRef<std::path::Path>,
+                     Q: std::convert::AsRef<std::path::Path>>(root: P, path: Q)
+-> Vec<std::path::PathBuf> {
+    let mut chain = vec![path.as_ref().to_path_buf()];
+
+    while chain.len() <= MAX_SYMLINK_HOPS {
+        let current = chain.last().unwrap();
+        let next = match under(root.as_ref(), current).read_link() {
+            Ok(n) => n,
+            Err(_) => break,
+        };
+
+        let next = match current.parent() {
+            Some(parent) if next.is_relative() => normalize(parent.join(next)),
+            _ => normalize(next),
+        };
+        let repeated = chain.contains(&next);
+
+        chain.push(next);
+
+        if repeated {
+            break;
+        }
+    }
+
+    chain
+}
+
+// Whether path, as the target of link, is link itself or reaches it through
+// symlinks, once the directories involved have been canonicalized. A relative
+// path is taken from the directory of link, as the kernel would.
+pub fn resolves_to<P: std::convert::AsRef<std::path::Path>,
+                   Q: std::convert::AsRef<std::path::Path>,
+                   R: std::convert::AsRef<std::path::Path>>(root: P, path: Q,
+                                                            link: R) -> bool {
+    let (root, link) = (root.as_ref(), link.as_ref());
+    let path = match link.parent() {
+        Some(dir) if path.as_ref().is_relative() => dir.join(path),
+        _ => path.as_ref().to_path_buf(),
+    };
+    let link = location(root, link);
+
+    symlink_chain(root, path).iter().any(|p| location(root, p) == link)
+}
+
+// Where path lives, with its directory canonicalized but the final component
+// left alone, so that a link is not confused with what it points to.
+fn location(root: &std::path::Path,
+            path: &std::path::Path) -> std::path::PathBuf {
+    let full = under(root, path);
+    let dir = match full.parent() {
+        Some(d) if d != std::path::Path::new("") => d,
+        _ => std::path::Path::new("."),
+    };
+
+    match (dir.canonicalize(), full.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => normalize(full),
+    }
+}
+
+// Resolves . and .. lexically, without looking at the filesystem.
+pub fn normalize(path: std::path::PathBuf) -> std::path::PathBuf {
+    let mut normal = std::path::PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => (),
+            std::path::Component::ParentDir => {
+                normal.pop();
+            },
+            c => normal.push(c),
+        }
+    }
+
+    normal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::under;
+
+    #[test]
+    fn under_keeps_paths_below_root() {
+        assert_eq!(under("/srv/image", "/usr/bin/cc"),
+                   std::path::Path::new("/srv/image/usr/bin/cc"));
+        assert_eq!(under("/srv/image", "/../../etc/passwd"),
+                   std::path::Path::new("/srv/image/etc/passwd"));
+        assert_eq!(under("/srv/image", "/usr/../../etc/passwd"),
+                   std::path::Path::new("/srv/image/etc/passwd"));
+        assert_eq!(under("/srv/image", "usr/./bin/../lib"),
+                   std::path::Path::new("/srv/image/usr/lib"));
+    }
+}
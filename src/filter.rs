@@ -0,0 +1,70 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+extern crate glob;
+extern crate regex;
+
+use super::alternative_db::AlternativeDb;
+use super::alternative_list::AlternativeList;
+
+// Selects groups by name, for machines with hundreds of them.
+pub enum Filter {
+    All,
+    Glob(glob::Pattern),
+    Regex(regex::Regex),
+}
+
+impl Filter {
+    pub fn glob(pattern: &str) -> Result<Filter, String> {
+        glob::Pattern::new(pattern).map(Filter::Glob)
+                                   .map_err(|e| e.to_string())
+    }
+
+    pub fn regex(pattern: &str) -> Result<Filter, String> {
+        regex::Regex::new(pattern).map(Filter::Regex)
+                                  .map_err(|e| e.to_string())
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match *self {
+            Filter::All => true,
+            Filter::Glob(ref p) => p.matches(name),
+            Filter::Regex(ref r) => r.is_match(name),
+        }
+    }
+}
+
+// The groups selected by filter, sorted by name.
+pub fn groups<'a>(db: &'a AlternativeDb, filter: &Filter)
+-> Vec<(&'a String, &'a AlternativeList)> {
+    let mut groups: Vec<_> = db.iter()
+                               .filter(|(name, _)| filter.matches(name))
+                               .collect();
+    groups.sort_by_key(|(name, _)| *name);
+
+    groups
+}
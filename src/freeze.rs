@@ -0,0 +1,115 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate serde_json;
+
+use super::audit;
+use super::filesystem;
+
+pub static FREEZE_PATH: &str = "/var/lib/update-alternatives/frozen";
+
+// Who froze the database, when, and why.
+#[derive(Serialize, Deserialize)]
+pub struct Freeze {
+    pub user: String,
+    pub since: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl std::fmt::Display for Freeze {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "frozen by {} since {}", self.user, self.since)?;
+
+        match self.reason {
+            Some(ref r) => write!(formatter, ": {}", r),
+            None => Ok(()),
+        }
+    }
+}
+
+// The freeze in effect below root, if any. A marker that cannot be read
+// still freezes the database.
+pub fn frozen<P: std::convert::AsRef<std::path::Path>>(root: P)
+-> Option<Freeze> {
+    let path = filesystem::under(root, FREEZE_PATH);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => return Some(Freeze{ user: String::from("unknown"),
+                                      since: String::from("unknown"),
+                                      reason: Some(e.to_string()) }),
+    };
+
+    Some(serde_json::from_str(&contents).unwrap_or_else(|e| Freeze{
+        user: String::from("unknown"), since: String::from("unknown"),
+        reason: Some(format!("unreadable marker {}: {}", path.display(), e)),
+    }))
+}
+
+// Fails if the database below root is frozen, saying by whom and how to thaw
+// it, so that nothing is changed.
+pub fn check<P: std::convert::AsRef<std::path::Path>>(root: P)
+-> std::io::Result<()> {
+    match frozen(root) {
+        Some(f) => Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("the database is {}; run 'update-alternatives thaw' to \
+                    allow changes", f)
+        )),
+        None => Ok(()),
+    }
+}
+
+pub fn freeze<P: std::convert::AsRef<std::path::Path>>(
+    root: P, reason: Option<String>
+) -> std::io::Result<Freeze> {
+    let freeze = Freeze{ user: audit::invoking_user(),
+                         since: audit::timestamp(std::time::SystemTime::now()),
+                         reason };
+    let path = filesystem::under(root, FREEZE_PATH);
+
+    if let Some(parent) = path.parent() {
+        filesystem::create_dir(parent)?;
+    }
+
+    filesystem::write(serde_json::to_string(&freeze)?, &path)?;
+    filesystem::set_mode(&path, 0o644)?;
+
+    Ok(freeze)
+}
+
+// Returns whether the database was frozen.
+pub fn thaw<P: std::convert::AsRef<std::path::Path>>(root: P)
+-> std::io::Result<bool> {
+    match std::fs::remove_file(filesystem::under(root, FREEZE_PATH)) {
+        Ok(()) => Ok(true),
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
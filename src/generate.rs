@@ -0,0 +1,334 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use super::alternative_db;
+use super::filesystem;
+use super::helper;
+
+pub static APT_HOOK_PATH: &str = "/etc/apt/apt.conf.d/99update-alternatives";
+
+// Hooks and units are text, which a path that is not valid UTF-8 could not
+// be written into without changing it.
+pub fn check_exe(exe: &std::path::Path) -> std::io::Result<()> {
+    match exe.to_str() {
+        Some(_) => Ok(()),
+        None => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                                        "the path is not valid UTF-8")),
+    }
+}
+
+// Quotes path for a POSIX shell, so that spaces, quotes, and other special
+// characters in it are taken literally.
+fn shell_quote(path: &std::path::Path) -> String {
+    format!("'{}'", path.display().to_string().replace('\'', "'\\''"))
+}
+
+// Quotes path as a single word of a systemd command line, where quotes,
+// backslashes, specifiers, and variables would otherwise be expanded.
+fn systemd_quote(path: &std::path::Path) -> String {
+    let escaped = path.display().to_string().replace('\\', "\\\\")
+                                            .replace('"', "\\\"")
+                                            .replace('%', "%%")
+                                            .replace('$', "$$");
+
+    format!("\"{}\"", escaped)
+}
+
+pub fn apt_hook(exe: &std::path::Path) -> std::io::Result<String> {
+    // APT's configuration strings have no escapes.
+    if exe.to_string_lossy().contains('"') {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                                       "APT cannot run a path containing \""));
+    }
+
+    Ok(format!("// Generated by update-alternatives generate-hook apt.\n\
+                // Rewrites the symlinks in /usr/local/bin after every dpkg run.\n\
+                DPkg::Post-Invoke {{ \"if [ -x {exe} ]; then {exe} sync; fi\"; }};\n",
+               exe = shell_quote(exe)))
+}
+
+pub static DNF_HOOK_PATH: &str =
+    "/etc/dnf/plugins/post-transaction-actions.d/update-alternatives.action";
+
+pub fn dnf_hook(exe: &std::path::Path) -> String {
+    format!("# Generated by update-alternatives generate-hook dnf.\n\
+             # Requires the post-transaction-actions dnf plugin. Identical\n\
+             # commands run once per transaction.\n\
+             *:any:{exe} sync\n", exe = shell_quote(exe))
+}
+
+pub fn rpm_trigger(exe: &std::path::Path) -> String {
+    // rpm expands macros in the script, even inside quotes.
+    let action = format!("if [ -x {exe} ]; then {exe} sync; fi",
+                         exe = shell_quote(exe).replace('%', "%%"));
+
+    format!("# Generated by update-alternatives generate-hook rpm.\n\
+             # Add to the spec file of a site package to run sync whenever a\n\
+             # transaction installs or removes executables.\n\
+             %transfiletriggerin -- /usr/bin /usr/sbin /opt\n\
+             {action}\n\
+             \n\
+             %transfiletriggerpostun -- /usr/bin /usr/sbin /opt\n\
+             {action}\n", action = action)
+}
+
+// Sourced by interactive shells; @PATHS@ is replaced by the directories
+// whose modification time tells that links changed.
+static SHELL_HOOK: &str = r#"# Generated by update-alternatives generate-hook shell.
+# Source from ~/.bashrc or ~/.zshrc to forget the locations of commands
+# whenever update-alternatives changes a link.
+__update_alternatives_stamp=
+__update_alternatives_rehash() {
+    local stamp
+    stamp=$(stat -c %Y @PATHS@ 2>/dev/null)
+    if [ "$stamp" != "$__update_alternatives_stamp" ]; then
+        __update_alternatives_stamp=$stamp
+        hash -r
+    fi
+}
+if [ -n "$ZSH_VERSION" ]; then
+    precmd_functions+=(__update_alternatives_rehash)
+else
+    PROMPT_COMMAND="__update_alternatives_rehash${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
+"#;
+
+pub fn shell_hook() -> String {
+    let paths = [alternative_db::DB_FOLDER, alternative_db::LINK_DIR];
+    let quoted: Vec<String> = paths.iter()
+                                   .map(|p| shell_quote(std::path::Path::new(p)))
+                                   .collect();
+
+    SHELL_HOOK.replace("@PATHS@", &quoted.join(" "))
+}
+
+pub static SYSTEMD_UNIT_DIR: &str = "/etc/systemd/system";
+
+pub static SYNC_SERVICE: &str = "update-alternatives-sync.service";
+
+pub static SYNC_TIMER: &str = "update-alternatives-sync.timer";
+
+pub static SYNC_PATH: &str = "update-alternatives-sync.path";
+
+pub fn sync_service(exe: &std::path::Path) -> String {
+    format!("# Generated by update-alternatives generate-systemd.\n\
+             [Unit]\n\
+             Description=Rewrite update-alternatives symlinks\n\
+             After=local-fs.target\n\
+             \n\
+             [Service]\n\
+             Type=oneshot\n\
+             ExecStart={exe} --no-escalate sync\n\
+             \n\
+             [Install]\n\
+             WantedBy=multi-user.target\n", exe = systemd_quote(exe))
+}
+
+pub fn sync_timer(calendar: &str) -> String {
+    format!("# Generated by update-alternatives generate-systemd.\n\
+             [Unit]\n\
+             Description=Periodically rewrite update-alternatives symlinks\n\
+             \n\
+             [Timer]\n\
+             OnCalendar={calendar}\n\
+             Persistent=true\n\
+             Unit={service}\n\
+             \n\
+             [Install]\n\
+             WantedBy=timers.target\n",
+            calendar = calendar, service = SYNC_SERVICE)
+}
+
+pub fn sync_path<P: std::convert::AsRef<std::path::Path>>(db_folder: P)
+-> String {
+    format!("# Generated by update-alternatives generate-systemd.\n\
+             [Unit]\n\
+             Description=Rewrite update-alternatives symlinks when the \
+             database changes\n\
+             \n\
+             [Path]\n\
+             PathChanged={folder}\n\
+             Unit={service}\n\
+             \n\
+             [Install]\n\
+             WantedBy=paths.target\n",
+            folder = db_folder.as_ref().display(), service = SYNC_SERVICE)
+}
+
+pub static EXPIRE_SERVICE: &str = "update-alternatives-expire.service";
+
+pub static EXPIRE_TIMER: &str = "update-alternatives-expire.timer";
+
+pub fn expire_service(exe: &std::path::Path) -> String {
+    format!("# Generated by update-alternatives generate-systemd.\n\
+             [Unit]\n\
+             Description=End expired update-alternatives selections\n\
+             After=local-fs.target\n\
+             \n\
+             [Service]\n\
+             Type=oneshot\n\
+             ExecStart={exe} --no-escalate expire\n", exe = systemd_quote(exe))
+}
+
+pub fn expire_timer() -> String {
+    format!("# Generated by update-alternatives generate-systemd.\n\
+             [Unit]\n\
+             Description=End expired update-alternatives selections \
+             regularly\n\
+             \n\
+             [Timer]\n\
+             OnCalendar=*:0/5\n\
+             Persistent=true\n\
+             Unit={service}\n\
+             \n\
+             [Install]\n\
+             WantedBy=timers.target\n", service = EXPIRE_SERVICE)
+}
+
+pub static HELPER_SOCKET: &str = "update-alternatives-helper.socket";
+
+pub static HELPER_SERVICE: &str = "update-alternatives-helper.service";
+
+pub fn helper_socket() -> String {
+    format!("# Generated by update-alternatives generate-systemd.\n\
+             [Unit]\n\
+             Description=update-alternatives helper socket\n\
+             \n\
+             [Socket]\n\
+             ListenStream={socket}\n\
+             SocketMode=0666\n\
+             RemoveOnStop=true\n\
+             \n\
+             [Install]\n\
+             WantedBy=sockets.target\n", socket = helper::SOCKET_PATH)
+}
+
+pub fn helper_service(exe: &std::path::Path) -> String {
+    format!("# Generated by update-alternatives generate-systemd.\n\
+             [Unit]\n\
+             Description=update-alternatives helper\n\
+             Requires={socket}\n\
+             \n\
+             [Service]\n\
+             ExecStart={exe} --no-escalate helper\n",
+            socket = HELPER_SOCKET, exe = systemd_quote(exe))
+}
+
+pub static WATCH_SERVICE: &str = "update-alternatives-watch.service";
+
+pub static SERVE_SERVICE: &str = "update-alternatives-serve.service";
+
+// How long the daemons may go without telling systemd that their loop still
+// turns before they are restarted.
+static WATCHDOG_SEC: u32 = 30;
+
+pub fn watch_service(exe: &std::path::Path) -> String {
+    format!("# Generated by update-alternatives generate-systemd.\n\
+             [Unit]\n\
+             Description=Repair update-alternatives symlinks as they change\n\
+             After=local-fs.target\n\
+             \n\
+             [Service]\n\
+             Type=notify\n\
+             ExecStart={exe} --no-escalate watch\n\
+             WatchdogSec={watchdog}\n\
+             Restart=on-failure\n\
+             \n\
+             [Install]\n\
+             WantedBy=multi-user.target\n",
+            exe = systemd_quote(exe), watchdog = WATCHDOG_SEC)
+}
+
+pub fn serve_service(exe: &std::path::Path) -> String {
+    format!("# Generated by update-alternatives generate-systemd.\n\
+             [Unit]\n\
+             Description=update-alternatives REST API and web interface\n\
+             After=network.target\n\
+             \n\
+             [Service]\n\
+             Type=notify\n\
+             ExecStart={exe} --no-escalate serve\n\
+             WatchdogSec={watchdog}\n\
+             Restart=on-failure\n\
+             \n\
+             [Install]\n\
+             WantedBy=multi-user.target\n",
+            exe = systemd_quote(exe), watchdog = WATCHDOG_SEC)
+}
+
+pub fn install<S, P: std::convert::AsRef<std::path::Path>>(contents: S, path: P)
+-> std::io::Result<usize> where String: std::convert::From<S> {
+    let written = filesystem::write(contents, path.as_ref())?;
+    filesystem::set_mode(path, 0o644)?;
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_exe, shell_quote};
+
+    // What sh makes of the quoted path as a single argument.
+    fn through_sh(path: &str) -> String {
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf %s {}", shell_quote(path.as_ref())))
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn quotes_as_one_word() {
+        assert_eq!(shell_quote("/usr/bin/update-alternatives".as_ref()),
+                   "'/usr/bin/update-alternatives'");
+        assert_eq!(shell_quote("/opt/it's/ua".as_ref()), "'/opt/it'\\''s/ua'");
+    }
+
+    #[test]
+    fn survives_the_shell() {
+        for path in ["/opt/My Tools/bin/tool", "/opt/it's/tool",
+                     "/opt/\"q\"/tool", "/opt/a|b;c&d/tool", "/opt/$HOME/`x`",
+                     "/opt/new\nline/tool", "/opt/back\\slash/*", "/opt/%h"] {
+            assert_eq!(through_sh(path), path);
+        }
+    }
+
+    #[test]
+    fn refuses_paths_that_are_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let exe = std::ffi::OsStr::from_bytes(b"/opt/caf\xe9/bin/ua");
+
+        assert!(check_exe(std::path::Path::new(exe)).is_err());
+        assert!(check_exe("/opt/My Tools/bin/ua".as_ref()).is_ok());
+    }
+}
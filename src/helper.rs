@@ -0,0 +1,336 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate nix;
+extern crate serde_json;
+
+use std::io::{BufRead, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use self::nix::sys::socket::{UnixCredentials, getsockopt, sockopt};
+use self::serde_json::Value;
+
+use super::alternative_db::{self, AlternativeDb};
+use super::audit::{self, AuditLog};
+use super::errors::{self, Error};
+use super::filesystem;
+use super::lock;
+use super::rpc;
+use super::signals;
+
+pub static SOCKET_PATH: &str = "/run/update-alternatives/helper.sock";
+
+// How long a connection is served at most before the next one is, however
+// its requests are spread.
+const CONNECTION_TIMEOUT: std::time::Duration =
+    std::time::Duration::from_secs(60);
+
+pub fn run<P: std::convert::AsRef<std::path::Path>>(log_path: P)
+-> std::io::Result<()> {
+    let listener = listen()?;
+
+    println!("update-alternatives: helper listening on {}", SOCKET_PATH);
+
+    // Connections are served one after the other, so that every change goes
+    // through a single writer.
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                error!(SERVICE, "helper: could not accept connection: {}", e);
+
+                continue;
+            },
+        };
+
+        if let Err(e) = serve(&stream, log_path.as_ref()) {
+            error!(SERVICE, "helper: {}", e);
+        }
+
+        signals::exit_if_pending();
+    }
+
+    Ok(())
+}
+
+// Whether a helper answers on SOCKET_PATH.
+pub fn available() -> bool {
+    UnixStream::connect(SOCKET_PATH).is_ok()
+}
+
+// Takes the socket passed by systemd when started by
+// update-alternatives-helper.socket, and binds SOCKET_PATH otherwise.
+fn listen() -> std::io::Result<UnixListener> {
+    use std::os::unix::io::FromRawFd;
+
+    let activated = std::env::var("LISTEN_PID").ok()
+                                               .and_then(|p| p.parse().ok())
+        == Some(std::process::id())
+        && std::env::var("LISTEN_FDS").is_ok_and(|n| n == "1");
+
+    if activated {
+        // systemd passes its socket as the first descriptor after stderr,
+        // and nothing else in the process owns it.
+        return Ok(unsafe { UnixListener::from_raw_fd(3) });
+    }
+
+    let path = std::path::Path::new(SOCKET_PATH);
+
+    if available() {
+        return Err(std::io::Error::new(std::io::ErrorKind::AddrInUse,
+                                       format!("another helper listens on {}",
+                                               path.display())));
+    }
+
+    if let Some(parent) = path.parent() {
+        filesystem::create_dir(parent)?;
+    }
+
+    // Left behind by a helper that did not exit cleanly.
+    match std::fs::remove_file(path) {
+        Ok(()) => (),
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => (),
+        Err(e) => return Err(e),
+    }
+
+    let listener = UnixListener::bind(path)?;
+
+    // Anyone may connect; every change is authorized through polkit.
+    filesystem::set_mode(path, 0o666)?;
+
+    Ok(listener)
+}
+
+// Reads from a connection until its deadline, which every read counts
+// towards, so that a client cannot keep it open by sending a little at a time.
+struct Deadline<'a> {
+    stream: &'a UnixStream,
+    until: std::time::Instant,
+}
+
+impl<'a> Read for Deadline<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let left = self.until
+                       .saturating_duration_since(std::time::Instant::now());
+
+        if left.is_zero() {
+            return Err(std::io::Error::new(std::io::ErrorKind::TimedOut,
+                                           "connection took too long"));
+        }
+
+        self.stream.set_read_timeout(Some(left))?;
+
+        let mut stream = self.stream;
+
+        stream.read(buf)
+    }
+}
+
+// Answers the requests of one client. Every request reads the database as it
+// is then; a change is made under the database lock, which is released as soon
+// as it is committed, before it is answered. Clients are authorized without
+// holding the lock, since polkit may ask them to authenticate.
+fn serve(stream: &UnixStream, log_path: &std::path::Path)
+-> std::io::Result<()> {
+    let peer = getsockopt(stream, sockopt::PeerCredentials)?;
+    let user = audit::user_name(nix::unistd::Uid::from_raw(peer.uid()));
+    let reader = Deadline{ stream,
+                           until: std::time::Instant::now()
+                                  + CONNECTION_TIMEOUT };
+    let mut authorized: Vec<&str> = Vec::new();
+
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+
+    rpc::answer(std::io::BufReader::new(reader), stream, |request| {
+        let action = action(&request.method);
+
+        if let Some(action) = action {
+            if !authorized.contains(&action) {
+                authorize(&peer, action).map_err(|e| {
+                    (-32001, Error::new(&errors::DENIED, e))
+                })?;
+                authorized.push(action);
+            }
+        }
+
+        let _lock = match action {
+            Some(_) => Some(lock::acquire("/").map_err(|e| {
+                (-32603, Error::new(&errors::LOCKED, e.to_string()))
+            })?),
+            None => None,
+        };
+        let mut db = AlternativeDb::from_folder(alternative_db::DB_FOLDER)
+            .map_err(|e| {
+                (-32603, Error::new(&errors::BAD_DATABASE, e.to_string()))
+            })?;
+
+        // Recorded only once the change is committed.
+        let mut log = AuditLog::for_user(log_path, user.clone());
+        let mut mutated = false;
+        let result = rpc::dispatch(&mut db, &mut log, request, &mut mutated);
+
+        if mutated && super::commit(&db).is_err() {
+            return Err((-32603, Error::new(&errors::COMMIT,
+                                           "could not commit changes")));
+        }
+
+        if let Err(e) = log.flush() {
+            error!(AUDIT, "could not write to audit log: {}", e);
+        }
+
+        result
+    });
+
+    Ok(())
+}
+
+// The polkit action that allows method, as for the D-Bus service. Reading
+// needs none.
+fn action(method: &str) -> Option<&'static str> {
+    match method {
+        "add" => Some("add"),
+        "remove" => Some("remove"),
+        "set" => Some("set-current"),
+        "sync" | "repair" => Some("sync"),
+        _ => None,
+    }
+}
+
+// Root may do anything. Everyone else is checked by pkcheck against
+// org.fthomys.update-alternatives.<action>, which may ask them to
+// authenticate once for the whole connection.
+fn authorize(peer: &UnixCredentials, action: &str) -> Result<(), String> {
+    if peer.uid() == 0 {
+        return Ok(());
+    }
+
+    let action_id = format!("org.fthomys.update-alternatives.{}", action);
+
+    // With its start time, a process ID cannot be reused by another process
+    // to inherit the authorization.
+    let start = start_time(peer.pid()).map_err(|e| {
+        format!("could not identify process {}: {}", peer.pid(), e)
+    })?;
+    let subject = format!("{},{},{}", peer.pid(), start, peer.uid());
+
+    let status = std::process::Command::new("pkcheck")
+        .args(["--action-id", &action_id, "--process", &subject,
+               "--allow-user-interaction"])
+        .stdout(std::process::Stdio::null())
+        .status()
+        .map_err(|e| format!("could not run pkcheck: {}", e))?;
+
+    if !status.success() {
+        return Err(format!("not authorized for {}", action_id));
+    }
+
+    Ok(())
+}
+
+fn start_time(pid: i32) -> std::io::Result<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid))?;
+
+    // The command name may contain spaces and parentheses, the fields after
+    // it do not; the start time is the 22nd field.
+    stat.rsplit_once(')')
+        .and_then(|(_, fields)| fields.split_whitespace().nth(19))
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData,
+                                           "malformed /proc stat"))
+}
+
+// A connection to a running helper, which makes changes on behalf of an
+// unprivileged process. Every method returns whether anything changed.
+pub struct Client {
+    stream: std::io::BufReader<UnixStream>,
+    next_id: u64,
+}
+
+impl Client {
+    pub fn connect() -> std::io::Result<Client> {
+        let stream = UnixStream::connect(SOCKET_PATH)?;
+
+        Ok(Client{ stream: std::io::BufReader::new(stream), next_id: 1 })
+    }
+
+    pub fn add(&mut self, name: &str, target: &str, priority: i64)
+    -> std::io::Result<bool> {
+        self.call("add", serde_json::json!({
+            "name": name, "target": target, "priority": priority,
+        }))
+    }
+
+    pub fn remove(&mut self, name: &str, target: &str)
+    -> std::io::Result<bool> {
+        self.call("remove", serde_json::json!({
+            "name": name, "target": target,
+        }))
+    }
+
+    pub fn set(&mut self, name: &str, target: &str) -> std::io::Result<bool> {
+        self.call("set", serde_json::json!({ "name": name, "target": target }))
+    }
+
+    // Rewrites the link of name, or all links.
+    pub fn sync(&mut self, name: Option<&str>) -> std::io::Result<bool> {
+        self.call("sync", serde_json::json!({ "name": name }))
+    }
+
+    pub fn repair(&mut self, name: &str) -> std::io::Result<bool> {
+        self.call("repair", serde_json::json!({ "name": name }))
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> std::io::Result<bool> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0", "id": self.next_id, "method": method,
+            "params": params,
+        });
+        self.next_id += 1;
+
+        writeln!(self.stream.get_mut(), "{}", request)?;
+
+        let mut line = String::new();
+
+        if self.stream.read_line(&mut line)? == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "the helper closed the connection"
+            ));
+        }
+
+        let response: Value = serde_json::from_str(&line)?;
+
+        if let Some(message) = response.pointer("/error/message") {
+            return Err(std::io::Error::other(
+                message.as_str().unwrap_or("unknown error").to_string()
+            ));
+        }
+
+        Ok(response.pointer("/result/changed") == Some(&Value::Bool(true)))
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+use super::alternative_db::LinkChange;
+use super::errors;
+use super::events;
+
+pub static HOOK_DIR: &str = "/etc/update-alternatives.d";
+
+// Runs every executable in HOOK_DIR/STAGE in name order, with the groups whose
+// selection changes in GROUPS and the arguments of this invocation. Stops at
+// the first failure if strict.
+fn run_stage(stage: &str, changes: &[LinkChange], strict: bool)
+-> std::io::Result<()> {
+    let dir = std::path::Path::new(HOOK_DIR).join(stage);
+    let mut hooks: Vec<std::path::PathBuf> = match dir.read_dir() {
+        Ok(entries) => entries.filter_map(|e| e.ok())
+                              .map(|e| e.path())
+                              .filter(|p| p.is_file())
+                              .collect(),
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(());
+        },
+        Err(e) => return Err(e),
+    };
+
+    hooks.sort();
+
+    let groups = changes.iter().map(|c| c.name.as_str())
+                        .collect::<Vec<&str>>().join(" ");
+
+    for hook in hooks {
+        let result = std::process::Command::new(&hook)
+            .args(std::env::args_os().skip(1))
+            .env("GROUPS", &groups)
+            .status()
+            .and_then(|status| if status.success() {
+                Ok(())
+            } else {
+                Err(std::io::Error::other(format!("{} exited with {}",
+                                                  hook.display(), status)))
+            });
+
+        match result {
+            Err(e) if strict => return Err(e),
+            Err(e) => {
+                let message = format!("{} hook failed: {}", stage, e);
+
+                error!(HOOK, "{}", message);
+                events::error(&errors::HOOK, &message);
+            },
+            Ok(()) => (),
+        }
+    }
+
+    Ok(())
+}
+
+pub fn pre_commit(changes: &[LinkChange]) -> std::io::Result<()> {
+    run_stage("pre-commit", changes, true)
+}
+
+pub fn post_commit(changes: &[LinkChange]) {
+    let _ = run_stage("post-commit", changes, false);
+}
+
+// Runs HOOK_DIR/NAME.post, if present, after the selection of NAME changed.
+pub fn run_post(change: &LinkChange) -> std::io::Result<()> {
+    let hook = std::path::Path::new(HOOK_DIR)
+        .join(format!("{}.post", change.name));
+
+    if !hook.is_file() {
+        return Ok(());
+    }
+
+    let old = change.old.as_ref().map(|o| o.as_os_str())
+                                 .unwrap_or_default();
+
+    let status = std::process::Command::new(&hook)
+        .env("GROUP", &change.name)
+        .env("OLD_TARGET", old)
+        .env("NEW_TARGET", &change.new)
+        .status()?;
+
+    if !status.success() {
+        return Err(std::io::Error::other(format!("{} exited with {}",
+                                                 hook.display(), status)));
+    }
+
+    Ok(())
+}
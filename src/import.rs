@@ -0,0 +1,167 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+extern crate nix;
+
+use super::alternative_db;
+use super::audit;
+use super::scan::Candidate;
+use super::version;
+
+#[derive(Clone, Copy)]
+pub enum Source {
+    Pyenv,
+    Asdf,
+    Mise,
+}
+
+impl Source {
+    pub fn from_name(name: &str) -> Option<Source> {
+        match name {
+            "pyenv" => Some(Source::Pyenv),
+            "asdf" => Some(Source::Asdf),
+            "mise" => Some(Source::Mise),
+            _ => None,
+        }
+    }
+
+    // Mirrors the lookup of PYENV_ROOT, ASDF_DATA_DIR and MISE_DATA_DIR.
+    pub fn default_dir(self) -> Option<std::path::PathBuf> {
+        let (var, fallback) = match self {
+            Source::Pyenv => ("PYENV_ROOT", ".pyenv"),
+            Source::Asdf => ("ASDF_DATA_DIR", ".asdf"),
+            Source::Mise => ("MISE_DATA_DIR", ".local/share/mise"),
+        };
+
+        if let Some(dir) = std::env::var_os(var).filter(|d| !d.is_empty()) {
+            return Some(std::path::PathBuf::from(dir));
+        }
+
+        if let Source::Mise = self {
+            if let Some(data) = std::env::var_os("XDG_DATA_HOME")
+                                    .filter(|d| !d.is_empty()) {
+                return Some(std::path::Path::new(&data).join("mise"));
+            }
+        }
+
+        invoking_home().map(|h| h.join(fallback))
+    }
+
+    // Directories that contain one subdirectory per installed version.
+    fn version_dirs(self, dir: &std::path::Path)
+    -> std::io::Result<Vec<std::path::PathBuf>> {
+        match self {
+            Source::Pyenv => Ok(vec![dir.join("versions")]),
+            Source::Asdf | Source::Mise => {
+                let mut tools = Vec::new();
+
+                for entry in dir.join("installs").read_dir()? {
+                    tools.push(entry?.path());
+                }
+
+                Ok(tools)
+            },
+        }
+    }
+}
+
+pub fn candidates(source: Source, dir: &std::path::Path)
+-> std::io::Result<Vec<Candidate>> {
+    let mut found = Vec::new();
+
+    for versions in source.version_dirs(dir)? {
+        for entry in versions.read_dir()? {
+            let install = entry?.path();
+            let name = install.file_name()
+                              .map(|n| n.to_string_lossy().into_owned())
+                              .unwrap_or_default();
+
+            let priority = match version::priority(&name) {
+                Some(p) => p,
+                None => {
+                    println!("update-alternatives: skipping {}: cannot derive \
+                             a priority from its version", install.display());
+
+                    continue;
+                },
+            };
+
+            let bin = match install.join("bin").read_dir() {
+                Ok(b) => b,
+                Err(_) => continue,
+            };
+
+            for executable in bin.filter_map(Result::ok) {
+                let target = executable.path();
+
+                if !is_executable(&target) {
+                    continue;
+                }
+
+                let name = executable.file_name().to_string_lossy()
+                                     .into_owned();
+
+                found.push(Candidate{
+                    link: alternative_db::default_link(&name),
+                    name,
+                    target,
+                    priority,
+                });
+            }
+        }
+    }
+
+    found.sort_by(|a, b| a.name.cmp(&b.name).then(a.priority.cmp(&b.priority)));
+
+    Ok(found)
+}
+
+// The version managers belong to the user who ran sudo or pkexec, not root.
+fn invoking_home() -> Option<std::path::PathBuf> {
+    if let Ok(Some(user)) = nix::unistd::User::from_name(
+        &audit::invoking_user()
+    ) {
+        return Some(user.dir);
+    }
+
+    std::env::var_os("HOME").filter(|h| !h.is_empty())
+                            .map(std::path::PathBuf::from)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path).map(|m| m.is_file()
+                                    && m.permissions().mode() & 0o111 != 0)
+                           .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file()
+}
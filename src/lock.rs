@@ -0,0 +1,253 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+#[cfg(unix)]
+extern crate nix;
+
+#[cfg(unix)]
+use super::filesystem;
+
+pub static LOCK_PATH: &str = "/var/lib/update-alternatives/lock";
+
+// Holds a lock file for every group that was changed on its own.
+pub static GROUP_LOCK_DIR: &str = "/var/lib/update-alternatives/locks";
+
+// Held while entries are moved into place, the only time the journal and the
+// manifest are written.
+pub static COMMIT_LOCK_PATH: &str = "/var/lib/update-alternatives/commit.lock";
+
+// Whether this process holds the database lock, in either mode, or the commit
+// lock, so that acquiring it again neither blocks nor releases it early.
+#[cfg(unix)]
+static HELD: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+#[cfg(unix)]
+static COMMITTING: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+
+#[cfg(unix)]
+type Held = nix::fcntl::Flock<std::fs::File>;
+
+#[cfg(not(unix))]
+type Held = std::fs::File;
+
+// What a lock keeps other processes from changing. Without locks, the groups
+// are never looked at.
+#[cfg_attr(not(unix), allow(dead_code))]
+enum Scope<'a> {
+    Database,
+    Groups(&'a [&'a str]),
+    Commit,
+}
+
+// Serializes processes that change the database or the links. The kernel
+// drops the lock with the process, however it ends.
+pub struct Lock {
+    files: Vec<Held>,
+    held: Option<&'static std::sync::atomic::AtomicBool>,
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        self.files.clear();
+
+        if let Some(held) = self.held.take() {
+            held.store(false, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+}
+
+// Waits until no other process changes any group, and keeps every other
+// process from changing one.
+pub fn acquire<P: std::convert::AsRef<std::path::Path>>(root: P)
+-> std::io::Result<Lock> {
+    lock(root.as_ref(), Scope::Database, true)?.ok_or_else(|| {
+        std::io::Error::from(std::io::ErrorKind::WouldBlock)
+    })
+}
+
+// Waits until no other process changes the groups names or every group, and
+// keeps other processes from changing those groups only. Processes that hold
+// the locks of different groups run at the same time.
+pub fn acquire_groups<P: std::convert::AsRef<std::path::Path>>(root: P,
+                                                              names: &[&str])
+-> std::io::Result<Lock> {
+    lock(root.as_ref(), Scope::Groups(names), true)?.ok_or_else(|| {
+        std::io::Error::from(std::io::ErrorKind::WouldBlock)
+    })
+}
+
+// Takes the locks of the groups names only if no other process holds them.
+pub fn try_acquire_groups<P: std::convert::AsRef<std::path::Path>>(
+    root: P, names: &[&str]
+) -> Option<Lock> {
+    lock(root.as_ref(), Scope::Groups(names), false).ok().flatten()
+}
+
+// Waits until no other process is moving entries into place.
+pub fn acquire_commit<P: std::convert::AsRef<std::path::Path>>(root: P)
+-> std::io::Result<Lock> {
+    lock(root.as_ref(), Scope::Commit, true)?.ok_or_else(|| {
+        std::io::Error::from(std::io::ErrorKind::WouldBlock)
+    })
+}
+
+// Takes the commit lock only if no other process holds it.
+pub fn try_acquire_commit<P: std::convert::AsRef<std::path::Path>>(root: P)
+-> Option<Lock> {
+    lock(root.as_ref(), Scope::Commit, false).ok().flatten()
+}
+
+// Waits until no other process holds file, and holds it until the result is
+// dropped, for files that several processes append to.
+#[cfg(unix)]
+pub fn exclusive(file: std::fs::File)
+-> std::io::Result<impl std::ops::DerefMut<Target = std::fs::File>> {
+    use self::nix::fcntl::{Flock, FlockArg};
+
+    Flock::lock(file, FlockArg::LockExclusive)
+        .map_err(|(_, e)| std::io::Error::from(e))
+}
+
+#[cfg(not(unix))]
+pub fn exclusive(file: std::fs::File)
+-> std::io::Result<impl std::ops::DerefMut<Target = std::fs::File>> {
+    Ok(Box::new(file))
+}
+
+#[cfg(unix)]
+fn lock(root: &std::path::Path, scope: Scope, wait: bool)
+-> std::io::Result<Option<Lock>> {
+    // Creating the lock files would be a write, and nothing is changed that
+    // needs them.
+    if filesystem::is_read_only() {
+        return Ok(Some(Lock{ files: Vec::new(), held: None }));
+    }
+
+    let held = match scope {
+        Scope::Commit => &COMMITTING,
+        _ => &HELD,
+    };
+
+    if held.swap(true, std::sync::atomic::Ordering::SeqCst) {
+        return Ok(Some(Lock{ files: Vec::new(), held: None }));
+    }
+
+    let database = filesystem::under(root, LOCK_PATH);
+    let locked = match scope {
+        Scope::Database => take(&database, true, wait, "")
+            .map(|f| f.map(|f| vec![f])),
+        Scope::Groups(names) => lock_groups(root, names, wait),
+        Scope::Commit => take(&filesystem::under(root, COMMIT_LOCK_PATH), true,
+                              wait, "")
+            .map(|f| f.map(|f| vec![f])),
+    };
+
+    match locked {
+        Ok(Some(files)) => Ok(Some(Lock{ files, held: Some(held) })),
+        Ok(None) => {
+            held.store(false, std::sync::atomic::Ordering::SeqCst);
+
+            Ok(None)
+        },
+        Err(e) => {
+            held.store(false, std::sync::atomic::Ordering::SeqCst);
+
+            Err(e)
+        },
+    }
+}
+
+// The database lock is shared, so that only processes that change every group
+// wait for those that change some. The group locks are taken in name order,
+// so that two processes never wait for each other.
+#[cfg(unix)]
+fn lock_groups(root: &std::path::Path, names: &[&str], wait: bool)
+-> std::io::Result<Option<Vec<Held>>> {
+    let mut names = names.to_vec();
+    names.sort_unstable();
+    names.dedup();
+
+    let dir = filesystem::under(root, GROUP_LOCK_DIR);
+    let mut files = Vec::with_capacity(names.len() + 1);
+
+    match take(&filesystem::under(root, LOCK_PATH), false, wait, "")? {
+        Some(f) => files.push(f),
+        None => return Ok(None),
+    }
+
+    for name in names {
+        match take(&dir.join(name), true, wait, &format!(" with {}", name))? {
+            Some(f) => files.push(f),
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some(files))
+}
+
+#[cfg(unix)]
+fn take(path: &std::path::Path, exclusive: bool, wait: bool, what: &str)
+-> std::io::Result<Option<Held>> {
+    use std::os::unix::fs::OpenOptionsExt;
+    use self::nix::fcntl::{Flock, FlockArg};
+
+    let file = path.parent().map_or(Ok(()), filesystem::create_dir)
+        .and_then(|_| std::fs::OpenOptions::new().read(true)
+                                                 .write(true)
+                                                 .create(true)
+                                                 .truncate(false)
+                                                 .mode(0o644)
+                                                 .open(path))?;
+
+    let (now, later) = if exclusive {
+        (FlockArg::LockExclusiveNonblock, FlockArg::LockExclusive)
+    } else {
+        (FlockArg::LockSharedNonblock, FlockArg::LockShared)
+    };
+
+    match Flock::lock(file, now) {
+        Ok(l) => Ok(Some(l)),
+        Err((file, nix::errno::Errno::EWOULDBLOCK)) if wait => {
+            eprintln!("update-alternatives: waiting for another instance to \
+                      finish{}...", what);
+
+            Flock::lock(file, later).map(Some)
+                                    .map_err(|(_, e)| std::io::Error::from(e))
+        },
+        Err((_, nix::errno::Errno::EWOULDBLOCK)) => Ok(None),
+        Err((_, e)) => Err(std::io::Error::from(e)),
+    }
+}
+
+// Elsewhere, as on wasm32-wasi, nothing is written that would need the lock.
+#[cfg(not(unix))]
+fn lock(_root: &std::path::Path, _scope: Scope, _wait: bool)
+-> std::io::Result<Option<Lock>> {
+    Ok(Some(Lock{ files: Vec::new(), held: None }))
+}
@@ -28,14 +28,49 @@
 extern crate clap;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
+extern crate update_alternatives;
 
-mod alternative;
-mod alternative_db;
-mod alternative_list;
-mod filesystem;
+mod apply;
+mod config;
+#[cfg(feature = "dbus")]
+mod dbus;
+mod doctor;
+mod events;
+mod filter;
+mod freeze;
+mod generate;
+mod helper;
+mod hooks;
+mod import;
+mod notify;
+mod probe;
+mod procs;
+mod profile;
+mod roots;
+mod rpc;
+mod scan;
+mod serve;
+mod shell;
+mod signals;
+mod snapper;
+mod strict;
+mod table;
+mod transactional;
+mod view;
+mod watch;
 
-use alternative::Alternative;
+use update_alternatives::{alternative, alternative_db, alternative_list, audit,
+                          diff, errors, filesystem, lock, manifest, package,
+                          path_serde, timings, trash, version, xdg};
+use alternative::{Alternative, check_priority};
 use alternative_db::AlternativeDb;
+use alternative_list::{Kind, Weights};
+use audit::AuditLog;
+
+// Returned when the group or target to remove does not exist, so that scripts
+// can tell it from other errors.
+const EXIT_NOT_FOUND: i32 = 3;
 
 fn escalate_privileges() -> std::io::Result<()> {
     use std::process::Command;
@@ -50,74 +85,534 @@ fn escalate_privileges() -> std::io::Result<()> {
                 let code = status.code().unwrap_or(1);
                 std::process::exit(code);
             }
-            Err(pkerr) => {
+            Err(_pkerr) => {
                 // Fallback to sudo
-                match Command::new("sudo").arg(&exe).args(&args).status() {
-                    Ok(status) => {
-                        let code = status.code().unwrap_or(1);
-                        std::process::exit(code);
-                    }
-                    Err(_suderr) => {
-                        Err(pkerr)
-                    }
-                }
+                run_sudo(&exe, &args)
             }
         }
     }
 
     #[cfg(not(target_os = "linux"))]
     {
-        match Command::new("sudo").arg(&exe).args(&args).status() {
-            Ok(status) => {
-                let code = status.code().unwrap_or(1);
-                std::process::exit(code);
-            }
-            Err(e) => Err(e),
+        run_sudo(&exe, &args)
+    }
+}
+
+fn run_sudo(exe: &std::path::Path, args: &[std::ffi::OsString])
+-> std::io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let interactive = std::fs::File::open("/dev/tty").is_ok();
+
+    if !interactive {
+        let probe = Command::new("sudo")
+            .args(["-n", "true"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+
+        if !probe.success() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "sudo requires a password but no terminal is available; run \
+                 update-alternatives as root or pass --no-escalate"
+            ));
         }
     }
+
+    let mut command = Command::new("sudo");
+
+    if !interactive {
+        command.arg("-n");
+    }
+
+    let status = command.arg(exe).args(args).status()?;
+    std::process::exit(status.code().unwrap_or(1));
 }
 
 fn main() {
-    let use_gui_flag = std::env::args().any(|a| a == "--gui");
+    nix::sys::stat::umask(nix::sys::stat::Mode::from_bits_truncate(0o022));
+    signals::install();
+
+    // Help, versions, and usage errors are dealt with before anything else.
+    let matches = app().get_matches();
+
+    if let Some(threshold) = matches.get_one::<u64>("timings") {
+        timings::enable(std::time::Duration::from_millis(*threshold));
+    }
+
+    if let Some(names) = matches.get_many::<String>("on") {
+        let names: Vec<String> = names.cloned().collect();
+        let resolved = config::Config::load(config::CONFIG_PATH)
+            .map_err(|e| format!("could not read {}: {}", config::CONFIG_PATH,
+                                 e))
+            .and_then(|c| roots::resolve(&c, &names));
+
+        match resolved {
+            Ok(r) => std::process::exit(roots::run_on(&r)),
+            Err(e) => {
+                error!(NOT_A_ROOT, "{}", e);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    let root = matches.get_one::<String>("root")
+                      .map(std::path::PathBuf::from)
+                      .or_else(destdir)
+                      .unwrap_or_else(|| std::path::PathBuf::from("/"));
+
+    // Set before anything could be written, by this process or by a
+    // privileged one.
+    let modes = config::Config::load(filesystem::under(&root,
+                                                       config::CONFIG_PATH))
+                               .map(|c| (c.read_only, c.strict))
+                               .unwrap_or_default();
+    let read_only = matches.get_flag("read-only") || modes.0;
+
+    if read_only {
+        filesystem::set_read_only();
+    }
+
+    if matches.get_flag("strict") || modes.1 {
+        strict::set_strict();
+    }
+
+    let use_gui_flag = matches.get_flag("gui");
+    let no_escalate_flag = matches.get_flag("no-escalate") || read_only;
+    // Image builds operate on files owned by the builder; never escalate.
+    let root_flag = matches.contains_id("root") || destdir().is_some();
     let euid = nix::unistd::geteuid();
-    if !euid.is_root() && !use_gui_flag {
+    // Neither an image build nor a read-only run writes to the host.
+    let writable = read_only || root_flag
+        || (filesystem::is_writable(alternative_db::DB_FOLDER)
+            && filesystem::is_writable(alternative_db::LINK_DIR));
+    // An unprivileged shell or watch hands its changes to a running helper
+    // instead.
+    let via_helper = !euid.is_root() && !writable && !root_flag
+        && matches!(matches.subcommand_name(), Some("shell") | Some("watch"))
+        && helper::available();
+    if !euid.is_root() && !writable && !use_gui_flag && !no_escalate_flag
+        && !root_flag && !via_helper && needs_privileges(&matches) {
         if let Err(e) = escalate_privileges() {
-            eprintln!("update-alternatives: must be run as root (auto-escalation failed: {})", e);
+            error!(PRIVILEGES, "must be run as root (auto-escalation \
+                               failed: {})", e);
             std::process::exit(1);
         } else {
             unreachable!("escalate_privileges should not return Ok(()) in non-root context");
         }
     }
-    
-    let matches = app().get_matches();
 
-    let mut db = match read_db("/etc/alternatives") {
+    let modifies_db = matches.get_flag("rpc")
+        || matches!(matches.subcommand_name(),
+                    Some("add") | Some("remove") | Some("restore")
+                    | Some("retarget") | Some("copy") | Some("adopt")
+                    | Some("renumber") | Some("set") | Some("auto")
+                    | Some("config") | Some("expire"))
+        || matches.subcommand().is_some_and(|(s, sub_m)| {
+            (s == "apply" && !sub_m.get_flag("VALIDATE_ONLY"))
+                || (s == "env" && changes_environment(sub_m))
+                || (s == "tag" && changes_tags(sub_m))
+                || (s == "weights" && sub_m.contains_id("POLICY"))
+        });
+
+    if (modifies_db || matches.subcommand_name() == Some("sync")) && !read_only {
+        if let Err(e) = freeze::check(&root) {
+            error!(FROZEN, "{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Everything that writes the database or links, so that it is locked,
+    // and goes to the next snapshot on a transactional system.
+    let commits = modifies_db
+        || matches!(matches.subcommand_name(),
+                    Some("sync") | Some("prune") | Some("import")
+                    | Some("shell"))
+        || matches.subcommand().is_some_and(|(s, sub_m)| {
+            s == "scan" && sub_m.get_flag("APPLY")
+        });
+
+    if commits && !read_only && root == std::path::Path::new("/")
+       && transactional::detect()
+                      == Some(transactional::System::TransactionalUpdate) {
+        eprintln!("update-alternatives: read-only root file system; applying \
+                  changes to the next snapshot through transactional-update");
+
+        if let Err(e) = transactional::run_in_snapshot() {
+            error!(PROGRAM, "could not run transactional-update: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let report = subcommand_flag(&matches, "REPORT");
+    let print0 = subcommand_flag(&matches, "PRINT0");
+    let json = subcommand_value(&matches, "FORMAT") == Some("json");
+
+    let mut machine_out = if matches.get_flag("rpc") || report || print0
+                             || json
+                             || matches!(matches.subcommand_name(),
+                                         Some("query") | Some("export")) {
+        match take_stdout() {
+            Ok(f) => Some(f),
+            Err(e) => {
+                error!(IO, "could not redirect stdout: {}", e);
+                std::process::exit(1);
+            },
+        }
+    } else {
+        None
+    };
+
+    // A broken configuration is reported by the commit that needs it.
+    if let Ok(config) = config::Config::load(
+        filesystem::under(&root, config::CONFIG_PATH)
+    ) {
+        // Skipped when read-only, as opening the stream may create it.
+        if !read_only {
+            if let Err(e) = events::open(&root, &config.events) {
+                error!(IO, "could not open event stream: {}", e);
+            }
+        }
+
+        filesystem::set_retry(config.retry.attempts, config.retry.delay());
+    }
+
+    let use_gui = matches.get_flag("gui");
+    let mut log = AuditLog::new(matches.get_one::<String>("log")
+                                       .map(std::path::PathBuf::from)
+                                       .unwrap_or_else(|| {
+                                           filesystem::under(&root,
+                                                             audit::LOG_PATH)
+                                       }));
+    log.set_root(&root);
+
+    // These do not use the database, or read it themselves whenever they
+    // need it, so it is not read here.
+    if !matches.get_flag("rpc") && !use_gui && !uses_db(&matches) {
+        match matches.subcommand() {
+            Some(("list", sub_m)) => list_all(&root, sub_m, &mut machine_out),
+            Some(("verify", sub_m)) => verify(&root, sub_m),
+            Some(("info", _sub_m)) => info(&root, &log, &matches),
+            Some(("explain-error", sub_m)) => explain_error(sub_m),
+            Some(("apply", sub_m)) => validate_manifest(sub_m),
+            Some(("schema", _sub_m)) => print_schema(),
+            Some(("freeze", sub_m)) => run_freeze(&root, &mut log, sub_m),
+            Some(("thaw", _sub_m)) => run_thaw(&root, &mut log),
+            Some(("trash", sub_m)) => run_trash(&root, &mut log, sub_m),
+            Some(("generate-hook", sub_m)) => generate_hook(&root, sub_m),
+            Some(("generate-systemd", sub_m)) => generate_systemd(&root, sub_m),
+            Some((daemon, _sub_m)) if root != std::path::Path::new("/")
+                                      && is_daemon(daemon) => {
+                error!(UNSUPPORTED, "{} does not support --root", daemon);
+                std::process::exit(1);
+            },
+            Some((daemon, _sub_m)) if read_only && is_daemon(daemon) => {
+                error!(UNSUPPORTED, "{} does not support --read-only", daemon);
+                std::process::exit(1);
+            },
+            Some(("watch", _sub_m)) => run_watch(&mut log, via_helper),
+            Some(("serve", sub_m)) => run_serve(&mut log, sub_m),
+            Some(("helper", _sub_m)) => run_helper(&matches),
+            #[cfg(feature = "dbus")]
+            Some(("dbus-service", _sub_m)) => run_dbus_service(&matches),
+            _ => false,
+        };
+
+        if let Err(e) = log.flush() {
+            error!(AUDIT, "could not write to audit log: {}", e);
+        }
+
+        timings::report();
+        signals::exit_if_pending();
+
+        return;
+    }
+
+    // Held from reading the database to committing it, so that no other
+    // instance changes it in between. The GUI runs a privileged instance for
+    // every change instead, and the helper takes the lock itself. Commands
+    // that change a single group only lock that group.
+    let group = locked_group(&matches, &root);
+    let _lock = if commits && !matches.get_flag("gui") && !via_helper {
+        let (locked, path) = match group {
+            Some(name) => (lock::acquire_groups(&root, &[name]),
+                           std::path::Path::new(lock::GROUP_LOCK_DIR)
+                                           .join(name)),
+            None => (lock::acquire(&root),
+                     std::path::PathBuf::from(lock::LOCK_PATH)),
+        };
+
+        match locked {
+            Ok(l) => Some(l),
+            Err(e) => {
+                error!(LOCKED, "could not lock {}: {}",
+                       filesystem::under(&root, path).display(), e);
+                std::process::exit(1);
+            },
+        }
+    } else {
+        None
+    };
+
+    let mut db = match read_db(&root) {
         Ok(d) => d,
         Err(_) => std::process::exit(1),
     };
 
-    let use_gui = matches.get_flag("gui");
+    if let (Some(_), Some(name)) = (&_lock, group) {
+        db.limit_to(&[name]);
+    }
+
+    let mut links_changed = false;
+    let mut json_out = if json { machine_out.take() } else { None };
 
-    let mutated = if use_gui {
+    let mutated = if matches.get_flag("rpc") {
+        rpc::run(&mut db, &mut log, std::io::stdin().lock(),
+                 machine_out.take().unwrap())
+    } else if use_gui {
         run_gui(&mut db)
     } else {
         match matches.subcommand() {
-            Some(("list", sub_m)) => list(&db, sub_m),
-            Some(("add", sub_m)) => add(&mut db, sub_m),
-            Some(("remove", sub_m)) => remove(&mut db, sub_m),
-            Some(("sync", _sub_m)) => sync(&db),
+            Some(("list", sub_m)) => list(&db, sub_m, if json { &mut json_out }
+                                                      else { &mut machine_out }),
+            Some(("display", sub_m)) => display(&db, sub_m),
+            Some(("query", sub_m)) => query(&db, sub_m, &mut machine_out),
+            Some(("export", sub_m)) => export(&db, sub_m, &mut machine_out),
+            Some(("show", sub_m)) => show(&db, &log, sub_m),
+            Some(("explain", sub_m)) => explain(&db, &log, sub_m),
+            Some(("blame", sub_m)) => blame(&db, &log, sub_m),
+            Some(("diff", sub_m)) => diff_states(&db, sub_m),
+            Some(("test", sub_m)) => test_selection(&db, sub_m),
+            Some(("add", sub_m)) => add(&mut db, &mut log, sub_m,
+                                        &mut json_out),
+            Some(("remove", sub_m)) => remove(&mut db, &mut log, sub_m),
+            Some(("restore", sub_m)) => restore(&mut db, &mut log, sub_m),
+            Some(("retarget", sub_m)) => retarget(&mut db, &mut log, sub_m),
+            Some(("copy", sub_m)) => copy(&mut db, &mut log, sub_m),
+            Some(("adopt", sub_m)) => adopt(&mut db, &mut log, sub_m),
+            Some(("env", sub_m)) => environment(&mut db, &mut log, sub_m),
+            Some(("tag", sub_m)) => tag(&mut db, &mut log, sub_m),
+            Some(("weights", sub_m)) => weights(&mut db, &mut log, sub_m),
+            Some(("renumber", sub_m)) => renumber(&mut db, &mut log, sub_m),
+            Some(("set", sub_m)) => set(&mut db, &mut log, sub_m),
+            Some(("auto", sub_m)) => auto(&mut db, &mut log, sub_m),
+            Some(("config", sub_m)) => configure(&mut db, &mut log, sub_m),
+            Some(("expire", _sub_m)) => expire(&mut db, &mut log),
+            Some(("sync", sub_m)) => sync(&db, &mut log, sub_m,
+                                          &mut links_changed),
+            Some(("prune", sub_m)) => prune(&mut db, &mut log, sub_m),
+            Some(("scan", sub_m)) => scan(&mut db, &mut log, sub_m),
+            Some(("import", sub_m)) => import(&mut db, &mut log, sub_m),
+            Some(("apply", sub_m)) => apply(&mut db, &mut log, sub_m),
+            Some(("shell", _sub_m)) => shell::run(&mut db, &mut log,
+                                                  via_helper),
+            Some(("doctor", sub_m)) => doctor(&db, sub_m),
+            Some(("status", sub_m)) => status(&db, sub_m, &mut machine_out),
+            Some(("search", sub_m)) => search(&db, sub_m, &mut machine_out),
             _ => false,
         }
     };
 
+    if mutated {
+        if let Some(provider) = package::detect(&root) {
+            db.record_packages(&*provider);
+        }
+    }
+
     if mutated && commit(&db).is_err() {
         std::process::exit(1);
     }
+
+    // Commits are checked as they are made.
+    if !mutated && strict::is_strict() {
+        if let Err(e) = check_strict(&db) {
+            error!(STRICT, "{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if let (Some(mut out), true) = (machine_out, report) {
+        use std::io::Write;
+
+        let changed = mutated || links_changed;
+
+        if let Err(e) = writeln!(out, "{}", if changed { "changed" }
+                                            else { "unchanged" }) {
+            error!(IO, "could not write report: {}", e);
+        }
+    }
+
+    if let Err(e) = log.flush() {
+        error!(AUDIT, "could not write to audit log: {}", e);
+    }
+
+    timings::report();
+    signals::exit_if_pending();
+}
+
+// The group a command changes, if it changes one group only and so locks only
+// that group rather than the whole database. Adding a group or followers
+// checks their links against every other group, which must not change
+// meanwhile, so it locks the whole database.
+fn locked_group<'a>(matches: &'a clap::ArgMatches, root: &std::path::Path)
+-> Option<&'a str> {
+    let (subcommand, sub_m) = matches.subcommand()?;
+
+    if matches.get_flag("rpc")
+       || !matches!(subcommand, "add" | "remove" | "restore" | "set" | "auto"
+                                | "config" | "renumber" | "env" | "tag"
+                                | "weights") {
+        return None;
+    }
+
+    // Invalid names are refused once the database is read.
+    let name = ["NAME", "NAME_POS"].iter()
+        .find_map(|id| sub_m.try_get_one::<String>(id).ok().flatten())
+        .map(|n| n.as_str())
+        .filter(|n| alternative_db::check_name(n).is_ok())?;
+
+    if subcommand == "add" {
+        let folder = filesystem::under(root, alternative_db::DB_FOLDER);
+
+        if sub_m.contains_id("LINK") || sub_m.contains_id("SLAVE")
+           || !alternative_db::entry_path(&folder, name).exists() {
+            return None;
+        }
+    }
+
+    Some(name)
+}
+
+fn subcommand_flag(matches: &clap::ArgMatches, id: &str) -> bool {
+    matches.subcommand()
+           .and_then(|(_, m)| m.try_get_one::<bool>(id).ok())
+           .flatten()
+           .cloned()
+           .unwrap_or(false)
+}
+
+fn subcommand_value<'a>(matches: &'a clap::ArgMatches,
+                        id: &str) -> Option<&'a str> {
+    matches.subcommand()
+           .and_then(|(_, m)| m.try_get_one::<String>(id).ok())
+           .flatten()
+           .map(|s| s.as_str())
+}
+
+// Writes every item followed by a NUL byte, for xargs -0. Paths are written
+// as they are, even if they are not valid UTF-8.
+fn print0<S: std::convert::AsRef<std::ffi::OsStr>,
+          I: std::iter::IntoIterator<Item = S>>(
+    out: &mut Option<std::fs::File>, items: I
+) {
+    use std::io::Write;
+    use std::os::unix::ffi::OsStrExt;
+
+    let out = out.as_mut().expect("stdout is taken for --print0");
+
+    for item in items {
+        let result = out.write_all(item.as_ref().as_bytes())
+                        .and_then(|_| out.write_all(b"\0"));
+
+        if let Err(e) = result {
+            error!(IO, "could not write output: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Writes value as a line of JSON to out, the original stdout, if it was taken
+// for --format json.
+fn write_json<T: serde::Serialize>(out: &mut Option<std::fs::File>,
+                                   value: &T) {
+    use std::io::Write;
+
+    let out = match out.as_mut() {
+        Some(o) => o,
+        None => return,
+    };
+
+    if let Err(e) = serde_json::to_writer(&mut *out, value)
+                        .map_err(std::io::Error::from)
+                        .and_then(|_| writeln!(out)) {
+        error!(IO, "could not write result: {}", e);
+    }
+}
+
+// Informational messages are printed to stdout throughout the crate, so for
+// machine-readable output the original stdout is kept aside and fd 1 is
+// pointed at stderr.
+fn take_stdout() -> std::io::Result<std::fs::File> {
+    let saved = nix::unistd::dup(std::io::stdout())?;
+    nix::unistd::dup2_stdout(std::io::stderr())?;
+
+    Ok(std::fs::File::from(saved))
+}
+
+fn destdir() -> Option<std::path::PathBuf> {
+    std::env::var_os("DESTDIR").filter(|d| !d.is_empty())
+                               .map(std::path::PathBuf::from)
+}
+
+// Whether the command writes to the database, the links, or system files.
+// Everything else runs as the invoking user.
+fn needs_privileges(matches: &clap::ArgMatches) -> bool {
+    match matches.subcommand() {
+        _ if matches.get_flag("rpc") => true,
+        Some(("verify", sub_m)) => sub_m.get_flag("UPDATE"),
+        Some(("generate-hook", sub_m)) | Some(("generate-systemd", sub_m)) => {
+            !sub_m.get_flag("STDOUT")
+        },
+        Some(("env", sub_m)) => changes_environment(sub_m),
+        Some(("tag", sub_m)) => changes_tags(sub_m),
+        Some(("weights", sub_m)) => sub_m.contains_id("POLICY"),
+        Some(("apply", sub_m)) => !sub_m.get_flag("VALIDATE_ONLY"),
+        Some(("trash", sub_m)) => sub_m.subcommand_name() == Some("restore"),
+        Some(("list", _)) | Some(("display", _)) | Some(("query", _))
+        | Some(("show", _)) | Some(("info", _)) | Some(("explain-error", _))
+        | Some(("explain", _)) | Some(("blame", _)) | Some(("diff", _))
+        | Some(("schema", _)) | Some(("export", _)) | Some(("test", _))
+        | Some(("doctor", _)) | Some(("status", _)) | Some(("search", _))
+        | None => false,
+        Some(_) => true,
+    }
 }
 
-fn read_db<P: std::convert::AsRef<std::path::Path>>(path: P)
+// Whether the subcommand works on the database read by main. list --all, and
+// list without a name, read it one group at a time instead.
+fn uses_db(matches: &clap::ArgMatches) -> bool {
+    if let Some(("list", sub_m)) = matches.subcommand() {
+        return !sub_m.get_flag("ALL") && !sub_m.contains_id("TAG")
+            && (sub_m.contains_id("NAME") || sub_m.contains_id("NAME_POS"));
+    }
+
+    if let Some(("apply", sub_m)) = matches.subcommand() {
+        return !sub_m.get_flag("VALIDATE_ONLY");
+    }
+
+    matches!(matches.subcommand_name(),
+             Some("display") | Some("query") | Some("show") | Some("explain")
+             | Some("blame") | Some("export")
+             | Some("diff") | Some("test") | Some("add") | Some("remove")
+             | Some("restore") | Some("retarget") | Some("copy") | Some("env")
+             | Some("adopt") | Some("tag") | Some("weights") | Some("renumber")
+             | Some("set") | Some("auto") | Some("config")
+             | Some("expire")
+             | Some("sync")
+             | Some("prune") | Some("scan") | Some("import")
+             | Some("shell") | Some("doctor") | Some("status")
+             | Some("search"))
+}
+
+fn is_daemon(subcommand: &str) -> bool {
+    matches!(subcommand, "watch" | "serve" | "helper" | "dbus-service")
+}
+
+fn read_db<P: std::convert::AsRef<std::path::Path>>(root: P)
 -> std::io::Result<AlternativeDb> {
-    match AlternativeDb::from_folder(path) {
+    match timings::phase("load", || AlternativeDb::open(root.as_ref())) {
         Ok(d) => {
             println!("update-alternatives: parsed {} alternatives",
                      d.num_alternatives());
@@ -125,256 +620,4586 @@ fn read_db<P: std::convert::AsRef<std::path::Path>>(path: P)
             Ok(d)
         },
         Err(e) => {
-            eprintln!("update-alternatives: could not read folder \
-                      /etc/alternatives: {}", e);
+            error!(BAD_DATABASE, "could not read folder {}: {}",
+                   filesystem::under(root, alternative_db::DB_FOLDER)
+                              .display(), e);
 
             Err(e)
         }
     }
 }
 
-fn list(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+// Prints each group as soon as it is read, so that a huge database is never
+// held in memory and the first rows show up at once.
+fn list_all(root: &std::path::Path, matches: &clap::ArgMatches,
+            out: &mut Option<std::fs::File>) -> bool {
+    let filter = group_filter(matches, "NAME_POS");
+    let triplet = matches.get_one::<String>("TRIPLET").map(|t| t.as_str());
+    let selected = |name: &str| {
+        filter.matches(name)
+            && triplet.is_none_or(|t| {
+                alternative_db::split_triplet(name).1 == Some(t)
+            })
+    };
+
+    let tag = matches.get_one::<String>("TAG").map(|t| t.as_str());
+    let tagged = |list: &alternative_list::AlternativeList| {
+        tag.is_none_or(|t| list.has_tag(t))
+    };
+
+    let result = if matches.get_one::<String>("FORMAT")
+                           .is_some_and(|f| f == "json") {
+        use std::io::Write;
+
+        let out = out.as_mut().expect("stdout is taken for --format json");
+        let mut first = true;
+        let mut written = write!(out, "[");
+
+        // Written a group at a time, like the table.
+        let result = timings::phase("load", || {
+            AlternativeDb::each_group(root, selected, |name, list| {
+                if !tagged(list) || written.is_err() {
+                    return;
+                }
+
+                let separator = if first { "" } else { "," };
+                first = false;
+
+                written = write!(out, "{}", separator).and_then(|_| {
+                    serde_json::to_writer(&mut *out,
+                                          &view::GroupView::new(name, list))
+                               .map_err(std::io::Error::from)
+                });
+            })
+        });
+
+        if let Err(e) = written.and_then(|_| writeln!(out, "]")) {
+            error!(IO, "could not write result: {}", e);
+            std::process::exit(1);
+        }
+
+        result
+    } else if matches.get_flag("PRINT0") {
+        let mut names = Vec::new();
+
+        let result = timings::phase("load", || {
+            AlternativeDb::each_group(root, selected, |name, list| {
+                if tagged(list) {
+                    names.push(name.to_string());
+                }
+            })
+        });
+        print0(out, names);
+
+        result
+    } else {
+        let mut table = alternatives_table();
+        select_columns(&mut table, matches, ALTERNATIVE_COLUMNS);
+
+        let mut stream = table.stream(matches.get_flag("WIDE"));
+
+        // Printing is counted as loading, since the two are interleaved.
+        let result = timings::phase("load", || {
+            AlternativeDb::each_group(root, selected, |name, list| {
+                if tagged(list) {
+                    push_alternatives(|row| stream.push(row), name, list,
+                                      |_| true);
+                }
+            })
+        });
+        stream.finish();
+
+        result
+    };
+
+    if let Err(e) = result {
+        error!(BAD_DATABASE, "could not read folder {}: {}",
+               filesystem::under(root, alternative_db::DB_FOLDER).display(),
+               e);
+        std::process::exit(1);
+    }
+
+    false
+}
+
+fn list(db: &AlternativeDb, matches: &clap::ArgMatches,
+        out: &mut Option<std::fs::File>) -> bool {
     let name = matches
         .get_one::<String>("NAME")
         .or_else(|| matches.get_one::<String>("NAME_POS"))
         .map(|s| s.as_str())
         .unwrap();
 
+    let archived = matches.get_flag("ARCHIVED");
+
     match db.alternatives(name) {
+        Some(alternatives) if matches.get_one::<String>("FORMAT")
+                                     .is_some_and(|f| f == "json") => {
+            let mut view = view::GroupView::new(name, alternatives);
+
+            if archived {
+                view.archived = Some(alternatives.archived());
+            }
+
+            write_json(out, &view);
+        },
+        Some(alternatives) if matches.get_flag("PRINT0") => {
+            let shown = if archived { alternatives.archived() } else { &[] };
+
+            print0(out, alternatives.links().iter()
+                                    .chain(shown)
+                                    .map(|a| a.target()));
+        },
+        Some(alternatives) if matches.get_flag("VERBOSE")
+                              || matches.get_flag("PROBE") => {
+            list_detailed(db, name, alternatives, matches.get_flag("VERBOSE"),
+                          matches.get_flag("PROBE"));
+        },
         Some(alternatives) => {
             print!("update-alternatives: {}", alternatives);
+
+            if archived {
+                for alternative in alternatives.archived() {
+                    println!("    {} [archived]", alternative);
+                }
+            }
         },
         None => {
-            eprintln!("update-alternatives: no alternatives found for {}", name);
+            error!(NOT_FOUND, "no alternatives found for {}", name);
+            std::process::exit(EXIT_NOT_FOUND);
         }
     }
 
     false
 }
 
-fn add(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
-    let target = matches
-        .get_one::<String>("TARGET")
-        .or_else(|| matches.get_one::<String>("TARGET_POS"))
-        .map(|s| s.as_str())
-        .unwrap();
-    let name = matches
-        .get_one::<String>("NAME")
-        .or_else(|| matches.get_one::<String>("NAME_POS"))
-        .map(|s| s.as_str())
-        .unwrap();
-    let weight_str = matches
-        .get_one::<String>("WEIGHT")
-        .or_else(|| matches.get_one::<String>("WEIGHT_POS"))
-        .map(|s| s.as_str())
-        .unwrap();
+fn display(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let name = matches.get_one::<String>("NAME").unwrap();
 
-    let weight: i32 = match weight_str.parse() {
-        Ok(w) => w,
-        Err(e) => {
-            eprintln!("update-alternatives: could not parse {} as \
-                      weight: {}", weight_str, e);
+    let list = match db.alternatives(name) {
+        Some(l) => l,
+        None => {
+            error!(NOT_FOUND, "no alternatives found for {}", name);
+            std::process::exit(EXIT_NOT_FOUND);
+        },
+    };
 
-            std::process::exit(1);
+    // Laid out as dpkg's update-alternatives --display, for the scripts and
+    // people who know it.
+    let until = match list.manual().and_then(|m| m.until()) {
+        Some(u) if list.mode() == "manual" => {
+            format!(" until {}", audit::timestamp(u))
         },
+        _ => String::new(),
     };
 
-    if db.add_alternative(name, Alternative::from_parts(target, weight)) {
-        println!("update-alternatives: added alternative {} for {} with \
-                 priority {}", target, name, weight);
+    println!("{} - {} mode{}", name, list.mode(), until);
 
-        return true;
+    match list.best() {
+        Some(best) => println!("  link best version is {}",
+                               best.target().display()),
+        None => println!("  link best version not available"),
     }
 
-    false
-}
+    match list.linked_target() {
+        Some(target) => println!("  link currently points to {}",
+                                 target.display()),
+        None => println!("  link currently absent"),
+    }
 
-fn remove(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
-    let target = matches
-        .get_one::<String>("TARGET")
-        .or_else(|| matches.get_one::<String>("TARGET_POS"))
-        .map(|s| s.as_str())
-        .unwrap();
-    let name = matches
-        .get_one::<String>("NAME")
-        .or_else(|| matches.get_one::<String>("NAME_POS"))
-        .map(|s| s.as_str())
-        .unwrap();
+    println!("  link {} is {}", name, list.path().display());
 
-    if db.remove_alternative(name, target) {
-        println!("update-alternatives: removed alternative {} for {}",
-                 target, name);
+    for link in list.follower_links() {
+        println!("  follower {}", link.display());
+    }
 
-        return true;
+    for alternative in list.links() {
+        let problem = list.problem(alternative)
+                          .map(|p| format!(" [{}]", p))
+                          .unwrap_or_default();
+
+        println!("{} - priority {}{}", alternative.target().display(),
+                 alternative.priority(), problem);
+
+        for follower in alternative.followers() {
+            println!("  follower {}: {}", follower.link.display(),
+                     follower.target.display());
+        }
+    }
+
+    if matches.get_flag("RESOLVE") {
+        print_chain(db.root(), list.path());
     }
 
     false
 }
 
-fn commit(db: &AlternativeDb) -> std::io::Result<()> {
-    if let Err(e) = db.write_out("/etc/alternatives") {
-        eprintln!("update-alternatives: could not commit changes to \
-                  /etc/alternatives: {}", e);
+// Prints the group in the format of dpkg's update-alternatives --query, so
+// that tools which parse it work unchanged. Followers have no names of their
+// own here, so the file names of their links stand in for them.
+fn query(db: &AlternativeDb, matches: &clap::ArgMatches,
+         out: &mut Option<std::fs::File>) -> bool {
+    use std::fmt::Write;
 
-        Err(e)
-    } else if let Err(e) = db.write_links() {
-        eprintln!("update-alternatives: could not write symlinks: {}", e);
+    let name = matches.get_one::<String>("NAME").unwrap();
 
-        Err(e)
-    } else {
-        Ok(())
-    }
-}
+    let list = db.alternatives(name).unwrap_or_else(|| {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
 
-fn run_gui(db: &mut AlternativeDb) -> bool {
-    use std::process::Command;
+    let follower_name = |link: &std::path::Path| {
+        link.file_name().map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| link.display().to_string())
+    };
 
-    // Check for zenity
-    let has_zenity = Command::new("sh")
-        .arg("-c")
-        .arg("command -v zenity >/dev/null 2>&1")
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false);
+    let mut text = String::new();
+    let _ = writeln!(text, "Name: {}", name);
+    let _ = writeln!(text, "Link: {}", list.path().display());
 
-    if !has_zenity {
-        eprintln!("update-alternatives: --gui requested but 'zenity' was not found in PATH. Please install 'zenity' or run without --gui.");
-        return false;
+    let followers = list.follower_links();
+
+    if !followers.is_empty() {
+        text.push_str("Slaves:\n");
+
+        for link in followers {
+            let _ = writeln!(text, " {} {}", follower_name(link),
+                             link.display());
+        }
     }
 
-    fn run_privileged(args: &[&str]) -> std::io::Result<std::process::ExitStatus> {
-        let exe = std::env::current_exe().unwrap_or_else(|_| std::path::PathBuf::from("update-alternatives"));
-        Command::new("pkexec").arg(&exe).args(args).status()
-            .or_else(|_| Command::new("sudo").arg(&exe).args(args).status())
+    let _ = writeln!(text, "Status: {}", list.mode());
+
+    if let Some(best) = list.best() {
+        let _ = writeln!(text, "Best: {}", best.target().display());
     }
 
-    loop {
-        let mut rows: Vec<(String, String)> = Vec::new();
-        for (name, list) in db.iter() {
-            let current = list
-                .current_target()
-                .map(|p| p.display().to_string())
-                .unwrap_or_else(|| String::from("<none>"));
-            rows.push((name.clone(), current));
-        }
-        rows.sort_by(|a, b| a.0.cmp(&b.0));
+    let value = list.linked_target()
+                    .map(|t| t.display().to_string())
+                    .unwrap_or_else(|| String::from("none"));
+    let _ = writeln!(text, "Value: {}", value);
 
-        let menu_out = match Command::new("zenity").args([
-            "--list", "--title", "update-alternatives",
-            "--text", "Choose an action",
-            "--width", "500", "--height", "300",
-            "--column", "Action",
-            "Add", "Remove", "Adjust priority", "Sync", "Close",
-        ]).output() {
-            Ok(o) => o,
-            Err(e) => { eprintln!("update-alternatives: failed to launch zenity: {}", e); return false; }
-        };
-        if !menu_out.status.success() {
-            return false; 
+    for alternative in list.links() {
+        let _ = writeln!(text, "\nAlternative: {}",
+                         alternative.target().display());
+        let _ = writeln!(text, "Priority: {}", alternative.priority());
+
+        if !alternative.followers().is_empty() {
+            text.push_str("Slaves:\n");
+
+            for follower in alternative.followers() {
+                let _ = writeln!(text, " {} {}", follower_name(&follower.link),
+                                 follower.target.display());
+            }
         }
-        let choice = String::from_utf8_lossy(&menu_out.stdout).trim().to_string();
-        match choice.as_str() {
-            "Close" => return false,
-            "Sync" => {
-                match run_privileged(&["sync"]) {
-                    Ok(s) if s.success() => { let _ = Command::new("zenity").args(["--info","--text","Symlinks were rewritten.","--title","update-alternatives"]).status(); }
-                    Ok(s) => { let _ = Command::new("zenity").args(["--error","--text", &format!("Sync failed (exit {:?}).", s.code()), "--title","update-alternatives"]).status(); }
-                    Err(e) => { let _ = Command::new("zenity").args(["--error","--text", &format!("Sync failed: {}", e), "--title","update-alternatives"]).status(); }
-                }
-            }
-            "Add" => {
-                let form = match Command::new("zenity").args([
-                    "--forms", "--title", "Add alternative",
-                    "--text", "Enter name, target and priority",
-                    "--add-entry", "Name",
-                    "--add-entry", "Target path",
-                    "--add-entry", "Priority (integer)",
-                    "--width", "500",
-                ]).output() { Ok(o) => o, Err(e) => { eprintln!("zenity error: {}", e); return false; } };
-                if !form.status.success() { continue; }
-                let resp = String::from_utf8_lossy(&form.stdout).trim().to_string();
-                let mut parts = resp.split('|');
-                let name = parts.next().unwrap_or("").trim();
-                let target = parts.next().unwrap_or("").trim();
-                let weight = parts.next().unwrap_or("").trim();
-                if name.is_empty() || target.is_empty() || weight.is_empty() { let _=Command::new("zenity").args(["--error","--text","All fields are required.","--title","update-alternatives"]).status(); continue; }
-                if weight.parse::<i32>().is_err() { let _=Command::new("zenity").args(["--error","--text","Priority must be an integer.","--title","update-alternatives"]).status(); continue; }
-                match run_privileged(&["add","-n", name, "-t", target, "-w", weight]) {
-                    Ok(s) if s.success() => { let _=Command::new("zenity").args(["--info","--text","Alternative added/updated.","--title","update-alternatives"]).status(); }
-                    Ok(s) => { let _=Command::new("zenity").args(["--error","--text", &format!("Add failed (exit {:?}).", s.code()), "--title","update-alternatives"]).status(); }
-                    Err(e) => { let _=Command::new("zenity").args(["--error","--text", &format!("Add failed: {}", e), "--title","update-alternatives"]).status(); }
-                }
+    }
+
+    let out = out.as_mut().expect("stdout is taken for query");
+
+    if let Err(e) = std::io::Write::write_all(out, text.as_bytes()) {
+        error!(IO, "could not write output: {}", e);
+        std::process::exit(1);
+    }
+
+    false
+}
+
+// The priority that the weight policy of the group name gives target, which
+// was added without one. A target already in the group keeps its own.
+fn weight_from_policy(db: &AlternativeDb, name: &str,
+                      target: &std::path::Path) -> i64 {
+    let existing = db.alternatives(name).and_then(|l| {
+        l.links().iter().find(|a| a.target() == target)
+    });
+
+    if let Some(existing) = existing {
+        println!("update-alternatives: keeping priority {} of {}",
+                 existing.priority(), target.display());
+
+        return existing.priority();
+    }
+
+    let weights = db.alternatives(name).map(|l| l.weights())
+                                       .unwrap_or_default();
+
+    match weights {
+        Weights::Explicit if !db.has_alternatives(name) => {
+            error!(PRIORITY, "{} is a new group: give a \
+                             priority, or --weight-from-version", name);
+            std::process::exit(1);
+        },
+        Weights::Explicit => {
+            error!(PRIORITY, "the weight policy of {} is {}: \
+                             give a priority, or --weight-from-version",
+                   name, weights);
+            std::process::exit(1);
+        },
+        Weights::Version => weight_from_version(db.root(), name, target),
+        Weights::Fixed(_) | Weights::Step(_) => {
+            let list = db.alternatives(name).unwrap();
+            let weight = list.next_priority().unwrap().unwrap_or_else(|e| {
+                error!(PRIORITY, "weight policy {} of {}: {}",
+                       weights, name, e);
+                std::process::exit(1);
+            });
+
+            println!("update-alternatives: priority {} from weight policy {} \
+                     of {}", weight, weights, name);
+
+            weight
+        },
+    }
+}
+
+// Derives a priority from the version in the path of target or the one it
+// reports when run, so that newer versions win on their own. A bare major
+// version in the path, as in python3, is only used if running it fails.
+fn weight_from_version(root: &std::path::Path, name: &str,
+                       target: &std::path::Path) -> i64 {
+    let from_path = version::from_path(target);
+
+    let version = match from_path {
+        Some(v) if v.contains('.') => v,
+        // Programs inside another root are not run.
+        _ if root != std::path::Path::new("/") => from_path.unwrap_or_else(|| {
+            error!(PRIORITY, "no version in the path of {}, and programs \
+                             below --root are not run", target.display());
+            std::process::exit(1);
+        }),
+        _ => {
+            let settings = match load_config(root) {
+                Ok(c) => c.probe,
+                Err(_) => std::process::exit(1),
+            };
+
+            match probe::version(target, &settings.arguments(name),
+                                 settings.timeout()) {
+                Ok(v) => v,
+                Err(_) if from_path.is_some() => from_path.unwrap(),
+                Err(e) => {
+                    error!(PRIORITY, "no version in the path of \
+                                     {}, and running it failed: {}",
+                           target.display(), e);
+                    std::process::exit(1);
+                },
             }
-            "Remove" | "Adjust priority" => {
-                if rows.is_empty() { let _=Command::new("zenity").args(["--warning","--text","No alternatives available.","--title","update-alternatives"]).status(); continue; }
-                let mut name_list_args = vec!["--list","--title","Select name","--column","Name"]; 
-                for (n, _) in &rows { name_list_args.push(n); }
-                let name_out = match Command::new("zenity").args(&name_list_args).output() { Ok(o)=>o, Err(e)=>{ eprintln!("zenity error: {}", e); return false; } };
-                if !name_out.status.success() { continue; }
-                let selected_name = String::from_utf8_lossy(&name_out.stdout).trim().to_string();
-                if selected_name.is_empty() { continue; }
-                let mut alt_rows: Vec<(String, i32)> = Vec::new();
-                if let Some(list) = db.alternatives(&selected_name) { for a in list.links() { alt_rows.push((a.target().display().to_string(), a.priority())); } }
-                if alt_rows.is_empty() { let _=Command::new("zenity").args(["--warning","--text","No targets for this name.","--title","update-alternatives"]).status(); continue; }
-                let mut alt_args: Vec<String> = vec!["--list".into(),"--title".into(),format!("{}: select target", selected_name),"--width".into(),"700".into(),"--column".into(),"Target".into(),"--column".into(),"Priority".into()];
-                for (t, w) in &alt_rows { alt_args.push(t.clone()); alt_args.push(w.to_string()); }
-                let alt_out = match Command::new("zenity").args(&alt_args).output() { Ok(o)=>o, Err(e)=>{ eprintln!("zenity error: {}", e); return false; } };
-                if !alt_out.status.success() { continue; }
-                let selected_target = String::from_utf8_lossy(&alt_out.stdout).trim().to_string();
-                if selected_target.is_empty() { continue; }
-                if choice == "Remove" {
-                    match run_privileged(&["remove","-n", &selected_name, "-t", &selected_target]) {
-                        Ok(s) if s.success() => { let _=Command::new("zenity").args(["--info","--text","Alternative removed.","--title","update-alternatives"]).status(); }
-                        Ok(s) => { let _=Command::new("zenity").args(["--error","--text", &format!("Remove failed (exit {:?}).", s.code()), "--title","update-alternatives"]).status(); }
-                        Err(e) => { let _=Command::new("zenity").args(["--error","--text", &format!("Remove failed: {}", e), "--title","update-alternatives"]).status(); }
-                    }
-                } else {
-                    let pr_out = match Command::new("zenity").args(["--entry","--title","Set priority","--text","Enter new priority (integer)"]).output() { Ok(o)=>o, Err(e)=>{ eprintln!("zenity error: {}", e); return false; } };
-                    if !pr_out.status.success() { continue; }
-                    let new_w = String::from_utf8_lossy(&pr_out.stdout).trim().to_string();
-                    if new_w.parse::<i32>().is_err() { let _=Command::new("zenity").args(["--error","--text","Priority must be an integer.","--title","update-alternatives"]).status(); continue; }
-                    match run_privileged(&["add","-n", &selected_name, "-t", &selected_target, "-w", &new_w]) {
-                        Ok(s) if s.success() => { let _=Command::new("zenity").args(["--info","--text","Priority updated.","--title","update-alternatives"]).status(); }
-                        Ok(s) => { let _=Command::new("zenity").args(["--error","--text", &format!("Update failed (exit {:?}).", s.code()), "--title","update-alternatives"]).status(); }
-                        Err(e) => { let _=Command::new("zenity").args(["--error","--text", &format!("Update failed: {}", e), "--title","update-alternatives"]).status(); }
-                    }
-                }
+        },
+    };
+
+    let weight = version::priority(&version)
+                         .and_then(|p| check_priority(p).ok())
+                         .unwrap_or_else(|| {
+        error!(PRIORITY, "cannot derive a priority from version \
+                         {} of {}", version, target.display());
+        std::process::exit(1);
+    });
+
+    eprintln!("update-alternatives: priority {} from version {} of {}", weight,
+              version, target.display());
+
+    weight
+}
+
+// Prints everything known about one alternative of a group.
+fn show(db: &AlternativeDb, log: &AuditLog, matches: &clap::ArgMatches)
+-> bool {
+    let name = matches.get_one::<String>("NAME").unwrap();
+    let target = matches.get_one::<std::path::PathBuf>("TARGET").unwrap();
+
+    let list = db.alternatives(name).unwrap_or_else(|| {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
+    let alternative = list.links().iter()
+                          .find(|a| a.target() == target.as_path())
+                          .unwrap_or_else(|| {
+        error!(NOT_MEMBER, "{} is not an alternative for {}",
+               target.display(), name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
+
+    let linked = list.linked_target();
+    let selected = if linked.as_deref() == Some(alternative.target()) {
+        format!("yes, {} ({})", list.path().display(),
+                list.selection(linked.as_deref()))
+    } else {
+        String::from("no")
+    };
+    let package = match alternative.package() {
+        Some(p) => format!("{} (recorded)", p),
+        None => match package::detect(db.root()) {
+            Some(provider) => provider.owner(alternative.target())
+                                      .map(|p| p.to_string())
+                                      .unwrap_or_else(|| String::from("none")),
+            None => String::from("unknown"),
+        },
+    };
+    let file = filesystem::under(db.root(), alternative.target());
+    let checksum = manifest::checksum(&file)
+                            .unwrap_or_else(|e| format!("unknown ({})", e));
+    let details = std::fs::metadata(&file)
+                          .map(|m| file_details(&m))
+                          .unwrap_or_else(|e| e.to_string());
+
+    println!("update-alternatives: {} for {}:", alternative.target().display(),
+             name);
+    println!("  priority: {}", alternative.priority());
+    println!("  state: {}", list.problem(alternative).unwrap_or("ok"));
+    println!("  selected: {}", selected);
+
+    for follower in alternative.followers() {
+        println!("  follower: {} -> {}", follower.link.display(),
+                 follower.target.display());
+    }
+
+    println!("  package: {}", package);
+    println!("  sha256: {}", checksum);
+    println!("  file: {}", details);
+    println!("  last added: {}",
+             last_logged(log.path(),
+                         &format!("add {} {}", name, target.display()))
+                 .unwrap_or_else(|| String::from("unknown")));
+
+    false
+}
+
+// Prints why the link of a group points where it does: the mode, the
+// candidates by priority, and what decided between them.
+fn explain(db: &AlternativeDb, log: &AuditLog, matches: &clap::ArgMatches)
+-> bool {
+    let name = matches.get_one::<String>("NAME").unwrap();
+
+    let list = db.alternatives(name).unwrap_or_else(|| {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
+    let exists = |a: &Alternative| {
+        filesystem::under(db.root(), a.target()).exists()
+    };
+    let selected = list.selected();
+    let now = std::time::SystemTime::now();
+    let manual = list.manual().filter(|m| {
+        selected.is_some_and(|s| s.target() == m.target())
+            && !m.is_expired(now)
+    });
+
+    println!("update-alternatives: {} ({}):", name, list.path().display());
+
+    match (manual, list.manual()) {
+        (Some(m), _) => {
+            let set = last_logged(log.path(), &format!("set {} {}", name,
+                                                       m.target().display()));
+
+            println!("  mode: manual, set {}{}",
+                     set.unwrap_or_else(|| String::from("at an unknown time")),
+                     m.until().map(|u| {
+                         format!(", until {}", audit::timestamp(u))
+                     }).unwrap_or_default());
+        },
+        (None, Some(m)) if m.is_expired(now) => {
+            println!("  mode: auto; the manual selection of {} expired at {}, \
+                     run 'update-alternatives expire' to clear it",
+                     m.target().display(),
+                     m.until().map(audit::timestamp).unwrap_or_default());
+        },
+        (None, Some(m)) => {
+            println!("  mode: auto; the manual target {} is missing from the \
+                     group, falling back to auto", m.target().display());
+        },
+        (None, None) => println!("  mode: auto"),
+    }
+
+    let mut candidates: Vec<&Alternative> = list.links().iter().collect();
+    let width = candidates.iter().map(|a| a.target().display().to_string()
+                                           .chars().count())
+                          .max().unwrap_or(0);
+
+    // Equal priorities keep the order they were added in.
+    candidates.sort_by_key(|a| std::cmp::Reverse(a.priority()));
+
+    println!("  candidates, highest priority first:");
+
+    for alternative in candidates.iter() {
+        let mark = if selected.is_some_and(|s| std::ptr::eq(s, *alternative)) {
+            "*"
+        } else {
+            " "
+        };
+        let note = match list.problem(alternative) {
+            Some("missing") => "  missing",
+            Some("not a file") => "  not a file",
+            Some(_) => "  not executable",
+            None => "",
+        };
+
+        println!("  {} {:<width$} {:>6}{}", mark,
+                 alternative.target().display().to_string(),
+                 alternative.priority(), note, width = width);
+    }
+
+    match selected {
+        None => println!("  decision: there are no alternatives"),
+        Some(s) if manual.is_some() => {
+            println!("  decision: {} was selected by hand, regardless of \
+                     priorities", s.target().display());
+        },
+        Some(s) => {
+            let tied: Vec<String> = candidates.iter()
+                .filter(|a| a.priority() == s.priority()
+                            && !std::ptr::eq(**a, s))
+                .map(|a| a.target().display().to_string())
+                .collect();
+            let missing = if exists(s) {
+                ""
+            } else {
+                ", although it is missing"
+            };
+
+            if tied.is_empty() {
+                println!("  decision: {} has the highest priority{}",
+                         s.target().display(), missing);
+            } else {
+                println!("  decision: {} shares priority {} with {}, and \
+                         wins as the one added last{}", s.target().display(),
+                         s.priority(), tied.join(", "), missing);
             }
-            _ => { }
+        },
+    }
+
+    match (list.linked_target(), selected) {
+        (None, _) => {
+            println!("  link: missing; 'update-alternatives sync' creates it");
+        },
+        (Some(ref l), Some(s)) if l == s.target() => {
+            println!("  link: {} -> {}", list.path().display(), l.display());
+        },
+        (Some(l), _) => {
+            println!("  link: points to {} instead, changed by hand; \
+                     'update-alternatives sync' restores it", l.display());
+        },
+    }
+
+    false
+}
+
+// When and by whom the audit log last recorded entry, such as
+// "add cc /usr/bin/clang".
+fn last_logged(log: &std::path::Path, entry: &str) -> Option<String> {
+    let contents = std::fs::read_to_string(log).ok()?;
+
+    audit::entries(&contents).iter().rev()
+        .find(|e| {
+            e.message.strip_prefix(entry)
+                     .is_some_and(|r| r.is_empty() || r.starts_with(' '))
+        })
+        .map(|e| format!("{} by {}{}", e.time, e.user, sequence(e)))
+}
+
+// The number of an audit log entry as shown next to it, if it has one.
+fn sequence(entry: &audit::Entry) -> String {
+    entry.sequence.map(|n| format!(" (#{})", n)).unwrap_or_default()
+}
+
+// Prints the audit log entry that last added each alternative of a group or
+// changed its priority, or moved it to its target, like git blame.
+fn blame(db: &AlternativeDb, log: &AuditLog, matches: &clap::ArgMatches)
+-> bool {
+    let name = matches.get_one::<String>("NAME").unwrap();
+
+    let list = db.alternatives(name).unwrap_or_else(|| {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
+    let contents = match std::fs::read_to_string(log.path()) {
+        Ok(c) => c,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            error!(IO, "could not read {}: {}", log.path().display(), e);
+            std::process::exit(1);
+        },
+    };
+    let entries = audit::entries(&contents);
+
+    // Renumbering gives every alternative of the group its priority.
+    let renumbered = format!("renumber {} ", name);
+    let mut table = table::Table::new(&["TARGET", "PRIORITY", "SEQ", "TIME",
+                                        "USER", "OPERATION"]);
+
+    for alternative in list.links() {
+        let target = alternative.target().display().to_string();
+        let added = format!("add {} {} ", name, target);
+        let adopted = format!("adopt {} {} ", name, target);
+        let retargeted = format!(" {}", target);
+        let copied = format!(" {} {}", name, list.path().display());
+        let entry = entries.iter().rev().find(|e| {
+            e.message.strip_prefix(&added)
+                     .or_else(|| e.message.strip_prefix(&adopted))
+                     .or_else(|| e.message.strip_prefix(&renumbered))
+                     .is_some_and(|w| w.parse::<i64>().is_ok())
+                || (e.message.starts_with("retarget ")
+                    && e.message.ends_with(&retargeted))
+                || e.message.strip_prefix("copy ")
+                            .is_some_and(|c| c.contains(&copied))
+        });
+        let mut row = vec![target, alternative.priority().to_string()];
+
+        match entry {
+            Some(e) => {
+                row.push(e.sequence.map(|n| format!("#{}", n))
+                                   .unwrap_or_else(|| String::from("-")));
+                row.extend([e.time, e.user, e.message]
+                               .iter().map(|s| s.to_string()));
+            },
+            None => row.extend(["-", "-", "-", "not in the audit log"]
+                                   .iter().map(|s| s.to_string())),
         }
 
-        if let Ok(new_db) = read_db("/etc/alternatives") { *db = new_db; }
+        table.push(row);
+    }
+
+    println!("update-alternatives: {} ({}):", name, list.path().display());
+    table.print(matches.get_flag("WIDE"));
+
+    false
+}
+
+// Prints every hop from link to the file it finally resolves to, flagging a
+// hop that points at nothing or back into the chain.
+fn print_chain(root: &std::path::Path, link: &std::path::Path) {
+    let chain = filesystem::symlink_chain(root, link);
+
+    println!("link chain:");
+
+    for (i, path) in chain.iter().enumerate() {
+        let arrow = if i == 0 { "  " } else { "  -> " };
+        let under = filesystem::under(root, path);
+        let problem = match under.symlink_metadata() {
+            Err(_) => " [broken]",
+            Ok(_) if chain[..i].contains(path) => " [loop]",
+            Ok(m) if m.file_type().is_symlink() && i + 1 == chain.len() => {
+                " [too many links]"
+            },
+            Ok(_) => "",
+        };
+
+        println!("{}{}{}", arrow, path.display(), problem);
     }
 }
 
-fn sync(db: &AlternativeDb) -> bool {
-    if let Err(e) = db.write_links() {
-        eprintln!("update-alternatives: could not write symlinks: {}", e);
+// A --regex, or else the glob in the glob argument, selects groups by name.
+fn group_filter(matches: &clap::ArgMatches, glob: &str) -> filter::Filter {
+    let filter = match (matches.get_one::<String>("REGEX"),
+                        matches.get_one::<String>(glob)) {
+        (Some(r), _) => filter::Filter::regex(r),
+        (None, Some(g)) => filter::Filter::glob(g),
+        (None, None) => Ok(filter::Filter::All),
+    };
+
+    filter.unwrap_or_else(|e| {
+        error!(BAD_ARGUMENT, "invalid pattern: {}", e);
+        std::process::exit(1);
+    })
+}
+
+static ALTERNATIVE_COLUMNS: &[&str] = &["name", "priority", "target", "state"];
+
+static GROUP_COLUMNS: &[&str] = &["name", "link", "current", "mode"];
+
+fn alternatives_table() -> table::Table {
+    table::Table::new(&["NAME", "PRIORITY", "TARGET", "STATE", "LINK",
+                        "CURRENT", "MODE", "COUNT"])
+}
+
+fn print_table(mut table: table::Table, matches: &clap::ArgMatches,
+               default: &[&str]) {
+    select_columns(&mut table, matches, default);
+
+    table.print(matches.get_flag("WIDE"));
+}
+
+fn select_columns(table: &mut table::Table, matches: &clap::ArgMatches,
+                  default: &[&str]) {
+    let columns: Vec<&str> = match matches.get_many::<String>("COLUMNS") {
+        Some(c) => c.map(|c| c.as_str()).collect(),
+        None => default.to_vec(),
+    };
+
+    if let Err(c) = table.select(&columns) {
+        error!(BAD_ARGUMENT, "unknown column {}; choose from {}", c,
+               table.columns().join(","));
         std::process::exit(1);
     }
+}
+
+// The group columns are repeated on every row of the group.
+fn group_cells(list: &alternative_list::AlternativeList,
+               linked: Option<&std::path::Path>) -> Vec<String> {
+    vec![list.path().display().to_string(),
+         linked.map(|t| t.display().to_string())
+               .unwrap_or_else(|| String::from("<none>")),
+         list.selection(linked).to_string(),
+         list.links().len().to_string()]
+}
+
+fn push_alternatives<P: FnMut(Vec<String>), F: Fn(&Alternative) -> bool>(
+    mut push: P, name: &str,
+    list: &alternative_list::AlternativeList, keep: F
+) {
+    let linked = list.linked_target();
+
+    for alternative in list.links().iter().filter(|a| keep(a)) {
+        let selected = linked.as_deref() == Some(alternative.target());
+        let state: Vec<&str> = selected.then_some("selected").into_iter()
+                                       .chain(list.problem(alternative))
+                                       .collect();
+
+        let mut row = vec![name.to_string(),
+                           alternative.priority().to_string(),
+                           alternative.target().display().to_string(),
+                           state.join(", ")];
+        row.extend(group_cells(list, linked.as_deref()));
+
+        push(row);
+    }
+}
+
+fn search(db: &AlternativeDb, matches: &clap::ArgMatches,
+          out: &mut Option<std::fs::File>) -> bool {
+    let pattern = matches.get_one::<String>("PATTERN").unwrap().as_str();
+    let found = |name: &str, alternative: &Alternative| {
+        name.contains(pattern)
+            || alternative.target().to_string_lossy().contains(pattern)
+    };
+
+    if matches.get_flag("PRINT0") {
+        let groups = filter::groups(db, &filter::Filter::All);
+
+        print0(out, groups.into_iter().flat_map(|(name, list)| {
+            list.links().iter().filter(move |a| found(name, a))
+                               .map(|a| a.target())
+        }));
+
+        return false;
+    }
+
+    let mut table = alternatives_table();
+
+    for (name, list) in filter::groups(db, &filter::Filter::All) {
+        push_alternatives(|row| table.push(row), name, list,
+                          |a| found(name, a));
+    }
+
+    if table.is_empty() {
+        error!(NOT_FOUND, "no alternatives match {}", pattern);
+        std::process::exit(EXIT_NOT_FOUND);
+    }
+
+    print_table(table, matches, ALTERNATIVE_COLUMNS);
 
     false
 }
 
+fn list_detailed(db: &AlternativeDb, name: &str,
+                 list: &alternative_list::AlternativeList, verbose: bool,
+                 probe: bool) {
+    let provider = if verbose { package::detect(db.root()) } else { None };
+    let linked = list.linked_target();
+    let settings = if probe {
+        // Programs inside another root are not run.
+        if db.root() != std::path::Path::new("/") {
+            error!(UNSUPPORTED, "--probe does not support --root");
+            std::process::exit(1);
+        }
 
-fn app() -> clap::Command {
-    use clap::{Arg, Command};
-    Command::new("update-alternatives")
-        .version(clap::crate_version!())
-        .author(clap::crate_authors!())
-        .about(ABOUT)
-        .arg(
-            Arg::new("gui")
-                .help("Launch a simple graphical interface for listing and syncing alternatives")
-                .long("gui")
-                .action(clap::ArgAction::SetTrue)
+        match load_config(db.root()) {
+            Ok(c) => Some(c.probe),
+            Err(_) => std::process::exit(1),
+        }
+    } else {
+        None
+    };
+
+    println!("update-alternatives: alternatives for {} ({}):",
+             list.path().display(), list.selection(linked.as_deref()));
+
+    for alternative in list.links() {
+        let mark = if linked.as_deref() == Some(alternative.target()) {
+            '*'
+        } else {
+            ' '
+        };
+        let problem = list.problem(alternative);
+        let version = match (settings.as_ref(), problem) {
+            (Some(s), None) => match probe::version(alternative.target(),
+                                                    &s.arguments(name),
+                                                    s.timeout()) {
+                Ok(v) => format!(" ({})", v),
+                Err(e) => format!(" (version unknown: {})", e),
+            },
+            _ => String::new(),
+        };
+
+        match problem {
+            Some(p) => println!("  {} {} [{}]{}", mark, alternative, p,
+                                version),
+            None => println!("  {} {}{}", mark, alternative, version),
+        }
+
+        if !verbose {
+            continue;
+        }
+
+        let owner = match provider {
+            Some(ref p) => p.owner(alternative.target())
+                            .map(|p| format!("package {}", p))
+                            .unwrap_or_else(|| String::from("no package")),
+            None => String::from("unknown package"),
+        };
+
+        println!("      {}", owner);
+
+        let target = filesystem::under(db.root(), alternative.target());
+
+        if let Ok(metadata) = std::fs::metadata(&target) {
+            println!("      {}", file_details(&metadata));
+        }
+    }
+}
+
+fn file_details(metadata: &std::fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    let user = audit::user_name(nix::unistd::Uid::from_raw(metadata.uid()));
+    let gid = nix::unistd::Gid::from_raw(metadata.gid());
+    let group = match nix::unistd::Group::from_gid(gid) {
+        Ok(Some(g)) => g.name,
+        _ => gid.to_string(),
+    };
+    let modified = metadata.modified()
+                           .map(audit::timestamp)
+                           .unwrap_or_else(|_| String::from("unknown"));
+
+    format!("{} bytes, {}:{} {:04o}, modified {}", metadata.len(), user, group,
+            metadata.mode() & 0o7777, modified)
+}
+
+// Names and paths are checked while parsing, before the program escalates its
+// privileges or touches anything, instead of failing with ENAMETOOLONG later.
+fn path_parser() -> impl clap::builder::TypedValueParser<
+    Value = std::path::PathBuf
+> {
+    use clap::builder::TypedValueParser;
+
+    clap::builder::OsStringValueParser::new().try_map(|s| {
+        let path = std::path::PathBuf::from(s);
+
+        filesystem::check_path(&path).map(|_| path)
+    })
+}
+
+fn parse_name(name: &str) -> Result<String, String> {
+    alternative_db::check_name(name).map(|_| name.to_string())
+}
+
+// Tags are words such as toolchain or site-local, so that they can be given
+// on the command line and in the audit log without quoting.
+fn parse_tag(tag: &str) -> Result<String, String> {
+    let is_word = tag.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && tag.chars().all(|c| c.is_ascii_alphanumeric()
+                               || matches!(c, '-' | '_' | '.'));
+
+    if !is_word {
+        return Err(format!("'{}' is not a tag such as toolchain or \
+                           site-local", tag));
+    }
+
+    Ok(tag.to_string())
+}
+
+// Weights are checked against the documented range while parsing, so that
+// every subcommand taking one rejects the same values.
+fn weight_parser() -> clap::builder::RangedI64ValueParser<i64> {
+    clap::value_parser!(i64).range(alternative::MIN_PRIORITY
+                                   ..=alternative::MAX_PRIORITY)
+}
+
+fn add(db: &mut AlternativeDb, log: &mut AuditLog, matches: &clap::ArgMatches,
+       out: &mut Option<std::fs::File>) -> bool {
+    let target = matches
+        .get_one::<std::path::PathBuf>("TARGET")
+        .or_else(|| matches.get_one::<std::path::PathBuf>("TARGET_POS"))
+        .unwrap();
+    let name = matches
+        .get_one::<String>("NAME")
+        .or_else(|| matches.get_one::<String>("NAME_POS"))
+        .map(|s| s.as_str())
+        .unwrap();
+    let weight = match matches.get_one::<i64>("WEIGHT")
+                              .or_else(|| matches.get_one::<i64>("WEIGHT_POS")) {
+        Some(w) => *w,
+        None if matches.get_flag("WEIGHT_FROM_VERSION") => {
+            weight_from_version(db.root(), name, target)
+        },
+        None => weight_from_policy(db, name, target),
+    };
+
+    if db.root() != std::path::Path::new("/") {
+        // Targets are stored as seen from inside the root.
+        if !target.is_absolute() {
+            error!(BAD_TARGET, "target {} must be an absolute path inside {}",
+                   target.display(), db.root().display());
+            std::process::exit(1);
+        }
+
+        if !filesystem::under(db.root(), target).exists() {
+            eprintln!("update-alternatives: warning: {} does not exist in {}",
+                      target.display(), db.root().display());
+        }
+    }
+
+    let mime_types: Vec<String> = matches.get_many::<String>("MIME_TYPE")
+                                         .map(|m| m.cloned().collect())
+                                         .unwrap_or_default();
+    let kind = match db.alternatives(name) {
+        Some(list) => list.kind().clone(),
+        None if !mime_types.is_empty() => Kind::Xdg{ mime_types },
+        None if matches.get_flag("LIBRARY") => Kind::Library,
+        None if matches.get_flag("UNIT") => Kind::Unit,
+        None => Kind::Symlink,
+    };
+    let link = match (db.alternatives(name),
+                      matches.get_one::<std::path::PathBuf>("LINK")) {
+        (Some(list), _) => list.path().to_path_buf(),
+        (None, Some(l)) => l.clone(),
+        (None, None) => match kind {
+            Kind::Symlink => alternative_db::default_link(name),
+            Kind::Library => std::path::Path::new(alternative_db::LIBRARY_DIR)
+                                            .join(name),
+            Kind::Unit => std::path::Path::new(generate::SYSTEMD_UNIT_DIR)
+                                         .join(name),
+            Kind::Xdg{ .. } => std::path::PathBuf::from(xdg::MIMEAPPS_PATH),
+        },
+    };
+
+    // A new group may not take over a link that another one manages. Groups
+    // of default applications all share mimeapps.list.
+    if db.alternatives(name).is_none() && !matches!(kind, Kind::Xdg{ .. }) {
+        let owner = db.iter().find(|(_, list)| {
+            list.path() == link || list.follower_links().contains(link.as_path())
+        });
+
+        if let Some((other, _)) = owner {
+            error!(CONFLICT, "link {} is managed by {} already",
+                   link.display(), other);
+            std::process::exit(1);
+        }
+    }
+
+    if matches!(kind, Kind::Xdg{ .. })
+       && target.extension().and_then(|e| e.to_str()) != Some("desktop") {
+        error!(BAD_TARGET, "target {} of {} must be a desktop \
+                           file", target.display(), name);
+        std::process::exit(1);
+    }
+
+    // systemd only follows aliases to units of the same type.
+    if kind == Kind::Unit && (link.extension().is_none()
+                              || target.extension() != link.extension()) {
+        error!(BAD_TARGET, "target {} of {} must be a unit of the same type \
+                           as {}", target.display(), name, link.display());
+        std::process::exit(1);
+    }
+
+    let followers = followers(db, name, &link, &kind, matches);
+    let existed = db.alternatives(name)
+                    .is_some_and(|l| l.links().iter()
+                                      .any(|a| a.target() == target));
+    let alternative = Alternative::from_parts(target, weight)
+                                  .with_followers(followers);
+    let changed = db.add_alternative_with_link(name, &link, alternative)
+                    .unwrap_or_else(|e| {
+                        error!(e);
+                        std::process::exit(1);
+                    });
+    let changed = db.set_kind(name, kind) || changed;
+
+    // Adding what is already there succeeds, so that hooks can add
+    // unconditionally.
+    let outcome = match (changed, existed) {
+        (false, _) => "unchanged",
+        (true, true) => "updated",
+        (true, false) => "added",
+    };
+
+    write_json(out, &view::AddView{ name, target, priority: weight, outcome,
+                                    changed });
+
+    if !changed {
+        println!("update-alternatives: alternative {} for {} with priority {} \
+                 is unchanged", target.display(), name, weight);
+
+        return false;
+    }
+
+    println!("update-alternatives: {} alternative {} for {} with priority {}",
+             outcome, target.display(), name, weight);
+    log.record(format!("add {} {} {}", name, target.display(), weight));
+
+    let list = db.alternatives(name).unwrap();
+    let link = list.path();
+
+    // A manual selection outlasts new alternatives, even better ones.
+    if let (Some(selected), Some(best)) = (list.selected(), list.best()) {
+        if list.manual().is_some() && selected.target() != best.target() {
+            println!("update-alternatives: {} stays selected by hand for {}; \
+                     'update-alternatives auto {}' selects {}",
+                     selected.target().display(), name, name,
+                     best.target().display());
+        }
+    }
+
+    warn_about_path(db, link);
+
+    true
+}
+
+// Warns when a login shell would not find link first, so that selecting
+// through it has no effect on commands run by name.
+fn warn_about_path(db: &AlternativeDb, link: &std::path::Path) {
+    for problem in doctor::check_path(db).iter()
+                                         .filter(|p| p.concerns(link)) {
+        eprintln!("update-alternatives: warning: {}", problem);
+    }
+}
+
+// The links given with --slave, which must be absolute and managed by no
+// other group, nor by this one as anything but a follower.
+fn followers(db: &AlternativeDb, name: &str, link: &std::path::Path,
+             kind: &Kind, matches: &clap::ArgMatches)
+-> Vec<alternative::Follower> {
+    let values: Vec<&std::path::PathBuf> =
+        matches.get_many::<std::path::PathBuf>("SLAVE")
+               .map(|v| v.collect())
+               .unwrap_or_default();
+    let mut followers: Vec<alternative::Follower> = Vec::new();
+
+    if !values.is_empty() && matches!(kind, Kind::Xdg{ .. }) {
+        error!(BAD_ARGUMENT, "{} sets default applications and has \
+                             no links to follow it", name);
+        std::process::exit(1);
+    }
+
+    for pair in values.chunks(2) {
+        let (follower, target) = (pair[0], pair[1]);
+        let fail = |why: String| -> ! {
+            error!(BAD_TARGET, "follower {} of {}: {}",
+                   follower.display(), name, why);
+            std::process::exit(1);
+        };
+
+        if !follower.is_absolute() {
+            fail(String::from("links must be absolute paths"));
+        }
+
+        if follower == link {
+            fail(String::from("it is the link of the group"));
+        }
+
+        if followers.iter().any(|f| f.link == *follower) {
+            fail(String::from("given twice"));
+        }
+
+        let owner = db.iter().find(|(other, list)| {
+            list.path() == follower.as_path()
+                || (*other != name
+                    && list.follower_links().contains(follower.as_path()))
+        });
+
+        if let Some((other, _)) = owner {
+            fail(format!("already managed by {}", other));
+        }
+
+        if filesystem::resolves_to(db.root(), target, follower) {
+            fail(format!("{} is or resolves through it", target.display()));
+        }
+
+        followers.push(alternative::Follower{ link: follower.clone(),
+                                              target: target.clone() });
+    }
+
+    followers
+}
+
+fn remove(db: &mut AlternativeDb, log: &mut AuditLog,
+          matches: &clap::ArgMatches) -> bool {
+    let target = matches
+        .get_one::<std::path::PathBuf>("TARGET")
+        .or_else(|| matches.get_one::<std::path::PathBuf>("TARGET_POS"))
+        .unwrap();
+    let name = matches
+        .get_one::<String>("NAME")
+        .or_else(|| matches.get_one::<String>("NAME_POS"))
+        .map(|s| s.as_str())
+        .unwrap();
+    let pinned = db.alternatives(name)
+                   .and_then(|l| l.manual())
+                   .is_some_and(|m| m.target() == target.as_path());
+    let report_pin = || if pinned {
+        println!("update-alternatives: {} was selected by hand; {} returns to \
+                 automatic selection", target.display(), name);
+    };
+
+    if matches.get_flag("ARCHIVE") && db.archive_alternative(name, target) {
+        println!("update-alternatives: archived alternative {} for {}",
+                 target.display(), name);
+        report_pin();
+        log.record(format!("archive {} {}", name, target.display()));
+
+        return true;
+    }
+
+    if db.remove_alternative(name, target) {
+        println!("update-alternatives: removed alternative {} for {}",
+                 target.display(), name);
+        report_pin();
+        log.record(format!("remove {} {}", name, target.display()));
+
+        return true;
+    }
+
+    if matches.get_flag("IGNORE_MISSING") {
+        return false;
+    }
+
+    if db.has_alternatives(name) {
+        error!(NOT_MEMBER, "{} is not an alternative for {}",
+               target.display(), name);
+    } else {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+    }
+
+    std::process::exit(EXIT_NOT_FOUND);
+}
+
+// Brings back an alternative removed with --archive.
+fn restore(db: &mut AlternativeDb, log: &mut AuditLog,
+           matches: &clap::ArgMatches) -> bool {
+    let name = matches.get_one::<String>("NAME").unwrap();
+    let target = matches.get_one::<std::path::PathBuf>("TARGET").unwrap();
+
+    let list = db.alternatives(name).unwrap_or_else(|| {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
+
+    if list.links().iter().any(|a| a.target() == target) {
+        println!("update-alternatives: {} is an alternative for {} already",
+                 target.display(), name);
+
+        return false;
+    }
+
+    let priority = match db.restore_alternative(name, target) {
+        Some(a) => a.priority(),
+        None => {
+            error!(NOT_MEMBER, "{} is not an archived alternative \
+                               for {}", target.display(), name);
+            std::process::exit(EXIT_NOT_FOUND);
+        },
+    };
+
+    println!("update-alternatives: restored alternative {} for {} with \
+             priority {}", target.display(), name, priority);
+    log.record(format!("restore {} {}", name, target.display()));
+
+    true
+}
+
+// Selects an alternative by hand, regardless of priorities, for good or until
+// a given time.
+fn set(db: &mut AlternativeDb, log: &mut AuditLog,
+       matches: &clap::ArgMatches) -> bool {
+    let target = matches
+        .get_one::<std::path::PathBuf>("TARGET")
+        .or_else(|| matches.get_one::<std::path::PathBuf>("TARGET_POS"))
+        .unwrap();
+    let name = matches
+        .get_one::<String>("NAME")
+        .or_else(|| matches.get_one::<String>("NAME_POS"))
+        .map(|s| s.as_str())
+        .unwrap();
+    let until = matches.get_one::<std::time::SystemTime>("UNTIL").copied();
+
+    let listed = match db.alternatives(name) {
+        Some(list) => list.links().iter().any(|a| a.target() == target),
+        None => {
+            error!(NOT_FOUND, "no alternatives found for {}", name);
+            std::process::exit(EXIT_NOT_FOUND);
+        },
+    };
+
+    if !listed {
+        error!(NOT_MEMBER, "{} is not an alternative for {}",
+               target.display(), name);
+        std::process::exit(EXIT_NOT_FOUND);
+    }
+
+    let until_text = until.map(|u| format!(" until {}", audit::timestamp(u)))
+                          .unwrap_or_default();
+
+    if !db.select(name, target, until) {
+        println!("update-alternatives: {} is already selected for {}{}",
+                 target.display(), name, until_text);
+
+        return false;
+    }
+
+    println!("update-alternatives: selected {} for {}{}", target.display(),
+             name, until_text);
+    log.record(format!("set {} {}{}", name, target.display(), until_text));
+
+    if let Some(list) = db.alternatives(name) {
+        warn_about_path(db, list.path());
+    }
+
+    true
+}
+
+// Ends the manual selection of a group, so the alternative with the highest
+// priority is selected again.
+fn auto(db: &mut AlternativeDb, log: &mut AuditLog,
+        matches: &clap::ArgMatches) -> bool {
+    let name = matches
+        .get_one::<String>("NAME")
+        .or_else(|| matches.get_one::<String>("NAME_POS"))
+        .map(|s| s.as_str())
+        .unwrap();
+
+    if db.alternatives(name).is_none() {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    }
+
+    let target = match db.clear_manual(name) {
+        Some(t) => t,
+        None => {
+            println!("update-alternatives: {} is in automatic mode already",
+                     name);
+
+            return false;
+        },
+    };
+
+    println!("update-alternatives: returned {} to automatic selection; {} is \
+             no longer selected by hand", name, target.display());
+    log.record(format!("auto {} {}", name, target.display()));
+
+    true
+}
+
+// Lets the user choose the alternative of a group from a numbered table, as
+// Debian's --config does: 0 stands for automatic selection, the others for
+// the targets, selected by hand.
+fn configure(db: &mut AlternativeDb, log: &mut AuditLog,
+             matches: &clap::ArgMatches) -> bool {
+    use std::io::{BufRead, Write};
+
+    let name = matches
+        .get_one::<String>("NAME")
+        .or_else(|| matches.get_one::<String>("NAME_POS"))
+        .map(|s| s.as_str())
+        .unwrap();
+
+    let list = match db.alternatives(name) {
+        Some(l) if l.num_links() > 0 => l,
+        _ => {
+            error!(NOT_FOUND, "no alternatives found for {}", name);
+            std::process::exit(EXIT_NOT_FOUND);
+        },
+    };
+
+    if list.num_links() == 1 {
+        println!("update-alternatives: {} is the only alternative for {}; \
+                 nothing to configure", list.links()[0].target().display(),
+                 name);
+
+        return false;
+    }
+
+    let manual = list.manual()
+                     .filter(|m| !m.is_expired(std::time::SystemTime::now()))
+                     .map(|m| m.target());
+    let targets: Vec<std::path::PathBuf> =
+        list.links().iter().map(|a| a.target().to_path_buf()).collect();
+    let current = manual.and_then(|m| targets.iter().position(|t| t == m))
+                        .map_or(0, |i| i + 1);
+
+    println!("There are {} choices for {} (providing {}).", targets.len(),
+             name, list.path().display());
+    println!();
+
+    let mut table = table::Table::new(&["", "SELECTION", "TARGET", "PRIORITY",
+                                        "MODE"]);
+    let mark = |i: usize| String::from(if i == current { "*" } else { "" });
+
+    if let Some(best) = list.best() {
+        table.push(vec![mark(0), String::from("0"),
+                        best.target().display().to_string(),
+                        best.priority().to_string(), String::from("auto")]);
+    }
+
+    for (i, alternative) in list.links().iter().enumerate() {
+        table.push(vec![mark(i + 1), (i + 1).to_string(),
+                        alternative.target().display().to_string(),
+                        alternative.priority().to_string(),
+                        String::from("manual")]);
+    }
+
+    table.print(false);
+    println!();
+
+    let choice = loop {
+        print!("Press <enter> to keep the current choice [*], or type a \
+               selection number: ");
+
+        let mut line = String::new();
+        let read = std::io::stdout().flush()
+            .and_then(|_| std::io::stdin().lock().read_line(&mut line));
+
+        match read {
+            // End of input keeps the current choice as well.
+            Ok(0) => {
+                println!();
+
+                return false;
+            },
+            Ok(_) => (),
+            Err(e) => {
+                error!(IO, "could not read selection: {}", e);
+                std::process::exit(1);
+            },
+        }
+
+        match line.trim() {
+            "" => return false,
+            n => match n.parse::<usize>() {
+                Ok(i) if i <= targets.len() => break i,
+                _ => println!("update-alternatives: {} is not a selection \
+                              number", n),
+            },
+        }
+    };
+
+    if choice == current {
+        println!("update-alternatives: keeping the current choice for {}",
+                 name);
+
+        return false;
+    }
+
+    if choice == 0 {
+        if let Some(target) = db.clear_manual(name) {
+            println!("update-alternatives: returned {} to automatic \
+                     selection; {} is no longer selected by hand", name,
+                     target.display());
+            log.record(format!("auto {} {}", name, target.display()));
+        }
+    } else {
+        let target = &targets[choice - 1];
+
+        db.select(name, target, None);
+        println!("update-alternatives: selected {} for {}", target.display(),
+                 name);
+        log.record(format!("set {} {}", name, target.display()));
+    }
+
+    if let Some(list) = db.alternatives(name) {
+        warn_about_path(db, list.path());
+    }
+
+    true
+}
+
+// Returns the groups whose manual selection has expired to automatic
+// selection.
+fn expire(db: &mut AlternativeDb, log: &mut AuditLog) -> bool {
+    let expired = db.expire(std::time::SystemTime::now());
+
+    for (name, target) in expired.iter() {
+        println!("update-alternatives: selection of {} for {} expired; \
+                 returning to automatic selection", target.display(), name);
+        log.record(format!("expire {} {}", name, target.display()));
+    }
+
+    !expired.is_empty()
+}
+
+// explicit, version, fixed:PRIORITY, or step:STEP with a positive STEP.
+fn parse_weights(text: &str) -> Result<Weights, String> {
+    let number = |n: &str| n.parse::<i64>().map_err(|e| {
+        format!("{} in {}: {}", n, text, e)
+    });
+
+    match text.split_once(':') {
+        None if text == "explicit" => Ok(Weights::Explicit),
+        None if text == "version" => Ok(Weights::Version),
+        Some(("fixed", n)) => Ok(Weights::Fixed(check_priority(number(n)?)?)),
+        Some(("step", n)) => match number(n)? {
+            step if step > 0 => Ok(Weights::Step(check_priority(step)?)),
+            _ => Err(format!("the step in {} must be positive", text)),
+        },
+        _ => Err(format!("{} is not explicit, version, fixed:PRIORITY, or \
+                         step:STEP", text)),
+    }
+}
+
+// A time such as 2026-10-17T18:00Z, or one relative to now such as 2h or
+// 1h30m, which must be in the future.
+fn parse_until(text: &str) -> Result<std::time::SystemTime, String> {
+    let now = std::time::SystemTime::now();
+    let until = match audit::parse_timestamp(text) {
+        Some(t) => t,
+        None => now + parse_duration(text).ok_or_else(|| {
+            format!("{} is neither a duration such as 2h or 1h30m nor a time \
+                    such as 2026-10-17T18:00Z", text)
+        })?,
+    };
+
+    if until <= now {
+        return Err(format!("{} is in the past", text));
+    }
+
+    Ok(until)
+}
+
+fn parse_duration(text: &str) -> Option<std::time::Duration> {
+    let mut seconds: u64 = 0;
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let end = rest.find(|c: char| !c.is_ascii_digit())?;
+        let count: u64 = rest[..end].parse().ok()?;
+        let unit = match rest[end..].chars().next()? {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86_400,
+            _ => return None,
+        };
+
+        seconds = seconds.checked_add(count.checked_mul(unit)?)?;
+        rest = &rest[end + 1..];
+    }
+
+    (seconds > 0).then(|| std::time::Duration::from_secs(seconds))
+}
+
+// Moves an alternative whose target was moved, in every group, keeping its
+// priority. Committing then rewrites the links that pointed at the old path.
+fn retarget(db: &mut AlternativeDb, log: &mut AuditLog,
+            matches: &clap::ArgMatches) -> bool {
+    let old = matches.get_one::<std::path::PathBuf>("OLD").unwrap();
+    let new = matches.get_one::<std::path::PathBuf>("NEW").unwrap();
+
+    if db.root() != std::path::Path::new("/") && !new.is_absolute() {
+        error!(BAD_TARGET, "target {} must be an absolute path \
+                           inside {}", new.display(), db.root().display());
+        std::process::exit(1);
+    }
+
+    // As with add, no link may end up pointing at itself.
+    let looping = db.iter().find(|(_, list)| {
+        list.links().iter().any(|a| a.target() == old.as_path())
+            && filesystem::resolves_to(db.root(), new, list.path())
+    });
+
+    if let Some((name, list)) = looping {
+        error!(LOOP, "target {} is or resolves through the link {} of {}",
+               new.display(), list.path().display(), name);
+        std::process::exit(1);
+    }
+
+    if !filesystem::under(db.root(), new).exists() {
+        eprintln!("update-alternatives: warning: {} does not exist",
+                  new.display());
+    }
+
+    let names = db.retarget(old, new);
+
+    if names.is_empty() {
+        error!(NOT_MEMBER, "{} is not an alternative of any group",
+               old.display());
+        std::process::exit(EXIT_NOT_FOUND);
+    }
+
+    println!("update-alternatives: retargeted {} to {} in {}",
+             old.display(), new.display(), names.join(", "));
+    log.record(format!("retarget {} {}", old.display(), new.display()));
+
+    true
+}
+
+// Runs the target selected for a group, as the database has it rather than
+// as PATH and the hash table of a shell find it, and exits with its status.
+fn test_selection(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    use std::io::Write;
+    use std::os::unix::process::ExitStatusExt;
+
+    let name = matches.get_one::<String>("NAME").unwrap();
+
+    let list = db.alternatives(name).unwrap_or_else(|| {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
+    let selected = list.selected().unwrap_or_else(|| {
+        error!(NOT_FOUND, "{} has no alternatives", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
+    let target = selected.target();
+
+    if db.root() != std::path::Path::new("/") {
+        error!(NOT_RUNNABLE, "programs below --root are not run");
+        std::process::exit(1);
+    }
+
+    let selects = match list.kind() {
+        Kind::Symlink => None,
+        Kind::Library => Some("a library"),
+        Kind::Unit => Some("a systemd unit"),
+        Kind::Xdg{ .. } => Some("a default application"),
+    };
+
+    if let Some(what) = selects {
+        error!(NOT_RUNNABLE, "{} selects {}, not a program", name, what);
+        std::process::exit(1);
+    }
+
+    if let Some(problem) = list.problem(selected) {
+        eprintln!("update-alternatives: warning: {} is {}", target.display(),
+                  problem);
+    }
+
+    let args: Vec<String> = match matches.get_many::<String>("ARGS") {
+        Some(a) => a.cloned().collect(),
+        None => match load_config(db.root()) {
+            Ok(c) => c.probe.arguments(name),
+            Err(_) => std::process::exit(1),
+        },
+    };
+
+    println!("update-alternatives: running {} {}", target.display(),
+             args.join(" "));
+    let _ = std::io::stdout().flush();
+
+    let status = std::process::Command::new(target).args(&args).status()
+                                      .unwrap_or_else(|e| {
+        error!(NOT_RUNNABLE, "could not run {}: {}", target.display(), e);
+        std::process::exit(1);
+    });
+
+    let code = match (status.code(), status.signal()) {
+        (Some(0), _) => {
+            println!("update-alternatives: {} exited successfully",
+                     target.display());
+
+            0
+        },
+        (Some(c), _) => {
+            error!(PROGRAM, "{} exited with status {}", target.display(), c);
+
+            c
+        },
+        (None, s) => {
+            let s = s.unwrap_or(0);
+
+            error!(PROGRAM, "{} was killed by signal {}", target.display(), s);
+
+            128 + s
+        },
+    };
+
+    std::process::exit(code);
+}
+
+// Prints what changed between two captured states of the database, or
+// between one and the current database.
+fn diff_states(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let (old, new) = match matches.get_one::<String>("AGAINST") {
+        Some(state) => (read_state(db.root(), state), None),
+        None => (read_state(db.root(), matches.get_one::<String>("OLD")
+                                              .unwrap()),
+                 Some(read_state(db.root(), matches.get_one::<String>("NEW")
+                                                   .unwrap()))),
+    };
+
+    let changes = diff::diff(&old, new.as_ref().unwrap_or(db));
+
+    for change in changes.iter() {
+        println!("{}", change);
+    }
+
+    println!("update-alternatives: {} difference(s)", changes.len());
+
+    false
+}
+
+// Reads a captured state of the database: the number of a snapper snapshot,
+// a root file system such as an image or a restored backup, a database
+// folder, or a manifest as written by export.
+fn read_state(root: &std::path::Path, state: &str) -> AlternativeDb {
+    let path = match state.parse::<u32>() {
+        Ok(n) => filesystem::under(root, snapper::snapshot_root(n)),
+        Err(_) => std::path::PathBuf::from(state),
+    };
+
+    if path.is_file() {
+        return manifest_state(root, &path);
+    }
+
+    let (folder, state_root) = match filesystem::under(&path,
+                                                       alternative_db::DB_FOLDER) {
+        f if f.is_dir() => (f, path),
+        _ => (path, root.to_path_buf()),
+    };
+
+    if !folder.is_dir() {
+        error!(NOT_A_ROOT, "{} is not a snapshot, a root file \
+                           system, a database folder, or a manifest", state);
+        std::process::exit(1);
+    }
+
+    match AlternativeDb::from_folder(&folder) {
+        Ok(mut db) => {
+            db.set_root(state_root);
+
+            db
+        },
+        Err(e) => {
+            error!(BAD_DATABASE, "could not read folder {}: {}",
+                   folder.display(), e);
+            std::process::exit(1);
+        },
+    }
+}
+
+// The database a manifest describes, as apply would make it from nothing.
+fn manifest_state(root: &std::path::Path, path: &std::path::Path)
+-> AlternativeDb {
+    let mut db = AlternativeDb::default();
+    db.set_root(root);
+
+    for group in read_manifest(path).groups {
+        let name = group.name.as_str();
+        let link = group.link.unwrap_or_else(|| {
+            alternative_db::default_link(name)
+        });
+
+        db.add_group(name, &link);
+
+        for entry in group.alternatives {
+            let mut alternative = Alternative::from_parts(&entry.target,
+                                                          entry.priority)
+                                              .with_followers(entry.followers);
+            alternative.set_package(entry.package);
+
+            if let Err(e) = db.add_alternative_with_link(name, &link,
+                                                         alternative) {
+                error!(BAD_MANIFEST, "{}: {}", path.display(), e.message);
+                std::process::exit(1);
+            }
+        }
+
+        if let Some(target) = group.selected {
+            db.select(name, target, None);
+        }
+    }
+
+    db
+}
+
+// Duplicates a group under a new name and link, as a starting point for a
+// related one.
+fn copy(db: &mut AlternativeDb, log: &mut AuditLog,
+        matches: &clap::ArgMatches) -> bool {
+    let name = matches.get_one::<String>("NAME").unwrap();
+    let new_name = matches.get_one::<String>("NEW_NAME").unwrap();
+    let link = matches.get_one::<std::path::PathBuf>("LINK")
+                      .cloned()
+                      .unwrap_or_else(|| alternative_db::default_link(new_name));
+
+    let list = db.alternatives(name).unwrap_or_else(|| {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
+
+    if db.has_alternatives(new_name) {
+        error!(CONFLICT, "{} exists already", new_name);
+        std::process::exit(1);
+    }
+
+    let owner = db.iter().find(|(_, l)| {
+        l.path() == link || l.follower_links().contains(link.as_path())
+    });
+
+    if let Some((other, _)) = owner {
+        error!(CONFLICT, "link {} is managed by {} already",
+               link.display(), other);
+        std::process::exit(1);
+    }
+
+    // As with add, the new link may not end up pointing at itself.
+    if let Some(a) = list.links().iter().find(|a| {
+        filesystem::resolves_to(db.root(), a.target(), &link)
+    }) {
+        error!(LOOP, "target {} is or resolves through the \
+                     link {}", a.target().display(), link.display());
+        std::process::exit(1);
+    }
+
+    db.copy(name, new_name, &link);
+
+    println!("update-alternatives: copied {} to {} ({})", name, new_name,
+             link.display());
+    log.record(format!("copy {} {} {}", name, new_name, link.display()));
+
+    true
+}
+
+fn adopt(db: &mut AlternativeDb, log: &mut AuditLog,
+         matches: &clap::ArgMatches) -> bool {
+    let links: Vec<&std::path::PathBuf> = matches
+        .get_many::<std::path::PathBuf>("LINKS")
+        .unwrap()
+        .collect();
+    let name = matches.get_one::<String>("NAME");
+    let weight = *matches.get_one::<i64>("WEIGHT").unwrap();
+
+    if name.is_some() && links.len() > 1 {
+        error!(BAD_ARGUMENT, "--name needs a single link to adopt");
+        std::process::exit(1);
+    }
+
+    let mut adopted = 0;
+    let mut refused = 0;
+
+    for link in links {
+        match adopt_link(db, link, name, weight) {
+            Ok(Some((name, target))) => {
+                println!("update-alternatives: adopted {} as {} with target {} \
+                         and priority {}", link.display(), name,
+                         target.display(), weight);
+                log.record(format!("adopt {} {} {}", name, target.display(),
+                                   weight));
+                adopted += 1;
+            },
+            Ok(None) => {},
+            Err(reason) => {
+                error!(BAD_TARGET, "not adopting {}: {}",
+                       link.display(), reason);
+                refused += 1;
+            },
+        }
+    }
+
+    if adopted == 0 && refused > 0 {
+        std::process::exit(1);
+    }
+
+    adopted > 0
+}
+
+// Turns the unmanaged symlink link into a group of its own whose only
+// alternative is the current target. Returns the name and target of the new
+// group, or None if link is managed already.
+fn adopt_link(db: &mut AlternativeDb, link: &std::path::Path,
+              name: Option<&String>, weight: i64)
+-> Result<Option<(String, std::path::PathBuf)>, String> {
+    if !link.is_absolute() {
+        return Err(String::from("links must be absolute paths"));
+    }
+
+    let owner = db.iter().find(|(_, list)| {
+        list.path() == link || list.follower_links().contains(link)
+    });
+
+    if let Some((other, _)) = owner {
+        println!("update-alternatives: {} is managed by {} already",
+                 link.display(), other);
+
+        return Ok(None);
+    }
+
+    match std::fs::symlink_metadata(filesystem::under(db.root(), link)) {
+        Ok(m) if m.file_type().is_symlink() => {},
+        Ok(_) => return Err(String::from("it is not a symlink")),
+        Err(e) => return Err(e.to_string()),
+    }
+
+    let name = match name {
+        Some(n) => n.clone(),
+        None => link.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(String::from)
+                    .ok_or_else(|| String::from("it has no usable name; \
+                                                 pass --name"))?,
+    };
+
+    alternative_db::check_name(&name)?;
+
+    if db.has_alternatives(&name) {
+        return Err(format!("{} exists already with another link", name));
+    }
+
+    // Stored as an absolute, normalized path, taken from the directory of the
+    // link if relative.
+    let target = filesystem::under(db.root(), link).read_link()
+        .map_err(|e| format!("could not read it: {}", e))?;
+    let target = filesystem::normalize(match link.parent() {
+        Some(parent) if target.is_relative() => parent.join(target),
+        _ => target,
+    });
+
+    if filesystem::resolves_to(db.root(), &target, link) {
+        return Err(format!("its target {} resolves through it",
+                           target.display()));
+    }
+
+    let directory = link.parent().unwrap_or(link);
+    let kind = if directory == std::path::Path::new(alternative_db::LIBRARY_DIR) {
+        Kind::Library
+    } else if directory == std::path::Path::new(generate::SYSTEMD_UNIT_DIR) {
+        Kind::Unit
+    } else {
+        Kind::Symlink
+    };
+
+    db.add_alternative_with_link(&name, link,
+                                 Alternative::from_parts(&target, weight))
+      .map_err(|e| e.message)?;
+    db.set_kind(&name, kind);
+
+    Ok(Some((name, target)))
+}
+
+fn renumber(db: &mut AlternativeDb, log: &mut AuditLog,
+            matches: &clap::ArgMatches) -> bool {
+    let name = matches.get_one::<String>("NAME").unwrap();
+    let step = *matches.get_one::<i64>("STEP").unwrap();
+
+    if !db.has_alternatives(name) {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    }
+
+    let changed = db.renumber(name, step).unwrap_or_else(|| {
+        error!(BAD_ARGUMENT, "the priorities of {} do not fit in \
+                             steps of {}", name, step);
+        std::process::exit(1);
+    });
+
+    if changed.is_empty() {
+        println!("update-alternatives: priorities of {} are unchanged", name);
+
+        return false;
+    }
+
+    for (target, old, new) in changed.iter() {
+        println!("update-alternatives: {}: priority {} → {}",
+                 target.display(), old, new);
+    }
+
+    log.record(format!("renumber {} {}", name, step));
+
+    true
+}
+
+fn changes_environment(matches: &clap::ArgMatches) -> bool {
+    matches.contains_id("ASSIGNMENTS") || matches.contains_id("UNSET")
+}
+
+fn environment(db: &mut AlternativeDb, log: &mut AuditLog,
+               matches: &clap::ArgMatches) -> bool {
+    let name = matches.get_one::<String>("NAME").unwrap();
+    let list = db.alternatives(name).unwrap_or_else(|| {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
+
+    if !changes_environment(matches) {
+        if list.environment().is_empty() {
+            println!("update-alternatives: {} exports no variables", name);
+        }
+
+        let target = list.current_target();
+
+        for (variable, template) in list.environment() {
+            match target {
+                Some(t) => println!("{}={} ({})", variable, template,
+                                    profile::expand(template, t)),
+                None => println!("{}={}", variable, template),
+            }
+        }
+
+        return false;
+    }
+
+    let is_variable = |v: &str| {
+        v.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && v.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let assignments: Vec<(&str, &str)> = matches
+        .get_many::<String>("ASSIGNMENTS")
+        .into_iter()
+        .flatten()
+        .map(|a| a.split_once('=').filter(|(v, _)| is_variable(v))
+                  .unwrap_or_else(|| {
+                      error!(BAD_ARGUMENT, "{} is not of the form \
+                                           VARIABLE=TEMPLATE", a);
+                      std::process::exit(1);
+                  }))
+        .collect();
+    let unset: Vec<&String> = matches.get_many::<String>("UNSET")
+                                     .into_iter()
+                                     .flatten()
+                                     .collect();
+
+    if let Some(v) = unset.iter().find(|v| !is_variable(v)) {
+        error!(BAD_ARGUMENT, "{} is not a variable name", v);
+        std::process::exit(1);
+    }
+
+    let mut changed = false;
+
+    for (variable, template) in assignments {
+        if db.set_environment(name, variable, Some(template)) {
+            println!("update-alternatives: {} exports {}={}", name, variable,
+                     template);
+            log.record(format!("env {} {}={}", name, variable, template));
+            changed = true;
+        }
+    }
+
+    for variable in unset {
+        if db.set_environment(name, variable, None) {
+            println!("update-alternatives: {} no longer exports {}", name,
+                     variable);
+            log.record(format!("env {} --unset {}", name, variable));
+            changed = true;
+        }
+    }
+
+    if !changed {
+        println!("update-alternatives: environment of {} is unchanged", name);
+    }
+
+    changed
+}
+
+fn weights(db: &mut AlternativeDb, log: &mut AuditLog,
+           matches: &clap::ArgMatches) -> bool {
+    let name = matches.get_one::<String>("NAME").unwrap();
+    let list = db.alternatives(name).unwrap_or_else(|| {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
+
+    let policy = match matches.get_one::<Weights>("POLICY") {
+        Some(p) => *p,
+        None => {
+            println!("{}", list.weights());
+
+            return false;
+        },
+    };
+
+    if !db.set_weights(name, policy) {
+        println!("update-alternatives: weight policy of {} is {} already",
+                 name, policy);
+
+        return false;
+    }
+
+    println!("update-alternatives: new alternatives of {} get priorities \
+             from weight policy {}", name, policy);
+    log.record(format!("weights {} {}", name, policy));
+
+    true
+}
+
+fn changes_tags(matches: &clap::ArgMatches) -> bool {
+    matches.contains_id("TAGS") || matches.contains_id("UNTAG")
+}
+
+fn tag(db: &mut AlternativeDb, log: &mut AuditLog,
+       matches: &clap::ArgMatches) -> bool {
+    let name = matches.get_one::<String>("NAME").unwrap();
+    let list = db.alternatives(name).unwrap_or_else(|| {
+        error!(NOT_FOUND, "no alternatives found for {}", name);
+        std::process::exit(EXIT_NOT_FOUND);
+    });
+
+    if !changes_tags(matches) {
+        if list.tags().is_empty() {
+            println!("update-alternatives: {} has no tags", name);
+        }
+
+        for tag in list.tags() {
+            println!("{}", tag);
+        }
+
+        return false;
+    }
+
+    let mut changed = false;
+
+    for tag in matches.get_many::<String>("TAGS").into_iter().flatten() {
+        if db.set_tag(name, tag, true) {
+            println!("update-alternatives: tagged {} {}", name, tag);
+            log.record(format!("tag {} {}", name, tag));
+            changed = true;
+        }
+    }
+
+    for tag in matches.get_many::<String>("UNTAG").into_iter().flatten() {
+        if db.set_tag(name, tag, false) {
+            println!("update-alternatives: {} is no longer tagged {}", name,
+                     tag);
+            log.record(format!("tag {} --remove {}", name, tag));
+            changed = true;
+        }
+    }
+
+    if !changed {
+        println!("update-alternatives: tags of {} are unchanged", name);
+    }
+
+    changed
+}
+
+fn prune(db: &mut AlternativeDb, log: &mut AuditLog,
+         matches: &clap::ArgMatches) -> bool {
+    let provider = match package::detect(db.root()) {
+        Some(p) => p,
+        None => {
+            error!(SOURCE, "no supported package manager found");
+            std::process::exit(1);
+        },
+    };
+
+    let mut stale: Vec<(String, std::path::PathBuf, String)> = db.iter()
+        .flat_map(|(name, list)| list.links().iter().filter_map(|a| {
+            let package = a.package()?;
+
+            if provider.is_installed(package) {
+                return None;
+            }
+
+            Some((name.clone(), a.target().to_path_buf(), package.to_string()))
+        }).collect::<Vec<_>>())
+        .collect();
+    stale.sort();
+
+    let dry_run = matches.get_flag("DRY_RUN");
+    let mut mutated = false;
+
+    for (name, target, package) in stale {
+        if dry_run {
+            println!("update-alternatives: would remove alternative {} for {} \
+                     ({} is not installed)", target.display(), name, package);
+        } else if db.remove_alternative(&name, &target) {
+            println!("update-alternatives: removed alternative {} for {} ({} \
+                     is not installed)", target.display(), name, package);
+            log.record(format!("prune {} {}", name, target.display()));
+            mutated = true;
+        }
+    }
+
+    mutated
+}
+
+fn scan(db: &mut AlternativeDb, log: &mut AuditLog,
+        matches: &clap::ArgMatches) -> bool {
+    let prefix = match matches.get_one::<String>("BREW_PREFIX")
+                              .map(std::path::PathBuf::from)
+                              .or_else(scan::brew_prefix) {
+        Some(p) => p,
+        None => {
+            error!(SOURCE, "no Homebrew installation found; \
+                           pass --brew-prefix");
+            std::process::exit(1);
+        },
+    };
+
+    let candidates = match scan::brew(&prefix) {
+        Ok(c) => c,
+        Err(e) => {
+            error!(SOURCE, "could not scan {}: {}",
+                   prefix.join("opt").display(), e);
+            std::process::exit(1);
+        },
+    };
+
+    if matches.get_flag("APPLY") {
+        return register(db, log, candidates);
+    }
+
+    for candidate in candidates {
+        let known = db.alternatives(&candidate.name).is_some_and(|l| {
+            l.links().iter().any(|a| a.target() == candidate.target)
+        });
+
+        if !known {
+            println!("update-alternatives add --link {} {} {} {}",
+                     candidate.link.display(), candidate.name,
+                     candidate.target.display(), candidate.priority);
+        }
+    }
+
+    false
+}
+
+// Reads a manifest, or reports its problems and exits.
+fn read_manifest(path: &std::path::Path) -> apply::Manifest {
+    let text = filesystem::read(path).unwrap_or_else(|e| {
+        error!(IO, "could not read {}: {}", path.display(), e);
+        std::process::exit(1);
+    });
+
+    apply::parse(&text).unwrap_or_else(|problems| {
+        for problem in problems.iter() {
+            error!(BAD_MANIFEST, "{}:{}", path.display(), problem);
+        }
+
+        error!(BAD_MANIFEST, "{} has {} problem(s)", path.display(),
+               problems.len());
+        std::process::exit(1);
+    })
+}
+
+// Checks a manifest for apply without reading the database.
+fn validate_manifest(matches: &clap::ArgMatches) -> bool {
+    let manifest = read_manifest(matches.get_one::<std::path::PathBuf>(
+        "MANIFEST"
+    ).unwrap());
+    let alternatives: usize = manifest.groups.iter()
+                                     .map(|g| g.alternatives.len())
+                                     .sum();
+
+    println!("update-alternatives: {} is valid: {} group(s), {} \
+             alternative(s)", matches.get_one::<std::path::PathBuf>("MANIFEST")
+                                     .unwrap().display(),
+             manifest.groups.len(), alternatives);
+
+    false
+}
+
+fn print_schema() -> bool {
+    match serde_json::to_string_pretty(&apply::schema()) {
+        Ok(s) => println!("{}", s),
+        Err(e) => {
+            error!(IO, "could not write schema: {}", e);
+            std::process::exit(1);
+        },
+    }
+
+    false
+}
+
+// Prints the database as a manifest that apply reads.
+fn export(db: &AlternativeDb, matches: &clap::ArgMatches,
+          out: &mut Option<std::fs::File>) -> bool {
+    let tag = matches.get_one::<String>("TAG").map(|t| t.as_str());
+    let text = apply::export(db, tag).unwrap_or_else(|e| {
+        error!(IO, "could not export the database: {}", e);
+        std::process::exit(1);
+    });
+    let out = out.as_mut().expect("stdout is taken for export");
+
+    if let Err(e) = std::io::Write::write_all(out, text.as_bytes()) {
+        error!(IO, "could not write output: {}", e);
+        std::process::exit(1);
+    }
+
+    false
+}
+
+// Makes the groups of a manifest match it, as the add, remove, set and auto
+// commands would. A group whose link is taken, or whose manifest gives it
+// another link than it has, is refused before anything is changed.
+fn apply(db: &mut AlternativeDb, log: &mut AuditLog,
+         matches: &clap::ArgMatches) -> bool {
+    let manifest = read_manifest(matches.get_one::<std::path::PathBuf>(
+        "MANIFEST"
+    ).unwrap());
+    let mut changed = false;
+
+    for group in manifest.groups.iter() {
+        check_manifest_group(db, group);
+    }
+
+    for group in manifest.groups {
+        let name = group.name.as_str();
+        let link = group.link.clone()
+                        .unwrap_or_else(|| alternative_db::default_link(name));
+        let unlisted: Vec<std::path::PathBuf> = db
+            .alternatives(name)
+            .map(|l| l.links().iter()
+                              .map(|a| a.target().to_path_buf())
+                              .filter(|t| !group.alternatives.iter()
+                                                .any(|e| e.target == *t))
+                              .collect())
+            .unwrap_or_default();
+
+        for target in unlisted {
+            if db.remove_alternative(name, &target) {
+                println!("update-alternatives: removed alternative {} for {}",
+                         target.display(), name);
+                log.record(format!("remove {} {}", name, target.display()));
+                changed = true;
+            }
+        }
+
+        for entry in group.alternatives {
+            let mut alternative = Alternative::from_parts(&entry.target,
+                                                          entry.priority)
+                                              .with_followers(entry.followers);
+            alternative.set_package(entry.package);
+
+            let added = db.add_alternative_with_link(name, &link, alternative)
+                          .unwrap_or_else(|e| {
+                              error!(e);
+                              std::process::exit(1);
+                          });
+
+            if added {
+                println!("update-alternatives: applied alternative {} for {} \
+                         with priority {}", entry.target.display(), name,
+                         entry.priority);
+                log.record(format!("add {} {} {}", name,
+                                   entry.target.display(), entry.priority));
+                changed = true;
+            }
+        }
+
+        match group.selected {
+            Some(target) => if db.select(name, &target, None) {
+                println!("update-alternatives: selected {} for {}",
+                         target.display(), name);
+                log.record(format!("set {} {}", name, target.display()));
+                changed = true;
+            },
+            None => if let Some(target) = db.clear_manual(name) {
+                println!("update-alternatives: returned {} to automatic \
+                         selection", name);
+                log.record(format!("auto {} {}", name, target.display()));
+                changed = true;
+            },
+        }
+    }
+
+    if !changed {
+        println!("update-alternatives: the database matches the manifest \
+                 already");
+    }
+
+    changed
+}
+
+// What add would refuse for the group of a manifest: a link or follower link
+// that another group manages, or a target that resolves through its link.
+fn check_manifest_group(db: &AlternativeDb, group: &apply::Group) {
+    let name = group.name.as_str();
+    let link = match (db.alternatives(name), &group.link) {
+        (Some(list), Some(link)) if list.path() != link => {
+            error!(CONFLICT, "{} manages {}, not {}; remove the group to move \
+                             its link", name, list.path().display(),
+                   link.display());
+            std::process::exit(1);
+        },
+        (Some(list), _) => list.path().to_path_buf(),
+        (None, link) => link.clone().unwrap_or_else(|| {
+            alternative_db::default_link(name)
+        }),
+    };
+    let mut links: Vec<&std::path::Path> =
+        group.alternatives.iter()
+                          .flat_map(|e| e.followers.iter())
+                          .map(|f| f.link.as_path())
+                          .collect();
+
+    if links.contains(&link.as_path()) {
+        error!(BAD_TARGET, "follower {} of {} is the link of the group",
+               link.display(), name);
+        std::process::exit(1);
+    }
+
+    // The link of an existing group is its own already.
+    if db.alternatives(name).is_none() {
+        links.push(&link);
+    }
+
+    for checked in links {
+        let owner = db.iter().find(|(other, list)| {
+            *other != name && (list.path() == checked
+                               || list.follower_links().contains(checked))
+        });
+
+        if let Some((other, _)) = owner {
+            error!(CONFLICT, "link {} of {} is managed by {} already",
+                   checked.display(), name, other);
+            std::process::exit(1);
+        }
+    }
+
+    for entry in group.alternatives.iter() {
+        if filesystem::resolves_to(db.root(), &entry.target, &link) {
+            error!(LOOP, "target {} is or resolves through the link {} of \
+                         {}", entry.target.display(), link.display(), name);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn import(db: &mut AlternativeDb, log: &mut AuditLog,
+          matches: &clap::ArgMatches) -> bool {
+    let from = matches.get_one::<String>("FROM").unwrap();
+    let source = import::Source::from_name(from)
+        .expect("clap only accepts known version managers");
+
+    let dir = match matches.get_one::<String>("DIR")
+                           .map(std::path::PathBuf::from)
+                           .or_else(|| source.default_dir()) {
+        Some(d) => d,
+        None => {
+            error!(SOURCE, "cannot locate the {} directory; pass --dir", from);
+            std::process::exit(1);
+        },
+    };
+
+    match import::candidates(source, &dir) {
+        Ok(c) => register(db, log, c),
+        Err(e) => {
+            error!(SOURCE, "could not read {} versions from \
+                           {}: {}", from, dir.display(), e);
+            std::process::exit(1);
+        },
+    }
+}
+
+fn register(db: &mut AlternativeDb, log: &mut AuditLog,
+            candidates: Vec<scan::Candidate>) -> bool {
+    let mut mutated = false;
+
+    for candidate in candidates {
+        let alternative = Alternative::from_parts(&candidate.target,
+                                                  candidate.priority);
+
+        let added = db.add_alternative_with_link(&candidate.name,
+                                                 &candidate.link, alternative);
+
+        if let Err(e) = added.as_ref() {
+            error!(e);
+        }
+
+        if added.unwrap_or(false) {
+            println!("update-alternatives: added alternative {} for {} with \
+                     priority {}", candidate.target.display(), candidate.name,
+                     candidate.priority);
+            log.record(format!("add {} {} {}", candidate.name,
+                               candidate.target.display(),
+                               candidate.priority));
+            mutated = true;
+        }
+    }
+
+    mutated
+}
+
+fn load_config(root: &std::path::Path) -> std::io::Result<config::Config> {
+    let config_path = filesystem::under(root, config::CONFIG_PATH);
+
+    config::Config::load(&config_path).map_err(|e| {
+        let message = format!("could not read {}: {}", config_path.display(),
+                              e);
+
+        error!(CONFIG, "{}", message);
+        events::error(&errors::CONFIG, &message);
+
+        e
+    })
+}
+
+fn commit(db: &AlternativeDb) -> std::io::Result<()> {
+    if strict::is_strict() {
+        check_strict(db).inspect_err(|e| {
+            error!(STRICT, "{}, not committing", e);
+        })?;
+    }
+
+    if filesystem::is_read_only() {
+        print_plan(db);
+
+        return Ok(());
+    }
+
+    // An interrupted commit would leave the links half-written.
+    let _critical = signals::critical();
+    let _lock = lock::acquire(db.root()).inspect_err(|e| {
+        error!(LOCKED, "could not lock {}: {}",
+               filesystem::under(db.root(), lock::LOCK_PATH).display(), e);
+    })?;
+    freeze::check(db.root()).inspect_err(|e| {
+        error!(FROZEN, "{}", e);
+    })?;
+    let config = load_config(db.root())?;
+
+    // Hooks of the running system do not apply to an alternate root.
+    if db.root() != std::path::Path::new("/") {
+        return write_db(db, &config);
+    }
+
+    let changes = db.pending_links();
+
+    if let Err(e) = timings::phase("validate",
+                                   || hooks::pre_commit(&changes)) {
+        let message = format!("pre-commit hook failed, not committing: {}", e);
+
+        error!(HOOK, "{}", message);
+        events::error(&errors::HOOK, &message);
+
+        return Err(e);
+    }
+
+    let result = snapshot_and_write(db, &config);
+
+    if result.is_ok() {
+        hooks::post_commit(&changes);
+    }
+
+    result
+}
+
+// Prints every problem that --strict does not let pass, and fails if there is
+// one.
+fn check_strict(db: &AlternativeDb) -> std::io::Result<()> {
+    let problems = strict::problems(db);
+
+    for problem in problems.iter() {
+        error!(STRICT, "strict: {}", problem);
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    Err(std::io::Error::other(format!(
+        "{} problem{} in strict mode", problems.len(),
+        if problems.len() == 1 { "" } else { "s" }
+    )))
+}
+
+fn snapshot_and_write(db: &AlternativeDb, config: &config::Config)
+-> std::io::Result<()> {
+    if !config.snapper.enabled {
+        return write_db(db, config);
+    }
+
+    let description = std::iter::once(String::from("update-alternatives"))
+        .chain(std::env::args_os().skip(1)
+                                  .map(|a| a.to_string_lossy().into_owned()))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    let pre = match snapper::pre(&config.snapper, &description) {
+        Ok(n) => Some(n),
+        Err(e) => {
+            error!(PROGRAM, "could not create snapper pre snapshot: {}", e);
+
+            None
+        },
+    };
+
+    let result = write_db(db, config);
+
+    if let Some(pre) = pre {
+        match snapper::post(&config.snapper, pre, &description) {
+            Ok(post) => println!("update-alternatives: created snapper \
+                                 snapshots {}..{}", pre, post),
+            Err(e) => error!(PROGRAM, "could not create \
+                                      snapper post snapshot: {}", e),
+        }
+    }
+
+    result
+}
+
+fn write_db(db: &AlternativeDb, config: &config::Config)
+-> std::io::Result<()> {
+    let folder = db.folder();
+    let manifest_path = filesystem::under(db.root(), manifest::MANIFEST_PATH);
+
+    // Instances that change other groups commit at the same time, but the
+    // manifest always matches the entries of one of them.
+    let commit_lock = lock::acquire_commit(db.root()).inspect_err(|e| {
+        error!(LOCKED, "could not lock {}: {}",
+               filesystem::under(db.root(), lock::COMMIT_LOCK_PATH).display(),
+               e);
+    })?;
+
+    if manifest::exists(&manifest_path) {
+        match timings::phase("validate",
+                             || manifest::verify(&folder, &manifest_path)) {
+            Ok(0) => (),
+            Ok(n) => {
+                error!(MANIFEST, "refusing to commit: {} entries of {} do not \
+                                 match {}; run 'update-alternatives verify \
+                                 --update' to accept them", n,
+                       folder.display(), manifest_path.display());
+
+                return Err(std::io::Error::other("manifest mismatch"));
+            },
+            Err(e) => {
+                error!(MANIFEST, "could not verify {}: {}",
+                       manifest_path.display(), e);
+
+                return Err(e);
+            },
+        }
+    }
+
+    let changed = db.changed_lists(&folder);
+
+    if let Err(e) = timings::phase("serialize", || db.write_out(&folder)) {
+        let message = format!("could not commit changes to {}: {}",
+                              folder.display(), e);
+
+        error!(COMMIT, "{}", message);
+        events::error(&errors::COMMIT, &message);
+
+        Err(e)
+    } else if let Err(e) = update_manifest(&folder, &manifest_path) {
+        Err(e)
+    } else {
+        // Every link belongs to a single group, so they need no commit lock.
+        drop(commit_lock);
+
+        for (name, list) in changed.iter() {
+            events::emit(events::Event::GroupChanged{
+                group: name, link: list.path(), alternatives: list.links(),
+            });
+        }
+
+        let changed: Vec<&str> = changed.iter().map(|(n, _)| *n).collect();
+
+        create_link_dirs(db, config);
+
+        match timings::phase("links", || db.write_links()) {
+            Ok(changes) => {
+                for change in changes.iter() {
+                    events::emit(events::Event::LinkWritten{
+                        group: &change.name, link: &change.link,
+                        old: change.old.as_deref(), new: &change.new,
+                    });
+                }
+
+                print_changes(&changes);
+                print_summary(db, &changed, &changes,
+                              |name, _| db.writes(name));
+                write_profiles(db)?;
+                after_switch(db, config, &changes);
+
+                Ok(())
+            },
+            Err(ref e) if can_defer_links(e) => defer_links(db.root()),
+            Err(e) => {
+                let message = format!("could not write symlinks: {}", e);
+
+                error!(COMMIT, "{}", message);
+                events::error(&errors::COMMIT, &message);
+
+                Err(e)
+            },
+        }
+    }
+}
+
+// What a commit would have written, for --read-only.
+fn print_plan(db: &AlternativeDb) {
+    let folder = filesystem::under(db.root(), alternative_db::DB_FOLDER);
+
+    for (name, _) in db.changed_lists(&folder) {
+        println!("update-alternatives: read-only: would write {}",
+                 alternative_db::entry_path(&folder, name).display());
+    }
+
+    print_changes(&db.pending_links());
+
+    for (path, contents) in profile::pending(db) {
+        println!("update-alternatives: read-only: would {} {}",
+                 if contents.is_some() { "write" } else { "remove" },
+                 path.display());
+    }
+
+    println!("update-alternatives: read-only: nothing was written");
+}
+
+// Keeps the profile.d snippets in line with the selections, which a commit
+// may have changed as well as the variables.
+fn write_profiles(db: &AlternativeDb) -> std::io::Result<()> {
+    match profile::write(db) {
+        Ok(written) => {
+            for (path, exists) in written {
+                println!("update-alternatives: {} {}",
+                         if exists { "wrote" } else { "removed" },
+                         path.display());
+            }
+
+            Ok(())
+        },
+        Err(e) => {
+            let message = format!("could not write {}: {}",
+                                  filesystem::under(db.root(),
+                                                    profile::PROFILE_DIR)
+                                             .display(), e);
+
+            error!(COMMIT, "{}", message);
+            events::error(&errors::COMMIT, &message);
+
+            Err(e)
+        },
+    }
+}
+
+// Summarizes the links a commit wrote, in the manner of a diff.
+fn print_changes(changes: &[alternative_db::LinkChange]) {
+    use std::io::IsTerminal;
+
+    let color = std::io::stdout().is_terminal()
+        && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty());
+    let paint = |code: &str, text: String| if color {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text
+    };
+
+    for change in changes {
+        match change.old {
+            // Only followers of the link changed.
+            Some(ref old) if *old == change.new => (),
+            Some(ref old) => println!("{} {}: {} → {}",
+                                      paint("33", String::from("~")),
+                                      change.link.display(),
+                                      paint("31", old.display().to_string()),
+                                      paint("32", change.new.display()
+                                                        .to_string())),
+            None => println!("{} {} → {}", paint("32", String::from("+")),
+                             change.link.display(),
+                             paint("32", change.new.display().to_string())),
+        }
+    }
+}
+
+// One line for hook logs that tells whether anything happened at all. Only
+// the groups in scope, those the command could have changed, count as
+// unchanged.
+fn print_summary<F>(db: &AlternativeDb, groups: &[&str],
+                    links: &[alternative_db::LinkChange], in_scope: F)
+where F: Fn(&str, &alternative_list::AlternativeList) -> bool {
+    let unchanged = db.iter().filter(|(name, list)| {
+        in_scope(name, list)
+            && !groups.contains(&name.as_str())
+            && !links.iter().any(|l| l.name == **name)
+    }).count();
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+
+    println!("update-alternatives: {} group{} changed, {} link{} rewritten, {} \
+             unchanged", groups.len(), plural(groups.len()), links.len(),
+             plural(links.len()), unchanged);
+}
+
+fn after_switch(db: &AlternativeDb, config: &config::Config,
+                changes: &[alternative_db::LinkChange]) {
+    let is_kind = |change: &alternative_db::LinkChange, kind: &Kind| {
+        db.alternatives(&change.name).is_some_and(|l| l.kind() == kind)
+    };
+
+    // The dynamic linker keeps resolving library names to the previous
+    // target until its cache is rebuilt.
+    if changes.iter().any(|c| is_kind(c, &Kind::Library)) {
+        refresh_ldconfig(db.root());
+    }
+
+    // Neither hooks nor processes of the running system belong to an
+    // alternate root.
+    if db.root() != std::path::Path::new("/") {
+        return;
+    }
+
+    // systemd resolves aliases when it loads units, and without a reload
+    // keeps starting the previous one.
+    if changes.iter().any(|c| is_kind(c, &Kind::Unit))
+       && std::path::Path::new("/run/systemd/system").is_dir() {
+        reload_systemd();
+    }
+
+    for change in changes {
+        if let Err(e) = hooks::run_post(change) {
+            let message = format!("post hook for {} failed: {}", change.name,
+                                  e);
+
+            error!(HOOK, "{}", message);
+            events::error(&errors::HOOK, &message);
+        }
+
+        if !config.switch.report_processes {
+            continue;
+        }
+
+        let old = match change.old {
+            Some(ref o) if *o != change.new => o,
+            _ => continue,
+        };
+
+        match procs::using(old) {
+            Ok(ref p) if p.is_empty() => (),
+            Ok(p) => {
+                let list: Vec<String> = p.iter()
+                    .map(|p| format!("{} ({})", p.pid, p.name))
+                    .collect();
+
+                println!("update-alternatives: {}: processes still using {}: \
+                         {}", change.name, old.display(), list.join(", "));
+            },
+            Err(e) => {
+                error!(IO, "could not scan processes: {}", e);
+            },
+        }
+    }
+
+    if config.switch.refresh_mandb
+       && changes.iter().any(|c| c.link.components()
+                                      .any(|p| p.as_os_str() == "man")) {
+        refresh_mandb();
+    }
+
+    // A shell that ran the command before keeps running it from where it
+    // found it then, even if the new link comes first in PATH.
+    if config.switch.hash_hint
+       && changes.iter().any(|c| c.old.is_none()
+                                 && is_kind(c, &Kind::Symlink)) {
+        println!("update-alternatives: run 'hash -r' (or 'rehash' in csh) so \
+                 that running shells find the new links");
+    }
+}
+
+fn refresh_mandb() {
+    match std::process::Command::new("mandb").arg("-q").status() {
+        Ok(s) if s.success() => {
+            println!("update-alternatives: refreshed the man-db index");
+        },
+        Ok(s) => error!(PROGRAM, "mandb failed: {}", s),
+        Err(e) => error!(PROGRAM, "could not run mandb: {}", e),
+    }
+}
+
+fn refresh_ldconfig(root: &std::path::Path) {
+    let mut command = std::process::Command::new("ldconfig");
+
+    if root != std::path::Path::new("/") {
+        command.arg("-r").arg(root);
+    }
+
+    match command.status() {
+        Ok(s) if s.success() => {
+            println!("update-alternatives: refreshed the ldconfig cache");
+        },
+        Ok(s) => error!(PROGRAM, "ldconfig failed: {}", s),
+        Err(e) => {
+            error!(PROGRAM, "could not run ldconfig: {}", e);
+        },
+    }
+}
+
+fn reload_systemd() {
+    match std::process::Command::new("systemctl").arg("daemon-reload")
+                                                 .status() {
+        Ok(s) if s.success() => {
+            println!("update-alternatives: reloaded the systemd units");
+        },
+        Ok(s) => error!(PROGRAM, "systemctl daemon-reload failed: {}", s),
+        Err(e) => {
+            error!(PROGRAM, "could not run systemctl: {}", e);
+        },
+    }
+}
+
+fn can_defer_links(error: &std::io::Error) -> bool {
+    error.kind() == std::io::ErrorKind::ReadOnlyFilesystem
+        && transactional::detect().is_some()
+}
+
+fn defer_links(root: &std::path::Path) -> std::io::Result<()> {
+    if let Err(e) = transactional::defer_links(root) {
+        error!(COMMIT, "could not write {}: {}",
+               filesystem::under(root, transactional::PENDING_PATH)
+                          .display(), e);
+
+        return Err(e);
+    }
+
+    println!("update-alternatives: link directory is read-only; symlinks will \
+             be written by {} at the next boot", generate::SYNC_SERVICE);
+
+    Ok(())
+}
+
+fn update_manifest(folder: &std::path::Path, manifest_path: &std::path::Path)
+-> std::io::Result<()> {
+    if !manifest::exists(manifest_path) {
+        return Ok(());
+    }
+
+    if let Err(e) = manifest::write(folder, manifest_path) {
+        error!(MANIFEST, "could not update {}: {}",
+               manifest_path.display(), e);
+
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+// The entries of a zenity form, separated by newlines, which entries cannot
+// contain, so that targets keep '|' and leading or trailing spaces.
+fn form_fields(output: &[u8]) -> Vec<String> {
+    let text = String::from_utf8_lossy(output);
+
+    text.strip_suffix('\n').unwrap_or(&text)
+        .split('\n')
+        .map(String::from)
+        .collect()
+}
+
+// The row picked from a zenity list by its hidden index column, which is
+// printed instead of the target, since zenity could only print that lossily.
+fn picked_row(output: &[u8]) -> Option<usize> {
+    String::from_utf8_lossy(output).trim().parse().ok()
+}
+
+fn run_gui(db: &mut AlternativeDb) -> bool {
+    use std::process::Command;
+
+    // Check for zenity
+    let has_zenity = std::env::var_os("PATH").is_some_and(|path| {
+        std::env::split_paths(&path).any(|dir| dir.join("zenity").is_file())
+    });
+
+    if !has_zenity {
+        error!(PROGRAM, "--gui requested but 'zenity' was not found in PATH. Please install 'zenity' or run without --gui.");
+        return false;
+    }
+
+    // Values are passed as --option=VALUE, so that none is taken for an
+    // option, and as OsStr, so that targets reach the command unchanged.
+    fn run_privileged(args: &[&std::ffi::OsStr]) -> std::io::Result<std::process::ExitStatus> {
+        let exe = std::env::current_exe().unwrap_or_else(|_| std::path::PathBuf::from("update-alternatives"));
+        Command::new("pkexec").arg(&exe).args(args).status()
+            .or_else(|_| Command::new("sudo").arg(&exe).args(args).status())
+    }
+
+    // A running helper makes the change without authenticating every time;
+    // call returns None for what it cannot send, such as a target that is
+    // not UTF-8, and the privileged instance makes it instead.
+    fn apply<F: FnOnce(&mut helper::Client) -> Option<std::io::Result<bool>>>(args: &[&std::ffi::OsStr], call: F) -> Result<(), String> {
+        if let Some(result) = helper::Client::connect().ok().and_then(|mut c| call(&mut c)) {
+            return result.map(|_| ()).map_err(|e| e.to_string());
+        }
+        match run_privileged(args) {
+            Ok(s) if s.success() => Ok(()),
+            Ok(s) => Err(format!("exit {:?}", s.code())),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    let provider = package::detect(db.root());
+    let owner = |target: &std::path::Path| provider.as_ref()
+        .and_then(|p| p.owner(target))
+        .map(|p| p.to_string())
+        .unwrap_or_default();
+
+    loop {
+        let mut rows: Vec<(String, String)> = Vec::new();
+        for (name, list) in db.iter() {
+            let current = list
+                .current_target()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| String::from("<none>"));
+            rows.push((name.clone(), current));
+        }
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let menu_out = match Command::new("zenity").args([
+            "--list", "--title", "update-alternatives",
+            "--text", "Choose an action",
+            "--width", "500", "--height", "300",
+            "--column", "Action",
+            "Add", "Remove", "Adjust priority", "Sync", "Close",
+        ]).output() {
+            Ok(o) => o,
+            Err(e) => { error!(PROGRAM, "failed to launch zenity: {}", e); return false; }
+        };
+        if !menu_out.status.success() {
+            return false; 
+        }
+        let choice = String::from_utf8_lossy(&menu_out.stdout).trim().to_string();
+        // Only the group an action changed is read again.
+        let mut touched: Option<String> = None;
+        match choice.as_str() {
+            "Close" => return false,
+            "Sync" => {
+                match apply(&["sync".as_ref()], |c| Some(c.sync(None))) {
+                    Ok(()) => { let _ = Command::new("zenity").args(["--info","--text","Symlinks were rewritten.","--title","update-alternatives"]).status(); }
+                    Err(e) => { let _ = Command::new("zenity").args(["--error","--text", &format!("Sync failed: {}", e), "--title","update-alternatives"]).status(); }
+                }
+            }
+            "Add" => {
+                let form = match Command::new("zenity").args([
+                    "--forms", "--title", "Add alternative",
+                    "--text", "Enter name, target and priority",
+                    "--add-entry", "Name",
+                    "--add-entry", "Target path",
+                    "--add-entry", "Priority (integer)",
+                    "--separator", "\n",
+                    "--width", "500",
+                ]).output() { Ok(o) => o, Err(e) => { eprintln!("zenity error: {}", e); return false; } };
+                if !form.status.success() { continue; }
+                let fields = form_fields(&form.stdout);
+                let field = |i: usize| fields.get(i).map(|f| f.as_str()).unwrap_or("");
+                let name = field(0).trim();
+                let target = field(1);
+                let weight = field(2).trim();
+                if name.is_empty() || target.is_empty() || weight.is_empty() { let _=Command::new("zenity").args(["--error","--text","All fields are required.","--title","update-alternatives"]).status(); continue; }
+                let priority: i64 = match weight.parse() { Ok(p) => p, Err(_) => { let _=Command::new("zenity").args(["--error","--text","Priority must be an integer.","--title","update-alternatives"]).status(); continue; } };
+                match apply(&["add".as_ref(), format!("--name={}", name).as_ref(), format!("--target={}", target).as_ref(), format!("--weight={}", weight).as_ref()], |c| Some(c.add(name, target, priority))) {
+                    Ok(()) => { touched = Some(name.to_string()); let _=Command::new("zenity").args(["--info","--text","Alternative added/updated.","--title","update-alternatives"]).status(); }
+                    Err(e) => { let _=Command::new("zenity").args(["--error","--text", &format!("Add failed: {}", e), "--title","update-alternatives"]).status(); }
+                }
+            }
+            "Remove" | "Adjust priority" => {
+                if rows.is_empty() { let _=Command::new("zenity").args(["--warning","--text","No alternatives available.","--title","update-alternatives"]).status(); continue; }
+                let mut name_list_args = vec!["--list","--title","Select name","--column","Name"]; 
+                for (n, _) in &rows { name_list_args.push(n); }
+                let name_out = match Command::new("zenity").args(&name_list_args).output() { Ok(o)=>o, Err(e)=>{ eprintln!("zenity error: {}", e); return false; } };
+                if !name_out.status.success() { continue; }
+                let selected_name = String::from_utf8_lossy(&name_out.stdout).trim_end_matches('\n').to_string();
+                if selected_name.is_empty() { continue; }
+                let mut alt_rows: Vec<(std::path::PathBuf, i64, String)> = Vec::new();
+                if let Some(list) = db.alternatives(&selected_name) { for a in list.links() { alt_rows.push((a.target().to_path_buf(), a.priority(), owner(a.target()))); } }
+                if alt_rows.is_empty() { let _=Command::new("zenity").args(["--warning","--text","No targets for this name.","--title","update-alternatives"]).status(); continue; }
+                // The hidden first column is printed instead of the target,
+                // which zenity could only show lossily.
+                let mut alt_args: Vec<String> = vec!["--list".into(),"--title".into(),format!("{}: select target", selected_name),"--width".into(),"700".into(),"--hide-column".into(),"1".into(),"--print-column".into(),"1".into(),"--column".into(),"#".into(),"--column".into(),"Target".into(),"--column".into(),"Priority".into(),"--column".into(),"Package".into()];
+                for (i, (t, w, p)) in alt_rows.iter().enumerate() { alt_args.push(i.to_string()); alt_args.push(t.display().to_string()); alt_args.push(w.to_string()); alt_args.push(p.clone()); }
+                let alt_out = match Command::new("zenity").args(&alt_args).output() { Ok(o)=>o, Err(e)=>{ eprintln!("zenity error: {}", e); return false; } };
+                if !alt_out.status.success() { continue; }
+                let selected_target = match picked_row(&alt_out.stdout).and_then(|i| alt_rows.get(i)) { Some(r) => r.0.clone(), None => continue };
+                let mut target_arg = std::ffi::OsString::from("--target=");
+                target_arg.push(&selected_target);
+                let name_arg = format!("--name={}", selected_name);
+                if choice == "Remove" {
+                    match apply(&["remove".as_ref(), name_arg.as_ref(), &target_arg], |c| selected_target.to_str().map(|t| c.remove(&selected_name, t))) {
+                        Ok(()) => { touched = Some(selected_name.clone()); let _=Command::new("zenity").args(["--info","--text","Alternative removed.","--title","update-alternatives"]).status(); }
+                        Err(e) => { let _=Command::new("zenity").args(["--error","--text", &format!("Remove failed: {}", e), "--title","update-alternatives"]).status(); }
+                    }
+                } else {
+                    let pr_out = match Command::new("zenity").args(["--entry","--title","Set priority","--text","Enter new priority (integer)"]).output() { Ok(o)=>o, Err(e)=>{ eprintln!("zenity error: {}", e); return false; } };
+                    if !pr_out.status.success() { continue; }
+                    let new_w = String::from_utf8_lossy(&pr_out.stdout).trim().to_string();
+                    let priority: i64 = match new_w.parse() { Ok(p) => p, Err(_) => { let _=Command::new("zenity").args(["--error","--text","Priority must be an integer.","--title","update-alternatives"]).status(); continue; } };
+                    match apply(&["add".as_ref(), name_arg.as_ref(), &target_arg, format!("--weight={}", new_w).as_ref()], |c| selected_target.to_str().map(|t| c.add(&selected_name, t, priority))) {
+                        Ok(()) => { touched = Some(selected_name.clone()); let _=Command::new("zenity").args(["--info","--text","Priority updated.","--title","update-alternatives"]).status(); }
+                        Err(e) => { let _=Command::new("zenity").args(["--error","--text", &format!("Update failed: {}", e), "--title","update-alternatives"]).status(); }
+                    }
+                }
+            }
+            _ => { }
+        }
+
+        if let Some(name) = touched {
+            if let Err(e) = db.reload(&name) {
+                error!(BAD_DATABASE, "could not read {}: {}", name, e);
+                if let Ok(new_db) = read_db(db.root()) { *db = new_db; }
+            }
+        }
+    }
+}
+
+fn sync(db: &AlternativeDb, log: &mut AuditLog, matches: &clap::ArgMatches,
+        changed: &mut bool) -> bool {
+    let tag = matches.get_one::<String>("TAG").map(|t| t.as_str());
+    let keep = |list: &alternative_list::AlternativeList| {
+        tag.is_none_or(|t| list.has_tag(t))
+    };
+    let total = db.iter().filter(|(_, list)| keep(list)).count();
+
+    if let Some(t) = tag.filter(|_| total == 0) {
+        println!("update-alternatives: no group is tagged {}", t);
+
+        return false;
+    }
+
+    if filesystem::is_read_only() {
+        let pending: Vec<alternative_db::LinkChange> = db.pending_links()
+            .into_iter()
+            .filter(|c| db.alternatives(&c.name).is_some_and(keep))
+            .collect();
+        print_changes(&pending);
+        println!("update-alternatives: read-only: nothing was written");
+
+        return false;
+    }
+
+    let _critical = signals::critical();
+    let config = match load_config(db.root()) {
+        Ok(c) => c,
+        Err(_) => std::process::exit(1),
+    };
+
+    create_link_dirs(db, &config);
+
+    let (changes, errors) = timings::phase("links", || sync_links(db, keep));
+
+    if errors.first().is_some_and(|(_, e)| can_defer_links(e)) {
+        if defer_links(db.root()).is_err() {
+            std::process::exit(1);
+        }
+
+        log.record("sync: deferred to next boot");
+
+        return false;
+    }
+
+    for change in changes.iter() {
+        events::emit(events::Event::LinkRepaired{
+            group: &change.name, link: &change.link,
+            old: change.old.as_deref(), new: &change.new,
+        });
+    }
+
+    print_summary(db, &[], &changes, |_, list| keep(list));
+
+    if write_profiles(db).is_err() {
+        std::process::exit(1);
+    }
+
+    after_switch(db, &config, &changes);
+    *changed = !changes.is_empty();
+
+    if !errors.is_empty() {
+        error!(COMMIT, "could not write {} of {} links:", errors.len(), total);
+
+        for (name, e) in errors.iter() {
+            eprintln!("update-alternatives:     {}: {}", name, e);
+            events::error(&errors::COMMIT,
+                          &format!("could not write link of {}: {}", name, e));
+        }
+
+        log.record(format!("sync: {} links failed", errors.len()));
+
+        if let Err(e) = log.flush() {
+            error!(AUDIT, "could not write to audit log: {}", e);
+        }
+
+        std::process::exit(1);
+    }
+
+    if transactional::is_pending(db.root()) {
+        if let Err(e) = transactional::clear_pending(db.root()) {
+            error!(IO, "could not remove {}: {}",
+                   filesystem::under(db.root(), transactional::PENDING_PATH)
+                              .display(), e);
+        }
+    }
+
+    match tag {
+        Some(t) => log.record(format!("sync --tag {}", t)),
+        None => log.record("sync"),
+    }
+
+    false
+}
+
+const PROGRESS_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(2);
+
+type SyncResult = (Vec<alternative_db::LinkChange>,
+                   Vec<(String, std::io::Error)>);
+
+// Creates the missing directories of the links, which minimal installs may
+// lack. Failures are left for writing the links to report.
+fn create_link_dirs(db: &AlternativeDb, config: &config::Config) {
+    let mut dirs: Vec<std::path::PathBuf> = db.iter()
+        .filter(|(_, list)| list.num_links() > 0)
+        .filter_map(|(_, list)| list.path().parent())
+        .map(|dir| filesystem::under(db.root(), dir))
+        .filter(|dir| !dir.exists())
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+
+    if dirs.is_empty() {
+        return;
+    }
+
+    // An unprivileged image build keeps the directories it creates.
+    let ids = if nix::unistd::geteuid().is_root() {
+        match config.link_dirs.ids() {
+            Ok(ids) => Some(ids),
+            Err(e) => {
+                error!(IO, "could not look up the owner \
+                           of link directories: {}", e);
+                None
+            },
+        }
+    } else {
+        None
+    };
+
+    for dir in dirs {
+        let mut missing: Vec<&std::path::Path> = dir.ancestors()
+            .take_while(|d| !d.exists())
+            .collect();
+        missing.reverse();
+
+        let created = filesystem::create_dir(&dir).and_then(|_| {
+            for d in missing.iter() {
+                filesystem::set_mode(d, 0o755)?;
+
+                if let Some((uid, gid)) = ids {
+                    nix::unistd::chown(*d, Some(uid), Some(gid))?;
+                }
+            }
+
+            Ok(())
+        });
+
+        match created {
+            Ok(()) => println!("update-alternatives: created link directory {}",
+                               dir.display()),
+            Err(e) => error!(IO, "could not create link \
+                                 directory {}: {}", dir.display(), e),
+        }
+    }
+}
+
+// Shows a progress bar on a terminal, and otherwise a line every few seconds
+// for hook logs.
+fn sync_links<K>(db: &AlternativeDb, keep: K) -> SyncResult
+where K: Fn(&alternative_list::AlternativeList) -> bool {
+    use std::io::IsTerminal;
+
+    let total = db.iter().filter(|(_, list)| keep(list)).count();
+
+    if std::io::stderr().is_terminal() {
+        let bar = indicatif::ProgressBar::new(total as u64);
+        bar.set_style(
+            indicatif::ProgressStyle::with_template(
+                "{bar:40} {pos}/{len} {wide_msg}"
+            ).expect("progress template is valid")
+        );
+
+        let result = db.write_links_each(&keep, |name| {
+            bar.set_message(name.to_string());
+            bar.inc(1);
+        });
+
+        bar.finish_and_clear();
+        db.print_changes(&result.0);
+
+        return result;
+    }
+
+    let mut done: usize = 0;
+    let mut last = std::time::Instant::now();
+
+    let result = db.write_links_each(keep, |_| {
+        done += 1;
+
+        if last.elapsed() >= PROGRESS_INTERVAL {
+            println!("update-alternatives: sync: {}/{} groups", done, total);
+            last = std::time::Instant::now();
+        }
+    });
+
+    db.print_changes(&result.0);
+
+    result
+}
+
+fn doctor(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let check = matches.get_one::<String>("CHECK").map(|s| s.as_str());
+    let runs = |name: &str| check.is_none() || check == Some(name);
+
+    let folder = db.folder();
+    let manifest_path = filesystem::under(db.root(), manifest::MANIFEST_PATH);
+    let mut problems: usize = 0;
+
+    if runs("permissions") {
+        problems += doctor::check_permissions(db, &folder);
+    }
+
+    if runs("path") {
+        for problem in doctor::check_path(db) {
+            println!("update-alternatives: doctor: {}", problem);
+            problems += 1;
+        }
+    }
+
+    if runs("cycles") {
+        for cycle in doctor::check_cycles(db) {
+            println!("update-alternatives: doctor: links of {} form a cycle",
+                     cycle.join(" -> "));
+            problems += 1;
+        }
+    }
+
+    if runs("manifest") && manifest::exists(&manifest_path) {
+        match manifest::verify(&folder, &manifest_path) {
+            Ok(n) => problems += n,
+            Err(e) => {
+                error!(MANIFEST, "could not verify {}: {}",
+                       manifest_path.display(), e);
+                problems += 1;
+            },
+        }
+    }
+
+    if transactional::is_pending(db.root()) {
+        println!("update-alternatives: doctor: symlinks are waiting for the \
+                 next boot ({} exists)",
+                 filesystem::under(db.root(), transactional::PENDING_PATH)
+                            .display());
+    }
+
+    if problems == 0 {
+        println!("update-alternatives: doctor found no problems");
+
+        return false;
+    }
+
+    error!(PROBLEMS, "doctor found {} problem(s)", problems);
+    std::process::exit(1);
+}
+
+fn status(db: &AlternativeDb, matches: &clap::ArgMatches,
+          out: &mut Option<std::fs::File>) -> bool {
+    let groups = filter::groups(db, &group_filter(matches, "GLOB"));
+
+    if matches.get_flag("PRINT0") {
+        print0(out, groups.into_iter().map(|(_, list)| list.path()));
+
+        return false;
+    }
+
+    let mut table = table::Table::new(&["NAME", "LINK", "CURRENT", "MODE",
+                                        "COUNT", "BEST"]);
+
+    for (name, list) in groups {
+        let linked = list.linked_target();
+        let best = list.current_target()
+                       .map(|t| t.display().to_string())
+                       .unwrap_or_else(|| String::from("<none>"));
+
+        let mut row = vec![name.clone()];
+        row.extend(group_cells(list, linked.as_deref()));
+        row.push(best);
+
+        table.push(row);
+    }
+
+    print_table(table, matches, GROUP_COLUMNS);
+
+    for problem in doctor::check_path(db) {
+        eprintln!("update-alternatives: warning: {}", problem);
+    }
+
+    report_foreign(db);
+
+    if let Some(f) = freeze::frozen(db.root()) {
+        eprintln!("update-alternatives: the database is {}", f);
+    }
+
+    false
+}
+
+// Entries of other tools are summed up, since dpkg alone keeps hundreds, while
+// anything else in the folder is unexpected and listed.
+fn report_foreign(db: &AlternativeDb) {
+    let foreign = db.foreign_entries();
+    let dpkg = foreign.iter()
+                      .filter(|e| e.kind == alternative_db::ForeignKind::Dpkg)
+                      .count();
+
+    if dpkg > 0 {
+        eprintln!("update-alternatives: {} dpkg alternatives in {} are left \
+                  alone", dpkg, db.folder().display());
+    }
+
+    for entry in foreign.iter()
+                        .filter(|e| e.kind != alternative_db::ForeignKind::Dpkg) {
+        eprintln!("update-alternatives: foreign {} {} is left alone",
+                  entry.kind, entry.path.display());
+    }
+}
+
+// The state a bug report starts from: what was built, where it reads and
+// writes, and how it would gain privileges.
+fn info(root: &std::path::Path, log: &AuditLog, matches: &clap::ArgMatches)
+-> bool {
+    let writable = |path: &std::path::Path| if filesystem::is_writable(path) {
+        "writable"
+    } else {
+        "not writable"
+    };
+    let database = filesystem::under(root, alternative_db::DB_FOLDER);
+    let links = filesystem::under(root, alternative_db::LINK_DIR);
+    let resolved = config::resolve(root);
+    let config = &resolved.config;
+    let features: Vec<&str> = [("dbus", cfg!(feature = "dbus"))]
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+
+    println!("update-alternatives {} (commit {})", clap::crate_version!(),
+             option_env!("UPDATE_ALTERNATIVES_COMMIT").unwrap_or("unknown"));
+    println!("  features: {}", if features.is_empty() {
+        String::from("none")
+    } else {
+        features.join(", ")
+    });
+    println!("  root: {}", root.display());
+    println!("  database: {} ({})", database.display(), writable(&database));
+    println!("  link directory: {} ({})", links.display(), writable(&links));
+    println!("  configuration: {} ({})", resolved.path.display(),
+             match resolved.origin {
+                 config::Origin::File => String::from("loaded"),
+                 config::Origin::Missing => String::from("not present, \
+                                                           defaults"),
+                 config::Origin::Invalid(ref e) => {
+                     format!("invalid, defaults: {}", e)
+                 },
+             });
+    println!("  read-only: {}", if filesystem::is_read_only() {
+        "yes"
+    } else {
+        "no"
+    });
+    println!("  strict: {}", if strict::is_strict() { "yes" } else { "no" });
+    println!("  escalation: {}", escalation(root, matches));
+    println!("  audit log: {}", log.path().display());
+    println!("  snapper: {}", if config.snapper.enabled {
+        format!("enabled, config {}", config.snapper.config)
+    } else {
+        String::from("disabled")
+    });
+    println!("  roots for --on: {}", if config.roots.is_empty() {
+        String::from("none")
+    } else {
+        config.roots.keys().cloned().collect::<Vec<_>>().join(", ")
+    });
+
+    false
+}
+
+// How a command that changes anything would get the privileges to, as main
+// decides it.
+fn escalation(root: &std::path::Path, matches: &clap::ArgMatches) -> String {
+    let on_path = |program: &str| {
+        std::env::var_os("PATH").is_some_and(|path| {
+            std::env::split_paths(&path).any(|dir| dir.join(program).is_file())
+        })
+    };
+
+    if root != std::path::Path::new("/") {
+        return String::from("never with --root");
+    }
+
+    if nix::unistd::geteuid().is_root() {
+        return String::from("not needed, running as root");
+    }
+
+    if filesystem::is_writable(filesystem::under(root,
+                                                 alternative_db::DB_FOLDER))
+       && filesystem::is_writable(filesystem::under(root,
+                                                    alternative_db::LINK_DIR)) {
+        return String::from("not needed, database and links are writable");
+    }
+
+    if matches.get_flag("no-escalate") || filesystem::is_read_only() {
+        return String::from("disabled");
+    }
+
+    let mut chain: Vec<&str> = Vec::new();
+
+    if cfg!(target_os = "linux") && on_path("pkexec") {
+        chain.push("pkexec");
+    }
+
+    if on_path("sudo") {
+        chain.push(if std::fs::File::open("/dev/tty").is_ok() {
+            "sudo"
+        } else {
+            "sudo -n"
+        });
+    }
+
+    if chain.is_empty() {
+        String::from("neither pkexec nor sudo found")
+    } else {
+        chain.join(", then ")
+    }
+}
+
+// Prints the description and common fixes of an error id, or every id with
+// its summary.
+fn explain_error(matches: &clap::ArgMatches) -> bool {
+    let id = match matches.get_one::<String>("ID") {
+        Some(i) => i,
+        None => {
+            for code in errors::CODES {
+                println!("{}  {}", code.id, code.summary);
+            }
+
+            return false;
+        },
+    };
+    let code = errors::lookup(id).unwrap_or_else(|| {
+        error!(BAD_ARGUMENT, "unknown error id {}; run 'update-alternatives \
+                             explain-error' for the list", id);
+        std::process::exit(1);
+    });
+
+    println!("{}: {}", code.id, code.summary);
+    println!();
+
+    for line in fill(code.description, 79) {
+        println!("{}", line);
+    }
+
+    println!();
+    println!("Common fixes:");
+
+    for fix in code.fixes {
+        for (i, line) in fill(fix, 75).iter().enumerate() {
+            println!("{}{}", if i == 0 { "  - " } else { "    " }, line);
+        }
+    }
+
+    false
+}
+
+// Breaks text into lines of at most width characters, between words.
+fn fill(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+
+        line.push_str(word);
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+fn verify(root: &std::path::Path, matches: &clap::ArgMatches) -> bool {
+    let folder = filesystem::under(root, alternative_db::DB_FOLDER);
+    let manifest_path = filesystem::under(root, manifest::MANIFEST_PATH);
+
+    if matches.get_flag("UPDATE") && filesystem::is_read_only() {
+        println!("update-alternatives: read-only: would record entries in {}",
+                 manifest_path.display());
+
+        return false;
+    }
+
+    if matches.get_flag("UPDATE") {
+        match manifest::write(&folder, &manifest_path) {
+            Ok(n) => {
+                println!("update-alternatives: recorded {} entries in {}", n,
+                         manifest_path.display());
+
+                return false;
+            },
+            Err(e) => {
+                error!(IO, "could not write {}: {}",
+                       manifest_path.display(), e);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    if !manifest::exists(&manifest_path) {
+        error!(MANIFEST, "no manifest found at {}; run \
+                         'update-alternatives verify --update' to create one",
+               manifest_path.display());
+        std::process::exit(1);
+    }
+
+    match manifest::verify(&folder, &manifest_path) {
+        Ok(0) => {
+            println!("update-alternatives: all entries match {}",
+                     manifest_path.display());
+
+            false
+        },
+        Ok(n) => {
+            error!(MANIFEST, "{} entries do not match {}", n,
+                   manifest_path.display());
+            std::process::exit(1);
+        },
+        Err(e) => {
+            error!(MANIFEST, "could not verify {}: {}",
+                   manifest_path.display(), e);
+            std::process::exit(1);
+        },
+    }
+}
+
+// Where the executable is looked for in an alternate root, in order.
+static INSTALLED_EXES: [&str; 2] = ["/usr/local/bin/update-alternatives",
+                                    "/usr/bin/update-alternatives"];
+
+// The executable that generated hooks and units run. Below an alternate root,
+// that is the one installed there, as seen from inside, rather than the one
+// building the image.
+fn installed_exe(root: &std::path::Path) -> std::path::PathBuf {
+    let exe = std::env::current_exe().unwrap_or_else(|_| {
+        std::path::PathBuf::from(INSTALLED_EXES[1])
+    });
+
+    if root == std::path::Path::new("/") {
+        return exe;
+    }
+
+    let inside = exe.strip_prefix(root).ok()
+                    .map(|p| std::path::Path::new("/").join(p));
+    let found = inside.into_iter()
+                      .chain(INSTALLED_EXES.iter()
+                                           .map(std::path::PathBuf::from))
+                      .find(|p| filesystem::under(root, p).is_file());
+
+    let exe = found.unwrap_or_else(|| {
+        eprintln!("update-alternatives: warning: update-alternatives is not \
+                  installed in {}; assuming {}", root.display(),
+                  INSTALLED_EXES[1]);
+
+        std::path::PathBuf::from(INSTALLED_EXES[1])
+    });
+
+    if let Err(e) = generate::check_exe(&exe) {
+        error!(BAD_ARGUMENT, "cannot run {} from a hook: {}",
+               exe.display(), e);
+        std::process::exit(1);
+    }
+
+    exe
+}
+
+fn generate_hook(root: &std::path::Path, matches: &clap::ArgMatches) -> bool {
+    let exe = installed_exe(root);
+
+    let (contents, default_path) = match matches.get_one::<String>("KIND")
+                                                .map(|s| s.as_str()) {
+        Some("apt") => match generate::apt_hook(&exe) {
+            Ok(hook) => (hook, Some(generate::APT_HOOK_PATH)),
+            Err(e) => {
+                error!(HOOK, "could not generate a hook for \
+                             {}: {}", exe.display(), e);
+                std::process::exit(1);
+            },
+        },
+        Some("dnf") => (generate::dnf_hook(&exe),
+                        Some(generate::DNF_HOOK_PATH)),
+        Some("rpm") => (generate::rpm_trigger(&exe), None),
+        Some("shell") => (generate::shell_hook(), None),
+        _ => unreachable!("clap only accepts known hook kinds"),
+    };
+
+    let path = match matches.get_one::<String>("OUTPUT")
+                            .map(std::path::PathBuf::from)
+                            .or_else(|| default_path.map(|p| {
+                                filesystem::under(root, p)
+                            })) {
+        Some(ref p) if filesystem::is_read_only()
+                       && !matches.get_flag("STDOUT") => {
+            println!("update-alternatives: read-only: would write {}:",
+                     p.display());
+            print!("{}", contents);
+
+            return false;
+        },
+        Some(p) if !matches.get_flag("STDOUT") => p,
+        _ => {
+            print!("{}", contents);
+
+            return false;
+        },
+    };
+
+    if let Err(e) = generate::install(contents, &path) {
+        error!(IO, "could not write {}: {}", path.display(), e);
+        std::process::exit(1);
+    }
+
+    println!("update-alternatives: wrote {}", path.display());
+
+    false
+}
+
+fn generate_systemd(root: &std::path::Path, matches: &clap::ArgMatches)
+-> bool {
+    let exe = installed_exe(root);
+
+    let mut units = vec![(generate::SYNC_SERVICE, generate::sync_service(&exe))];
+
+    if let Some(calendar) = matches.get_one::<String>("TIMER") {
+        units.push((generate::SYNC_TIMER, generate::sync_timer(calendar)));
+    }
+
+    if matches.get_flag("PATH") {
+        units.push((generate::SYNC_PATH,
+                    generate::sync_path(alternative_db::DB_FOLDER)));
+    }
+
+    if matches.get_flag("EXPIRE") {
+        units.push((generate::EXPIRE_SERVICE, generate::expire_service(&exe)));
+        units.push((generate::EXPIRE_TIMER, generate::expire_timer()));
+    }
+
+    if matches.get_flag("HELPER") {
+        units.push((generate::HELPER_SOCKET, generate::helper_socket()));
+        units.push((generate::HELPER_SERVICE, generate::helper_service(&exe)));
+    }
+
+    if matches.get_flag("WATCH") {
+        units.push((generate::WATCH_SERVICE, generate::watch_service(&exe)));
+    }
+
+    if matches.get_flag("SERVE") {
+        units.push((generate::SERVE_SERVICE, generate::serve_service(&exe)));
+    }
+
+    if matches.get_flag("STDOUT") {
+        for (name, contents) in units.iter() {
+            print!("# {}\n{}", name, contents);
+        }
+
+        return false;
+    }
+
+    let dir = matches.get_one::<String>("OUTPUT")
+                     .map(std::path::PathBuf::from)
+                     .unwrap_or_else(|| {
+                         filesystem::under(root, generate::SYSTEMD_UNIT_DIR)
+                     });
+
+    if filesystem::is_read_only() {
+        for (name, contents) in units.iter() {
+            println!("update-alternatives: read-only: would write {}:",
+                     dir.join(name).display());
+            print!("{}", contents);
+        }
+
+        return false;
+    }
+
+    for (name, contents) in units.iter() {
+        let path = dir.join(name);
+
+        if let Err(e) = generate::install(contents.as_str(), &path) {
+            error!(IO, "could not write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+
+        println!("update-alternatives: wrote {}", path.display());
+    }
+
+    // The expire service is started by its timer only, and the helper by
+    // its socket.
+    let enable: Vec<&str> = units.iter().map(|&(n, _)| n)
+                                 .filter(|n| *n != generate::EXPIRE_SERVICE
+                                             && *n != generate::HELPER_SERVICE)
+                                 .collect();
+    println!("update-alternatives: run 'systemctl daemon-reload' and \
+             'systemctl enable {}' to activate", enable.join(" "));
+
+    false
+}
+
+fn run_freeze(root: &std::path::Path, log: &mut AuditLog,
+              matches: &clap::ArgMatches) -> bool {
+    if let Some(f) = freeze::frozen(root) {
+        println!("update-alternatives: the database is already {}", f);
+
+        return false;
+    }
+
+    if filesystem::is_read_only() {
+        println!("update-alternatives: read-only: would freeze the database");
+
+        return false;
+    }
+
+    let reason = matches.get_one::<String>("REASON").cloned();
+
+    match freeze::freeze(root, reason) {
+        Ok(f) => {
+            println!("update-alternatives: the database is {}; changes are \
+                     refused until 'update-alternatives thaw'", f);
+            log.record(format!("freeze{}", f.reason.map(|r| format!(": {}", r))
+                                               .unwrap_or_default()));
+        },
+        Err(e) => {
+            error!(IO, "could not write {}: {}",
+                   filesystem::under(root, freeze::FREEZE_PATH).display(),
+                   e);
+            std::process::exit(1);
+        },
+    }
+
+    false
+}
+
+fn run_trash(root: &std::path::Path, log: &mut AuditLog,
+             matches: &clap::ArgMatches) -> bool {
+    match matches.subcommand() {
+        Some(("list", sub_m)) => trash_list(root, sub_m),
+        Some(("restore", sub_m)) => trash_restore(root, log, sub_m),
+        _ => unreachable!("clap requires a subcommand of trash"),
+    }
+}
+
+fn trash_list(root: &std::path::Path, matches: &clap::ArgMatches) -> bool {
+    let items = trash::list(root).unwrap_or_else(|e| {
+        error!(IO, "could not read {}: {}",
+               filesystem::under(root, trash::TRASH_DIR).display(), e);
+        std::process::exit(1);
+    });
+
+    if items.is_empty() {
+        println!("update-alternatives: the trash is empty");
+
+        return false;
+    }
+
+    let mut table = table::Table::new(&["ID", "PATH", "KIND", "TIME", "USER",
+                                        "REPLACED BY"]);
+
+    for item in items {
+        let info = item.info;
+
+        table.push(vec![item.id, info.path.display().to_string(), info.kind,
+                        info.time, info.user,
+                        info.replaced_by.display().to_string()]);
+    }
+
+    table.print(matches.get_flag("WIDE"));
+
+    false
+}
+
+fn trash_restore(root: &std::path::Path, log: &mut AuditLog,
+                 matches: &clap::ArgMatches) -> bool {
+    let id = matches.get_one::<String>("ID").unwrap();
+    let to = matches.get_one::<String>("TO").map(std::path::Path::new);
+
+    if to.is_some_and(|t| !t.is_absolute()) {
+        error!(BAD_ARGUMENT, "--to must be an absolute path");
+        std::process::exit(1);
+    }
+
+    if filesystem::is_read_only() {
+        println!("update-alternatives: read-only: would restore {} from the \
+                 trash", id);
+
+        return false;
+    }
+
+    // So that no commit writes a link where the file is being restored.
+    let _lock = lock::acquire(root).unwrap_or_else(|e| {
+        error!(LOCKED, "could not lock {}: {}",
+               filesystem::under(root, lock::LOCK_PATH).display(), e);
+        std::process::exit(1);
+    });
+
+    match trash::restore(root, id, to) {
+        Ok(path) => {
+            println!("update-alternatives: restored {} to {}", id,
+                     path.display());
+            log.record(format!("trash restore {} {}", id, path.display()));
+        },
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+            error!(NOT_IN_TRASH, "{}; see 'update-alternatives trash list'",
+                   e);
+            std::process::exit(EXIT_NOT_FOUND);
+        },
+        Err(ref e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            error!(CONFLICT, "{}; remove it first, or restore to another path \
+                             with --to", e);
+            std::process::exit(1);
+        },
+        Err(e) => {
+            error!(IO, "could not restore {}: {}", id, e);
+            std::process::exit(1);
+        },
+    }
+
+    false
+}
+
+fn run_thaw(root: &std::path::Path, log: &mut AuditLog) -> bool {
+    if filesystem::is_read_only() {
+        if freeze::frozen(root).is_some() {
+            println!("update-alternatives: read-only: would thaw the database");
+        } else {
+            println!("update-alternatives: the database is not frozen");
+        }
+
+        return false;
+    }
+
+    match freeze::thaw(root) {
+        Ok(true) => {
+            println!("update-alternatives: the database is thawed");
+            log.record("thaw");
+        },
+        Ok(false) => println!("update-alternatives: the database is not frozen"),
+        Err(e) => {
+            error!(IO, "could not remove {}: {}",
+                   filesystem::under(root, freeze::FREEZE_PATH).display(),
+                   e);
+            std::process::exit(1);
+        },
+    }
+
+    false
+}
+
+fn run_watch(log: &mut AuditLog, via_helper: bool) -> bool {
+    if let Err(e) = watch::run(alternative_db::DB_FOLDER, log, via_helper) {
+        error!(SERVICE, "watch failed: {}", e);
+        std::process::exit(1);
+    }
+
+    false
+}
+
+fn run_serve(log: &mut AuditLog, matches: &clap::ArgMatches) -> bool {
+    let listen = matches.get_one::<String>("LISTEN").unwrap();
+    let token_path = matches.get_one::<String>("TOKEN_FILE")
+                            .map(|s| s.as_str())
+                            .unwrap_or(serve::TOKEN_PATH);
+
+    let token = match serve::load_or_create_token(token_path) {
+        Ok(t) => t,
+        Err(e) => {
+            error!(CONFIG, "could not read API token from {}: \
+                           {}", token_path, e);
+            std::process::exit(1);
+        },
+    };
+
+    if let Err(e) = serve::run(alternative_db::DB_FOLDER, listen, &token, log) {
+        error!(SERVICE, "serve failed: {}", e);
+        std::process::exit(1);
+    }
+
+    false
+}
+
+fn run_helper(matches: &clap::ArgMatches) -> bool {
+    let log_path = matches.get_one::<String>("log")
+                          .map(|s| s.as_str())
+                          .unwrap_or(audit::LOG_PATH);
+
+    if let Err(e) = helper::run(log_path) {
+        error!(SERVICE, "helper failed: {}", e);
+        std::process::exit(1);
+    }
+
+    false
+}
+
+#[cfg(feature = "dbus")]
+fn run_dbus_service(matches: &clap::ArgMatches) -> bool {
+    let log_path = matches.get_one::<String>("log")
+                          .map(|s| s.as_str())
+                          .unwrap_or(audit::LOG_PATH);
+
+    if let Err(e) = dbus::run(alternative_db::DB_FOLDER, log_path) {
+        error!(SERVICE, "D-Bus service failed: {}", e);
+        std::process::exit(1);
+    }
+
+    false
+}
+
+#[cfg(feature = "dbus")]
+fn with_dbus(app: clap::Command) -> clap::Command {
+    app.subcommand(clap::Command::new("dbus-service").about(DBUS_SERVICE_ABOUT))
+}
+
+#[cfg(not(feature = "dbus"))]
+fn with_dbus(app: clap::Command) -> clap::Command {
+    app
+}
+
+fn report_arg() -> clap::Arg {
+    clap::Arg::new("REPORT")
+        .help("Print only 'changed' or 'unchanged' on stdout; the exit status \
+               still reflects success")
+        .long("report")
+        .action(clap::ArgAction::SetTrue)
+}
+
+fn tag_arg() -> clap::Arg {
+    clap::Arg::new("TAG")
+        .long("tag")
+        .value_name("TAG")
+        .value_parser(parse_tag)
+        .num_args(1)
+}
+
+fn format_arg() -> clap::Arg {
+    clap::Arg::new("FORMAT")
+        .help("Print the result as text, or as a single JSON object on stdout \
+               with all other messages on stderr")
+        .long("format")
+        .value_name("FORMAT")
+        .value_parser(["text", "json"])
+        .default_value("text")
+}
+
+fn print0_arg() -> clap::Arg {
+    clap::Arg::new("PRINT0")
+        .help("Print only the names or paths, each terminated by a NUL byte")
+        .short('0')
+        .long("print0")
+        .action(clap::ArgAction::SetTrue)
+}
+
+fn columns_arg(columns: &'static str) -> clap::Arg {
+    clap::Arg::new("COLUMNS")
+        .help(columns)
+        .long("columns")
+        .value_name("COLUMN,...")
+        .value_delimiter(',')
+        .num_args(1)
+}
+
+fn regex_arg() -> clap::Arg {
+    clap::Arg::new("REGEX")
+        .help("Only show groups whose name matches this regular expression")
+        .long("regex")
+        .value_name("REGEX")
+        .num_args(1)
+}
+
+fn wide_arg() -> clap::Arg {
+    clap::Arg::new("WIDE")
+        .help("Do not truncate columns to the width of the terminal")
+        .short('w')
+        .long("wide")
+        .action(clap::ArgAction::SetTrue)
+}
+
+fn app() -> clap::Command {
+    use clap::{Arg, Command};
+    with_dbus(Command::new("update-alternatives")
+        .version(clap::crate_version!())
+        .author(clap::crate_authors!())
+        .about(ABOUT)
+        .arg(
+            Arg::new("gui")
+                .help("Launch a simple graphical interface for listing and syncing alternatives")
+                .long("gui")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("read-only")
+        )
+        .arg(
+            Arg::new("rpc")
+                .help("Read newline-delimited JSON-RPC requests (add, remove, \
+                       list) from stdin and write responses to stdout, \
+                       committing all changes once at the end")
+                .long("rpc")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("gui")
+        )
+        .arg(
+            Arg::new("read-only")
+                .help("Never escalate and never write; print what would have \
+                       changed instead")
+                .long("read-only")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("strict")
+                .help("Treat warnings about the database, such as missing \
+                       targets, shared priorities, shadowed links, and \
+                       foreign entries, as errors: fail, and commit nothing")
+                .long("strict")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("no-escalate")
+                .help("Never re-run through pkexec or sudo; fail with a \
+                       permission error instead if access is denied")
+                .long("no-escalate")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("timings")
+                .help("Report the time spent loading, validating, and \
+                       writing the database and its links on stderr, and \
+                       every group that takes at least MS in one of them")
+                .long("timings")
+                .value_name("MS")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("10")
+                .value_parser(clap::value_parser!(u64))
+                .global(true),
+        )
+        .arg(
+            Arg::new("root")
+                .help("Operate on the system below DIR, e.g. an image being \
+                       built; defaults to $DESTDIR if set. Never escalates.")
+                .long("root")
+                .value_name("DIR")
+                .num_args(1)
+                .global(true)
+        )
+        .arg(
+            Arg::new("on")
+                .help("Run the command below each of the roots named in the \
+                       [roots] table of /etc/update-alternatives.toml, or \
+                       host for /, and report how each went")
+                .long("on")
+                .value_name("NAME,...")
+                .value_delimiter(',')
+                .num_args(1)
+                .conflicts_with("root")
+                .global(true)
+        )
+        .arg(
+            Arg::new("log")
+                .help("Append audit log entries to FILE instead of \
+                       /var/log/update-alternatives.log")
+                .long("log")
+                .value_name("FILE")
+                .num_args(1)
+                .global(true),
+        )
+        .subcommand(
+            Command::new("list")
+                .about(LIST_ABOUT)
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives to query")
+                        .value_name("NAME")
+                        .short('n')
+                        .long("name")
+                        .num_args(1)
+                        .group("GROUP_NAME")
+                        .conflicts_with_all(["NAME_POS", "ALL", "TAG"]),
+                )
+                .arg(
+                    Arg::new("NAME_POS")
+                        .help("The name of the alternatives to query, or a \
+                               glob of group names with --all")
+                        .value_name("NAME")
+                        .index(1)
+                        .group("GROUP_NAME")
+                        .conflicts_with("NAME"),
+                )
+                .arg(regex_arg().requires("ALL"))
+                .arg(
+                    Arg::new("ALL")
+                        .help("List the alternatives of every group as a \
+                               table, as without NAME")
+                        .short('a')
+                        .long("all")
+                        .action(clap::ArgAction::SetTrue)
+                        .default_value_if("TAG",
+                                          clap::builder::ArgPredicate::IsPresent,
+                                          "true"),
+                )
+                .arg(tag_arg().help("List only the groups tagged TAG; implies \
+                                     --all"))
+                .arg(
+                    Arg::new("TRIPLET")
+                        .help("List only the groups of a cross toolchain, \
+                               such as aarch64-linux-gnu")
+                        .long("triplet")
+                        .value_name("TRIPLET")
+                        .num_args(1)
+                        .requires("ALL"),
+                )
+                .arg(wide_arg())
+                .arg(columns_arg(ALTERNATIVE_COLUMNS_HELP).requires("ALL"))
+                .arg(print0_arg().conflicts_with("VERBOSE"))
+                .arg(
+                    Arg::new("PROBE")
+                        .help("Run every target with --version and show the \
+                               version it reports")
+                        .long("probe")
+                        .action(clap::ArgAction::SetTrue)
+                        .requires("GROUP_NAME")
+                        .conflicts_with_all(["ALL", "PRINT0"]),
+                )
+                .arg(
+                    Arg::new("ARCHIVED")
+                        .help("Also list the alternatives removed with \
+                               --archive")
+                        .long("archived")
+                        .action(clap::ArgAction::SetTrue)
+                        .requires("GROUP_NAME")
+                        .conflicts_with_all(["ALL", "PROBE", "VERBOSE"]),
+                )
+                .arg(
+                    Arg::new("VERBOSE")
+                        .help("Show the package that owns each target")
+                        .short('v')
+                        .long("verbose")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(format_arg().help("Print the groups as text, or as JSON \
+                                        on stdout with all other messages on \
+                                        stderr: an object for NAME, an array \
+                                        with --all")
+                                 .conflicts_with_all(["PRINT0", "PROBE",
+                                                      "VERBOSE", "COLUMNS",
+                                                      "WIDE"])),
+        )
+        .subcommand(
+            Command::new("display")
+                .about(DISPLAY_ABOUT)
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives to display")
+                        .value_name("NAME")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("RESOLVE")
+                        .help("Follow the link through every symlink to the \
+                               file it finally points to")
+                        .long("resolve")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("query")
+                .about(QUERY_ABOUT)
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives to query")
+                        .value_name("NAME")
+                        .index(1)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("show")
+                .about(SHOW_ABOUT)
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives")
+                        .value_name("NAME")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("TARGET")
+                        .help("The target of the alternative to show")
+                        .value_name("TARGET")
+                        .value_parser(path_parser())
+                        .index(2)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("explain")
+                .about(EXPLAIN_ABOUT)
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives")
+                        .value_name("NAME")
+                        .index(1)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("blame")
+                .about(BLAME_ABOUT)
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives")
+                        .value_name("NAME")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(wide_arg()),
+        )
+        .subcommand(
+            Command::new("test")
+                .about(TEST_ABOUT)
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives")
+                        .value_name("NAME")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("ARGS")
+                        .help("Arguments to run the target with [default: \
+                               those of list --probe]")
+                        .value_name("ARGS")
+                        .index(2)
+                        .num_args(1..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about(DIFF_ABOUT)
+                .arg(
+                    Arg::new("OLD")
+                        .help("The earlier state")
+                        .value_name("STATE")
+                        .index(1)
+                        .required_unless_present("AGAINST"),
+                )
+                .arg(
+                    Arg::new("NEW")
+                        .help("The later state")
+                        .value_name("STATE")
+                        .index(2)
+                        .required_unless_present("AGAINST"),
+                )
+                .arg(
+                    Arg::new("AGAINST")
+                        .help("Compare the current database against an \
+                               earlier state")
+                        .value_name("STATE")
+                        .long("against")
+                        .num_args(1)
+                        .conflicts_with_all(["OLD", "NEW"]),
+                ),
+        )
+        .subcommand(
+            Command::new("add")
+                .about(ADD_ABOUT)
+                .arg(
+                    Arg::new("TARGET")
+                        .help("The target of the alternative to add")
+                        .value_name("TARGET")
+                        .value_parser(path_parser())
+                        .short('t')
+                        .long("target")
+                        .num_args(1)
+                        .required_unless_present("TARGET_POS")
+                        .conflicts_with("TARGET_POS"),
+                )
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternative to add")
+                        .value_name("NAME")
+                        .value_parser(parse_name)
+                        .short('n')
+                        .long("name")
+                        .num_args(1)
+                        .required_unless_present("NAME_POS")
+                        .conflicts_with("NAME_POS"),
+                )
+                .arg(
+                    Arg::new("WEIGHT")
+                        .help("The priority of the alternative to add \
+                               [default: from the weight policy of <NAME>]")
+                        .value_name("WEIGHT")
+                        .value_parser(weight_parser())
+                        .allow_negative_numbers(true)
+                        .short('w')
+                        .long("weight")
+                        .num_args(1)
+                        .conflicts_with("WEIGHT_POS"),
+                )
+                .arg(
+                    Arg::new("NAME_POS")
+                        .help("The name of the alternative to add")
+                        .value_name("NAME")
+                        .value_parser(parse_name)
+                        .index(1)
+                        .required_unless_present("NAME")
+                        .conflicts_with("NAME"),
+                )
+                .arg(
+                    Arg::new("TARGET_POS")
+                        .help("The target of the alternative to add")
+                        .value_name("TARGET")
+                        .value_parser(path_parser())
+                        .index(2)
+                        .required_unless_present("TARGET")
+                        .conflicts_with("TARGET"),
+                )
+                .arg(
+                    Arg::new("WEIGHT_POS")
+                        .help("The priority of the alternative to add")
+                        .value_name("WEIGHT")
+                        .value_parser(weight_parser())
+                        .allow_negative_numbers(true)
+                        .index(3)
+                        .conflicts_with("WEIGHT"),
+                )
+                .arg(
+                    Arg::new("WEIGHT_FROM_VERSION")
+                        .help("Derive the priority from the version in the \
+                               path of <TARGET>, or else from its --version \
+                               output, e.g. 3.12.4 -> 31204")
+                        .long("weight-from-version")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with_all(["WEIGHT", "WEIGHT_POS"]),
+                )
+                .arg(
+                    Arg::new("LINK")
+                        .help("The link to manage if <NAME> is a new group \
+                               [default: /usr/local/bin/<NAME>]")
+                        .value_name("LINK")
+                        .value_parser(path_parser())
+                        .short('l')
+                        .long("link")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("SLAVE")
+                        .help("A link that follows <NAME>: while <TARGET> is \
+                               selected, LINK points at PATH; may be repeated, \
+                               and replaces the followers of <TARGET>")
+                        .value_names(["LINK", "PATH"])
+                        .value_parser(path_parser())
+                        .long("slave")
+                        .num_args(2)
+                        .action(clap::ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("LIBRARY")
+                        .help("Make <NAME> a new group of shared libraries, \
+                               linked in /usr/local/lib unless --link is \
+                               given, after whose switch ldconfig is run")
+                        .long("library")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("MIME_TYPE"),
+                )
+                .arg(
+                    Arg::new("UNIT")
+                        .help("Make <NAME> a new group of systemd units, \
+                               such as display-manager.service, linked in \
+                               /etc/systemd/system unless --link is given, \
+                               after whose switch systemd is reloaded")
+                        .long("unit")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with_all(["LIBRARY", "MIME_TYPE"]),
+                )
+                .arg(
+                    Arg::new("MIME_TYPE")
+                        .help("Make <NAME> a new group whose selection is the \
+                               default application for TYPE in the \
+                               mimeapps.list at <LINK> [default: \
+                               /etc/xdg/mimeapps.list]; may be repeated")
+                        .value_name("TYPE")
+                        .long("mime-type")
+                        .action(clap::ArgAction::Append),
+                )
+                .arg(report_arg())
+                .arg(format_arg().conflicts_with("REPORT")),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about(REMOVE_ABOUT)
+                .arg(
+                    Arg::new("TARGET")
+                        .help("The target of the alternative to remove")
+                        .value_name("TARGET")
+                        .value_parser(clap::value_parser!(std::path::PathBuf))
+                        .short('t')
+                        .long("target")
+                        .num_args(1)
+                        .required_unless_present("TARGET_POS")
+                        .conflicts_with("TARGET_POS"),
+                )
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternative to remove")
+                        .value_name("NAME")
+                        .short('n')
+                        .long("name")
+                        .num_args(1)
+                        .required_unless_present("NAME_POS")
+                        .conflicts_with("NAME_POS"),
+                )
+                .arg(
+                    Arg::new("NAME_POS")
+                        .help("The name of the alternative to remove")
+                        .value_name("NAME")
+                        .index(1)
+                        .required_unless_present("NAME")
+                        .conflicts_with("NAME"),
+                )
+                .arg(
+                    Arg::new("TARGET_POS")
+                        .help("The target of the alternative to remove")
+                        .value_name("TARGET")
+                        .value_parser(clap::value_parser!(std::path::PathBuf))
+                        .index(2)
+                        .required_unless_present("TARGET")
+                        .conflicts_with("TARGET"),
+                )
+                .arg(
+                    Arg::new("IGNORE_MISSING")
+                        .help("Succeed without changes if there is no such \
+                               group or target")
+                        .long("ignore-missing")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("ARCHIVE")
+                        .help("Keep the alternative, with its priority and \
+                               package, for restore instead of forgetting it")
+                        .long("archive")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(report_arg()),
+        )
+        .subcommand(
+            Command::new("restore")
+                .about(RESTORE_ABOUT)
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives")
+                        .value_name("NAME")
+                        .value_parser(parse_name)
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("TARGET")
+                        .help("The archived target to restore")
+                        .value_name("TARGET")
+                        .value_parser(path_parser())
+                        .index(2)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("set")
+                .about(SET_ABOUT)
+                .arg(
+                    Arg::new("TARGET")
+                        .help("The target to select")
+                        .value_name("TARGET")
+                        .value_parser(clap::value_parser!(std::path::PathBuf))
+                        .short('t')
+                        .long("target")
+                        .num_args(1)
+                        .required_unless_present("TARGET_POS")
+                        .conflicts_with("TARGET_POS"),
+                )
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives")
+                        .value_name("NAME")
+                        .short('n')
+                        .long("name")
+                        .num_args(1)
+                        .required_unless_present("NAME_POS")
+                        .conflicts_with("NAME_POS"),
+                )
+                .arg(
+                    Arg::new("NAME_POS")
+                        .help("The name of the alternatives")
+                        .value_name("NAME")
+                        .index(1)
+                        .required_unless_present("NAME")
+                        .conflicts_with("NAME"),
+                )
+                .arg(
+                    Arg::new("TARGET_POS")
+                        .help("The target to select")
+                        .value_name("TARGET")
+                        .value_parser(clap::value_parser!(std::path::PathBuf))
+                        .index(2)
+                        .required_unless_present("TARGET")
+                        .conflicts_with("TARGET"),
+                )
+                .arg(
+                    Arg::new("UNTIL")
+                        .help("Return to automatic selection at WHEN, a \
+                               duration such as 2h or 1h30m or a UTC time \
+                               such as 2026-10-17T18:00Z, once expire runs")
+                        .value_name("WHEN")
+                        .long("until")
+                        .num_args(1)
+                        .value_parser(parse_until),
+                )
+                .arg(report_arg()),
+        )
+        .subcommand(
+            Command::new("auto")
+                .about(AUTO_ABOUT)
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives")
+                        .value_name("NAME")
+                        .short('n')
+                        .long("name")
+                        .num_args(1)
+                        .required_unless_present("NAME_POS")
+                        .conflicts_with("NAME_POS"),
+                )
+                .arg(
+                    Arg::new("NAME_POS")
+                        .help("The name of the alternatives")
+                        .value_name("NAME")
+                        .index(1)
+                        .required_unless_present("NAME")
+                        .conflicts_with("NAME"),
+                )
+                .arg(report_arg())
         )
         .subcommand(
-            Command::new("list")
-                .about(LIST_ABOUT)
+            Command::new("config")
+                .about(CONFIG_ABOUT)
                 .arg(
                     Arg::new("NAME")
-                        .help("The name of the alternatives to query")
+                        .help("The name of the alternatives")
                         .value_name("NAME")
                         .short('n')
                         .long("name")
@@ -384,118 +5209,524 @@ fn app() -> clap::Command {
                 )
                 .arg(
                     Arg::new("NAME_POS")
-                        .help("The name of the alternatives to query")
+                        .help("The name of the alternatives")
                         .value_name("NAME")
                         .index(1)
                         .required_unless_present("NAME")
                         .conflicts_with("NAME"),
+                )
+                .arg(report_arg())
+        )
+        .subcommand(
+            Command::new("expire").about(EXPIRE_ABOUT).arg(report_arg())
+        )
+        .subcommand(
+            Command::new("freeze")
+                .about(FREEZE_ABOUT)
+                .arg(
+                    Arg::new("REASON")
+                        .help("Why the database is frozen, shown to everyone \
+                               whose changes are refused")
+                        .value_name("REASON")
+                        .short('r')
+                        .long("reason")
+                        .num_args(1),
                 ),
         )
+        .subcommand(Command::new("thaw").about(THAW_ABOUT))
         .subcommand(
-            Command::new("add")
-                .about(ADD_ABOUT)
+            Command::new("trash")
+                .about(TRASH_ABOUT)
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("list")
+                        .about(TRASH_LIST_ABOUT)
+                        .arg(
+                            Arg::new("WIDE")
+                                .help("Do not shorten long paths to fit the \
+                                       terminal")
+                                .long("wide")
+                                .action(clap::ArgAction::SetTrue),
+                        ),
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about(TRASH_RESTORE_ABOUT)
+                        .arg(
+                            Arg::new("ID")
+                                .help("The id that trash list shows")
+                                .value_name("ID")
+                                .index(1)
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::new("TO")
+                                .help("Restore to PATH instead of where it \
+                                       was")
+                                .value_name("PATH")
+                                .long("to")
+                                .num_args(1),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("retarget")
+                .about(RETARGET_ABOUT)
                 .arg(
-                    Arg::new("TARGET")
-                        .help("The target of the alternative to add")
-                        .value_name("TARGET")
-                        .short('t')
-                        .long("target")
-                        .num_args(1)
-                        .required_unless_present("TARGET_POS")
-                        .conflicts_with("TARGET_POS"),
+                    Arg::new("OLD")
+                        .help("The target to move")
+                        .value_name("OLD_PATH")
+                        .value_parser(path_parser())
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("NEW")
+                        .help("Where the target was moved to")
+                        .value_name("NEW_PATH")
+                        .value_parser(path_parser())
+                        .index(2)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("copy")
+                .about(COPY_ABOUT)
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives to copy")
+                        .value_name("NAME")
+                        .value_parser(parse_name)
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("NEW_NAME")
+                        .help("The name of the new group")
+                        .value_name("NEWNAME")
+                        .value_parser(parse_name)
+                        .index(2)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("LINK")
+                        .help("The link of the new group [default: \
+                               /usr/local/bin/<NEWNAME>]")
+                        .value_name("LINK")
+                        .value_parser(path_parser())
+                        .short('l')
+                        .long("link")
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("adopt")
+                .about(ADOPT_ABOUT)
+                .arg(
+                    Arg::new("LINKS")
+                        .help("The symlinks to adopt")
+                        .value_name("LINK")
+                        .value_parser(path_parser())
+                        .index(1)
+                        .num_args(1..)
+                        .required(true),
                 )
                 .arg(
                     Arg::new("NAME")
-                        .help("The name of the alternative to add")
+                        .help("The name of the new group [default: the file \
+                               name of <LINK>]")
                         .value_name("NAME")
+                        .value_parser(parse_name)
                         .short('n')
                         .long("name")
-                        .num_args(1)
-                        .required_unless_present("NAME_POS")
-                        .conflicts_with("NAME_POS"),
+                        .num_args(1),
                 )
                 .arg(
                     Arg::new("WEIGHT")
-                        .help("The priority of the alternative to add")
+                        .help("The priority of the adopted target")
                         .value_name("WEIGHT")
+                        .value_parser(weight_parser())
+                        .allow_negative_numbers(true)
                         .short('w')
                         .long("weight")
                         .num_args(1)
-                        .required_unless_present("WEIGHT_POS")
-                        .conflicts_with("WEIGHT_POS"),
+                        .default_value("0"),
+                ),
+        )
+        .subcommand(
+            Command::new("renumber")
+                .about(RENUMBER_ABOUT)
+                .arg(
+                    Arg::new("NAME")
+                        .help("The name of the alternatives to renumber")
+                        .value_name("NAME")
+                        .value_parser(parse_name)
+                        .index(1)
+                        .required(true),
                 )
+                .arg(
+                    Arg::new("STEP")
+                        .help("The priority of the lowest alternative, and the \
+                               distance to the next")
+                        .value_name("STEP")
+                        .value_parser(clap::value_parser!(i64)
+                                          .range(1..=alternative::MAX_PRIORITY))
+                        .long("step")
+                        .default_value("10"),
+                ),
+        )
+        .subcommand(
+            Command::new("env")
+                .about(ENV_ABOUT)
                 .arg(
                     Arg::new("NAME")
-                        .help("The name of the alternative to add")
+                        .help("The name of the alternatives")
                         .value_name("NAME")
+                        .value_parser(parse_name)
                         .index(1)
-                        .required_unless_present("NAME")
-                        .conflicts_with("NAME"),
+                        .required(true),
                 )
                 .arg(
-                    Arg::new("TARGET")
-                        .help("The target of the alternative to add")
-                        .value_name("TARGET")
+                    Arg::new("ASSIGNMENTS")
+                        .help("Variables to export, such as \
+                               JAVA_HOME=<target-root>; <target>, \
+                               <target-dir>, and <target-root> stand for the \
+                               selected target, its directory, and the one \
+                               above")
+                        .value_name("VARIABLE=TEMPLATE")
                         .index(2)
-                        .required_unless_present("TARGET")
-                        .conflicts_with("TARGET"),
+                        .num_args(1..),
                 )
                 .arg(
-                    Arg::new("WEIGHT")
-                        .help("The priority of the alternative to add")
-                        .value_name("WEIGHT")
-                        .index(3)
-                        .required_unless_present("WEIGHT")
-                        .conflicts_with("WEIGHT"),
+                    Arg::new("UNSET")
+                        .help("Stop exporting VARIABLE; may be repeated")
+                        .value_name("VARIABLE")
+                        .long("unset")
+                        .action(clap::ArgAction::Append),
                 ),
         )
         .subcommand(
-            Command::new("remove")
-                .about(REMOVE_ABOUT)
+            Command::new("tag")
+                .about(TAG_ABOUT)
                 .arg(
-                    Arg::new("TARGET")
-                        .help("The target of the alternative to remove")
-                        .value_name("TARGET")
-                        .short('t')
-                        .long("target")
-                        .num_args(1)
-                        .required_unless_present("TARGET_POS")
-                        .conflicts_with("TARGET_POS"),
+                    Arg::new("NAME")
+                        .help("The name of the alternatives")
+                        .value_name("NAME")
+                        .value_parser(parse_name)
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("TAGS")
+                        .help("Tags to add, such as toolchain or site-local")
+                        .value_name("TAG")
+                        .value_parser(parse_tag)
+                        .index(2)
+                        .num_args(1..),
                 )
+                .arg(
+                    Arg::new("UNTAG")
+                        .help("Remove TAG; may be repeated")
+                        .value_name("TAG")
+                        .value_parser(parse_tag)
+                        .long("remove")
+                        .action(clap::ArgAction::Append),
+                ),
+        )
+        .subcommand(
+            Command::new("weights")
+                .about(WEIGHTS_ABOUT)
                 .arg(
                     Arg::new("NAME")
-                        .help("The name of the alternative to remove")
+                        .help("The name of the alternatives")
                         .value_name("NAME")
+                        .value_parser(parse_name)
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("POLICY")
+                        .help("How add derives the priority of a new \
+                               alternative without one: not at all \
+                               (explicit), from its version, fixed:PRIORITY, \
+                               or step:STEP above the highest")
+                        .value_name("POLICY")
+                        .value_parser(parse_weights)
+                        .index(2),
+                ),
+        )
+        .subcommand(
+            Command::new("sync")
+                .about(SYNC_ABOUT)
+                .arg(report_arg())
+                .arg(tag_arg().help("Write only the links of the groups \
+                                     tagged TAG")),
+        )
+        .subcommand(
+            Command::new("scan")
+                .about(SCAN_ABOUT)
+                .arg(
+                    Arg::new("BREW_PREFIX")
+                        .help("The Homebrew prefix to scan [default: \
+                               $HOMEBREW_PREFIX, /opt/homebrew, /usr/local or \
+                               /home/linuxbrew/.linuxbrew]")
+                        .value_name("DIR")
+                        .long("brew-prefix")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("APPLY")
+                        .help("Register the proposed alternatives instead of \
+                               printing them")
+                        .long("apply")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about(IMPORT_ABOUT)
+                .arg(
+                    Arg::new("FROM")
+                        .help("The version manager to import from")
+                        .value_name("MANAGER")
+                        .long("from")
+                        .num_args(1)
+                        .required(true)
+                        .value_parser(["pyenv", "asdf", "mise"]),
+                )
+                .arg(
+                    Arg::new("DIR")
+                        .help("The data directory of the version manager \
+                               [default: $PYENV_ROOT, $ASDF_DATA_DIR or \
+                               $MISE_DATA_DIR, falling back to the invoking \
+                               user's ~/.pyenv, ~/.asdf or \
+                               ~/.local/share/mise]")
+                        .value_name("DIR")
+                        .long("dir")
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("apply")
+                .about(APPLY_ABOUT)
+                .arg(
+                    Arg::new("MANIFEST")
+                        .help("The manifest of the groups to apply")
+                        .value_name("MANIFEST")
+                        .value_parser(path_parser())
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("VALIDATE_ONLY")
+                        .help("Report the problems of <MANIFEST> without \
+                               reading or changing the database")
+                        .long("validate-only")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(report_arg().conflicts_with("VALIDATE_ONLY")),
+        )
+        .subcommand(Command::new("schema").about(SCHEMA_ABOUT))
+        .subcommand(
+            Command::new("export")
+                .about(EXPORT_ABOUT)
+                .arg(tag_arg().help("Export only the groups tagged TAG")),
+        )
+        .subcommand(Command::new("shell").about(SHELL_ABOUT))
+        .subcommand(
+            Command::new("doctor")
+                .about(DOCTOR_ABOUT)
+                .arg(
+                    Arg::new("CHECK")
+                        .help("Run only this check")
+                        .value_name("CHECK")
+                        .index(1)
+                        .value_parser(["permissions", "manifest", "path",
+                                       "cycles"]),
+                ),
+        )
+        .subcommand(
+            Command::new("status")
+                .about(STATUS_ABOUT)
+                .arg(
+                    Arg::new("GLOB")
+                        .help("Only show groups whose name matches this glob")
+                        .index(1)
+                        .conflicts_with("REGEX"),
+                )
+                .arg(regex_arg())
+                .arg(wide_arg())
+                .arg(columns_arg(GROUP_COLUMNS_HELP))
+                .arg(print0_arg()),
+        )
+        .subcommand(
+            Command::new("search")
+                .about(SEARCH_ABOUT)
+                .arg(
+                    Arg::new("PATTERN")
+                        .help("Text to look for in group names and targets")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(wide_arg())
+                .arg(columns_arg(ALTERNATIVE_COLUMNS_HELP))
+                .arg(print0_arg()),
+        )
+        .subcommand(
+            Command::new("prune")
+                .about(PRUNE_ABOUT)
+                .arg(
+                    Arg::new("DRY_RUN")
+                        .help("Only show what would be removed")
                         .short('n')
-                        .long("name")
+                        .long("dry-run")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(Command::new("info").about(INFO_ABOUT))
+        .subcommand(
+            Command::new("explain-error")
+                .about(EXPLAIN_ERROR_ABOUT)
+                .arg(
+                    Arg::new("ID")
+                        .help("The id of the error, such as UA0007")
+                        .value_name("ID")
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about(VERIFY_ABOUT)
+                .arg(
+                    Arg::new("UPDATE")
+                        .help("Record the current database state in the \
+                               manifest instead of checking it")
+                        .long("update")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(Command::new("watch").about(WATCH_ABOUT))
+        .subcommand(Command::new("helper").about(HELPER_ABOUT))
+        .subcommand(
+            Command::new("serve")
+                .about(SERVE_ABOUT)
+                .arg(
+                    Arg::new("LISTEN")
+                        .help("The address to listen on")
+                        .value_name("ADDR")
+                        .long("listen")
                         .num_args(1)
-                        .required_unless_present("NAME_POS")
-                        .conflicts_with("NAME_POS"),
+                        .default_value("127.0.0.1:8787"),
                 )
                 .arg(
-                    Arg::new("NAME")
-                        .help("The name of the alternative to remove")
-                        .value_name("NAME")
+                    Arg::new("TOKEN_FILE")
+                        .help("Read the API token from FILE instead of \
+                               /var/lib/update-alternatives/serve-token, \
+                               creating it if it does not exist")
+                        .value_name("FILE")
+                        .long("token-file")
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("generate-hook")
+                .about(GENERATE_HOOK_ABOUT)
+                .arg(
+                    Arg::new("KIND")
+                        .help("The package manager to generate a hook for, \
+                               or shell for interactive shells")
+                        .value_name("KIND")
+                        .value_parser(["apt", "dnf", "rpm", "shell"])
                         .index(1)
-                        .required_unless_present("NAME")
-                        .conflicts_with("NAME"),
+                        .required(true),
                 )
                 .arg(
-                    Arg::new("TARGET")
-                        .help("The target of the alternative to remove")
-                        .value_name("TARGET")
-                        .index(2)
-                        .required_unless_present("TARGET")
-                        .conflicts_with("TARGET"),
+                    Arg::new("OUTPUT")
+                        .help("Write the hook to FILE instead of the package \
+                               manager's default location")
+                        .value_name("FILE")
+                        .short('o')
+                        .long("output")
+                        .num_args(1)
+                        .conflicts_with("STDOUT"),
+                )
+                .arg(
+                    Arg::new("STDOUT")
+                        .help("Print the hook instead of writing it")
+                        .long("stdout")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("generate-systemd")
+                .about(GENERATE_SYSTEMD_ABOUT)
+                .arg(
+                    Arg::new("TIMER")
+                        .help("Also generate a timer that runs sync on the \
+                               given OnCalendar= schedule, e.g. 'daily'")
+                        .value_name("CALENDAR")
+                        .long("timer")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("PATH")
+                        .help("Also generate a path unit that runs sync \
+                               whenever /etc/alternatives changes")
+                        .long("path")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("EXPIRE")
+                        .help("Also generate a service and a timer that run \
+                               expire every five minutes, ending selections \
+                               made with set --until")
+                        .long("expire")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("HELPER")
+                        .help("Also generate a socket and a service that \
+                               start the helper on the first connection")
+                        .long("helper")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("WATCH")
+                        .help("Also generate a service that runs watch, \
+                               restarted by systemd if it stops responding")
+                        .long("watch")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("SERVE")
+                        .help("Also generate a service that runs serve, \
+                               restarted by systemd if it stops responding")
+                        .long("serve")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("OUTPUT")
+                        .help("Write the units to DIR instead of \
+                               /etc/systemd/system")
+                        .value_name("DIR")
+                        .short('o')
+                        .long("output")
+                        .num_args(1)
+                        .conflicts_with("STDOUT"),
+                )
+                .arg(
+                    Arg::new("STDOUT")
+                        .help("Print the units instead of writing them")
+                        .long("stdout")
+                        .action(clap::ArgAction::SetTrue),
                 ),
         )
-        .subcommand(Command::new("sync").about(SYNC_ABOUT))
         .subcommand_required(false)
         .arg_required_else_help(true)
-        .propagate_version(true)
+        .propagate_version(true))
 }
 
-static ABOUT: &'static str =
+static ABOUT: &str =
     "Manages symlinks to be placed in /usr/local/bin. Data is stored in \
     /etc/alternatives for persistence between invocations. Provides similar \
     functionality to Debian's update-alternatives, but with a slightly \
@@ -504,21 +5735,303 @@ static ABOUT: &'static str =
     Example usage to use 'vim' to open 'nvim'': \
     \nsudo update-alternatives add -n vim -t /usr/bin/nvim -w 100 ";
 
-static LIST_ABOUT: &'static str =
-    "Lists all alternatives for <NAME> and their assigned priority.";
+static LIST_ABOUT: &str =
+    "Lists all alternatives for <NAME> and their assigned priority, or those \
+    of every group with --all or without <NAME>.";
+
+static DISPLAY_ABOUT: &str =
+    "Shows everything about the group <NAME> as dpkg's --display does: its \
+    mode, the best and the current target, its link and follower links, and \
+    every alternative with its priority and followers. With --resolve, also \
+    every symlink between its link and the file it finally points to.";
+
+static QUERY_ABOUT: &str =
+    "Prints the group <NAME> in the format of dpkg's --query: a stanza with \
+    its Name, Link, Status, Best and Value, then one with the Alternative and \
+    Priority of each target.";
 
-static ADD_ABOUT: &'static str =
+static SHOW_ABOUT: &str =
+    "Shows everything known about the alternative <TARGET> of <NAME>: its \
+    priority, state, whether it is selected, its package, the checksum and \
+    details of its file, and when it was last added.";
+
+static EXPLAIN_ABOUT: &str =
+    "Explains why the link of <NAME> points where it does: whether it was \
+    selected by hand, by whom and until when, how the priorities of its \
+    targets compare, which are missing, and whether the link was changed by \
+    hand.";
+
+static BLAME_ABOUT: &str =
+    "Shows for every alternative of <NAME> the audit log entry that last \
+    added it or changed its priority: when, by whom, and the operation.";
+
+static TEST_ABOUT: &str =
+    "Runs the target selected for <NAME> in the database, not the one found \
+    through PATH, with <ARGS>, reports how it exited, and exits with its \
+    status.";
+
+static DIFF_ABOUT: &str =
+    "Shows the groups and alternatives that were added or removed, the \
+    priorities that changed, and the selections that changed between two \
+    states of the database, or between one and the current database with \
+    --against. A state is the number of a snapper snapshot, a root file \
+    system such as an image or a restored backup, or a database folder.";
+
+static ADD_ABOUT: &str =
     "Adds or modifies an alternative for <NAME> that points to <TARGET> with \
     priority <WEIGHT>. If the database is modified, requires read/write access \
     to /etc/alternatives and /usr/local/bin.";
 
-static REMOVE_ABOUT: &'static str =
+static REMOVE_ABOUT: &str =
     "If one exists, removes the alternative for <NAME> that points to \
     <TARGET>. If the database is modified, requires read/write access to \
     /etc/alternatives and /usr/local/bin.";
 
-static SYNC_ABOUT: &'static str =
+static RESTORE_ABOUT: &str =
+    "Returns the alternative for <NAME> that points to <TARGET>, removed with \
+    remove --archive, with its priority and package. Requires read/write \
+    access to /etc/alternatives and /usr/local/bin.";
+
+static SET_ABOUT: &str =
+    "Selects <TARGET> for <NAME> by hand, regardless of priorities, and points \
+    the link at it, for good or until the time given with --until. Requires \
+    read/write access to /etc/alternatives and /usr/local/bin.";
+
+static AUTO_ABOUT: &str =
+    "Ends the manual selection of <NAME>, if there is one, and points the link \
+    at the alternative with the highest priority again. Requires read/write \
+    access to /etc/alternatives and /usr/local/bin.";
+
+static CONFIG_ABOUT: &str =
+    "Shows the alternatives of <NAME> as a numbered table and reads a \
+    selection number from standard input, like Debian's --config: 0 returns \
+    the group to automatic selection, any other number selects that target by \
+    hand, and an empty line keeps the current choice.";
+
+static EXPIRE_ABOUT: &str =
+    "Returns every group whose manual selection has expired to automatic \
+    selection and rewrites its link. Meant to run from a timer, such as the \
+    one written by 'generate-systemd --expire'.";
+
+static FREEZE_ABOUT: &str =
+    "Freezes the database: every command, hook, and service that would change \
+    it or its links refuses to until 'thaw'. For audits, incident response, \
+    or capturing a golden image.";
+
+static THAW_ABOUT: &str =
+    "Ends a freeze, allowing changes again.";
+
+static TRASH_ABOUT: &str =
+    "Lists and restores the files that were in the way of a link. A commit \
+    that writes a link where a file or directory that is not a symlink \
+    exists moves it into /var/lib/update-alternatives/trash first, with where \
+    it was, when, by whom, and what replaced it.";
+
+static TRASH_LIST_ABOUT: &str =
+    "Lists the files in the trash, oldest first, with their ids.";
+
+static TRASH_RESTORE_ABOUT: &str =
+    "Moves the file with the id <ID> back where it was, or to --to PATH. \
+    Nothing is overwritten: if a link of a group is there now, remove the \
+    group or restore to another path.";
+
+static RETARGET_ABOUT: &str =
+    "Replaces the target <OLD_PATH> with <NEW_PATH> in every group, keeping \
+    its priority, and rewrites the links that pointed at it. Requires \
+    read/write access to /etc/alternatives and /usr/local/bin.";
+
+static COPY_ABOUT: &str =
+    "Adds the group <NEWNAME> with the alternatives, priorities, and \
+    selection of <NAME>, managing a new link. Requires read/write access to \
+    /etc/alternatives and /usr/local/bin.";
+
+static ADOPT_ABOUT: &str =
+    "Turns existing symlinks that no group manages into groups of their own, \
+    each with the current target as its only alternative, and manages them \
+    from then on. Requires read/write access to /etc/alternatives and the \
+    directories of the links.";
+
+static RENUMBER_ABOUT: &str =
+    "Reassigns the priorities of <NAME> in steps of --step, keeping their \
+    order and the alternative that wins. Requires read/write access to \
+    /etc/alternatives and /usr/local/bin.";
+
+static WEIGHTS_ABOUT: &str =
+    "Sets how add derives the priority of a new alternative of <NAME> when \
+    none is given, or prints it if no policy is given. Requires read/write \
+    access to /etc/alternatives to change it.";
+
+static TAG_ABOUT: &str =
+    "Tags <NAME> with categories such as toolchain or site-local, which \
+    'list --tag' and 'sync --tag' select groups by, or prints its tags if none \
+    are given. Requires read/write access to /etc/alternatives to change \
+    them.";
+
+static ENV_ABOUT: &str =
+    "Sets the environment variables exported for the selection of <NAME> in \
+    /etc/profile.d/update-alternatives-<NAME>.sh, or prints them if none are \
+    given. Requires read/write access to /etc/alternatives and \
+    /etc/profile.d to change them.";
+
+static SYNC_ABOUT: &str =
     "Rewrites all symlinks in /usr/local/bin based on the current state of \
     /etc/alternatives without modifying the database. Useful for package \
     manager hooks (e.g., pacman libalpm hooks) after installs, upgrades, or \
     removals.";
+
+static SCAN_ABOUT: &str =
+    "Looks for versioned Homebrew formulae such as python@3.12 or llvm@18 and \
+    proposes a group for every executable that several of them provide, linked \
+    in the bin directory of the Homebrew prefix. Prints the corresponding add \
+    commands, or registers them with --apply.";
+
+static IMPORT_ABOUT: &str =
+    "Registers every executable of every version installed by pyenv, asdf or \
+    mise as an alternative in /usr/local/bin, with a priority derived from the \
+    version.";
+
+static APPLY_ABOUT: &str =
+    "Makes the groups listed in the TOML manifest <MANIFEST> match it: adds \
+    the groups and alternatives it lists, updates their priorities, packages \
+    and followers, removes the alternatives of those groups it does not list, \
+    and selects the alternative it names or returns the group to automatic \
+    selection. Other groups are left alone. The whole manifest is checked \
+    first, and every problem is reported with its line and column.";
+
+static SCHEMA_ABOUT: &str =
+    "Prints a JSON Schema of the manifests that apply reads, for editors and \
+    for linting manifests in CI.";
+
+static EXPORT_ABOUT: &str =
+    "Prints the database as a manifest that apply reads, to keep in \
+    configuration management or compare against later with diff --against.";
+
+static SHELL_ABOUT: &str =
+    "Starts an interactive shell with tab completion that loads the database \
+    once and offers the list, add, remove, set, diff and commit commands. \
+    Pending changes are written on commit and on exit.";
+
+static DOCTOR_ABOUT: &str =
+    "Checks the alternatives database and managed links for problems, such as \
+    world-writable database entries, link directories, or targets, and \
+    entries that do not match the integrity manifest, and links shadowed by \
+    earlier PATH entries. Runs only <CHECK> if given. Exits non-zero if any \
+    problem is found.";
+
+static STATUS_ABOUT: &str =
+    "Shows the link and current target of every group, and warns about links \
+    that are shadowed by same-named executables earlier in PATH.";
+
+static PRUNE_ABOUT: &str =
+    "Removes alternatives whose target was owned by a package of the system \
+    package manager (pacman, dpkg, or rpm) that is no longer installed. The \
+    owning package is recorded whenever the database is modified.";
+
+static ALTERNATIVE_COLUMNS_HELP: &str =
+    "Columns to print, from name, priority, target, state, link, current, \
+    mode, and count [default: name,priority,target,state]";
+
+static GROUP_COLUMNS_HELP: &str =
+    "Columns to print, from name, link, current, mode, count, and best \
+    [default: name,link,current,mode]";
+
+static SEARCH_ABOUT: &str =
+    "Lists the alternatives whose group name or target contains <PATTERN>.";
+
+static INFO_ABOUT: &str =
+    "Prints the version and commit, enabled features, the database, link \
+    directory, configuration file and audit log in use, and how privileges \
+    would be gained, as a baseline for bug reports.";
+
+static EXPLAIN_ERROR_ABOUT: &str =
+    "Prints the description and common fixes of the error with the id <ID>, \
+    such as UA0007, which every error message carries in brackets. Lists \
+    every id with a short summary if <ID> is not given. The ids stay the same \
+    across releases and translations, so scripts and support documents can \
+    match on them instead of on the message.";
+
+static VERIFY_ABOUT: &str =
+    "Compares the entries in /etc/alternatives against the checksums \
+    recorded in /var/lib/update-alternatives/manifest and reports entries \
+    that were created, modified, or removed outside update-alternatives. Once \
+    a manifest exists it is kept up to date by every commit, and commits are \
+    refused while entries do not match it.";
+
+static GENERATE_HOOK_ABOUT: &str =
+    "Writes a package manager hook that runs 'update-alternatives sync' after \
+    every transaction. 'apt' writes a DPkg::Post-Invoke snippet to \
+    /etc/apt/apt.conf.d/99update-alternatives, 'dnf' writes an action for the \
+    post-transaction-actions plugin to \
+    /etc/dnf/plugins/post-transaction-actions.d/update-alternatives.action, \
+    and 'rpm' prints RPM file-trigger scriptlets to add to a package's spec \
+    file. 'shell' prints a snippet for ~/.bashrc or ~/.zshrc that clears the \
+    shell's command hash table whenever links change.";
+
+static GENERATE_SYSTEMD_ABOUT: &str =
+    "Writes a oneshot update-alternatives-sync.service to /etc/systemd/system \
+    that runs 'update-alternatives sync' at boot, optionally with a timer for \
+    periodic reconciliation, a path unit that reacts to changes in \
+    /etc/alternatives, a socket that starts the helper, and services that run \
+    watch and serve under systemd's watchdog.";
+
+static WATCH_ABOUT: &str =
+    "Runs until interrupted, watching /etc/alternatives, the link directories, \
+    and the directories of all registered targets. Whenever a target appears \
+    or disappears, a managed link is tampered with, or the database changes, \
+    the affected links are rewritten and the action is recorded in the audit \
+    log.";
+
+static HELPER_ABOUT: &str =
+    "Runs until interrupted, answering JSON-RPC requests on \
+    /run/update-alternatives/helper.sock one connection at a time, so that \
+    the GUI, an unprivileged shell, and watch change the database through a \
+    single privileged writer. Changes by users other than root are authorized \
+    through polkit.";
+
+static SERVE_ABOUT: &str =
+    "Serves a small REST API and web interface for listing, adding, and \
+    removing alternatives and rewriting symlinks. Every /api request must \
+    carry the token from /var/lib/update-alternatives/serve-token as an \
+    'Authorization: Bearer' header.";
+
+#[cfg(feature = "dbus")]
+static DBUS_SERVICE_ABOUT: &str =
+    "Serves org.fthomys.UpdateAlternatives on the D-Bus system bus. Listing \
+    is unrestricted; Add, Remove, SetCurrent, and Sync are authorized through \
+    polkit and emit a GroupChanged signal for every group they change. \
+    Normally started through D-Bus activation.";
+
+#[cfg(test)]
+mod tests {
+    use super::{form_fields, picked_row};
+
+    #[test]
+    fn form_fields_keep_pipes_and_spaces() {
+        assert_eq!(form_fields(b"tool\n /opt/My Tools/a|b \n10\n"),
+                   ["tool", " /opt/My Tools/a|b ", "10"]);
+        assert_eq!(form_fields(b"it's\n/opt/\"q\"/tool\n5\n"),
+                   ["it's", "/opt/\"q\"/tool", "5"]);
+    }
+
+    #[test]
+    fn form_fields_keep_empty_entries() {
+        assert_eq!(form_fields(b"tool\n\n10\n"), ["tool", "", "10"]);
+        assert_eq!(form_fields(b"\n\n\n"), ["", "", ""]);
+    }
+
+    #[test]
+    fn form_fields_do_not_shift_on_bad_bytes() {
+        let fields = form_fields(b"tool\n/opt/caf\xe9\n10\n");
+
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0], "tool");
+        assert_eq!(fields[2], "10");
+    }
+
+    #[test]
+    fn picked_row_reads_the_hidden_index() {
+        assert_eq!(picked_row(b"2\n"), Some(2));
+        assert_eq!(picked_row(b""), None);
+        assert_eq!(picked_row(b"/opt/caf\xe9|x\n"), None);
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate sha2;
+
+use self::sha2::Digest;
+
+use super::filesystem;
+
+pub static MANIFEST_PATH: &str = "/var/lib/update-alternatives/manifest";
+
+type Checksums = std::collections::BTreeMap<String, String>;
+
+pub fn exists<P: std::convert::AsRef<std::path::Path>>(manifest: P) -> bool {
+    manifest.as_ref().is_file()
+}
+
+pub fn write<P: std::convert::AsRef<std::path::Path>,
+             Q: std::convert::AsRef<std::path::Path>>(
+    folder: P, manifest: Q
+) -> std::io::Result<usize> {
+    let manifest_path = manifest.as_ref();
+    let checksums = checksum_folder(folder.as_ref())?;
+
+    let mut contents = String::new();
+
+    for (entry, checksum) in checksums.iter() {
+        contents.push_str(&format!("{}  {}\n", checksum, entry));
+    }
+
+    if let Some(parent) = manifest_path.parent() {
+        if !parent.exists() {
+            filesystem::create_dir(parent)?;
+        }
+    }
+
+    filesystem::write_atomic(contents, manifest_path)?;
+    filesystem::set_mode(manifest_path, 0o644)?;
+
+    Ok(checksums.len())
+}
+
+pub fn verify<P: std::convert::AsRef<std::path::Path>,
+              Q: std::convert::AsRef<std::path::Path>>(
+    folder: P, manifest: Q
+) -> std::io::Result<usize> {
+    let folder_path = folder.as_ref();
+    let expected = parse(&filesystem::read(manifest)?);
+    let actual = checksum_folder(folder_path)?;
+    let mut problems: usize = 0;
+
+    for (entry, checksum) in expected.iter() {
+        let path = folder_path.join(entry);
+
+        match actual.get(entry) {
+            Some(c) if c == checksum => (),
+            Some(_) => {
+                println!("update-alternatives: verify: {} was modified \
+                         outside update-alternatives", path.display());
+                problems += 1;
+            },
+            None => {
+                println!("update-alternatives: verify: {} was removed \
+                         outside update-alternatives", path.display());
+                problems += 1;
+            },
+        }
+    }
+
+    for entry in actual.keys() {
+        if !expected.contains_key(entry) {
+            println!("update-alternatives: verify: {} was created outside \
+                     update-alternatives", folder_path.join(entry).display());
+            problems += 1;
+        }
+    }
+
+    Ok(problems)
+}
+
+fn parse(contents: &str) -> Checksums {
+    contents.lines()
+            .filter_map(|l| l.split_once("  "))
+            .map(|(c, e)| (e.to_string(), c.to_string()))
+            .collect()
+}
+
+fn checksum_folder(folder: &std::path::Path) -> std::io::Result<Checksums> {
+    let mut checksums = Checksums::new();
+
+    for child in folder.read_dir()? {
+        let child = child?;
+        let path = child.path();
+
+        // Symlinks belong to other tools, such as dpkg.
+        if path.extension().is_none_or(|e| e != "json")
+           || !child.file_type()?.is_file() {
+            continue;
+        }
+
+        let entry = match path.file_name() {
+            Some(n) => n.to_string_lossy().into_owned(),
+            None => continue,
+        };
+
+        let contents = std::fs::read(&path)?;
+        checksums.insert(entry, hex(&sha2::Sha256::digest(&contents)));
+    }
+
+    Ok(checksums)
+}
+
+// The SHA-256 checksum of the file at path, as the manifest records it.
+pub fn checksum<P: std::convert::AsRef<std::path::Path>>(path: P)
+-> std::io::Result<String> {
+    Ok(hex(&sha2::Sha256::digest(std::fs::read(path)?)))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,110 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate nix;
+
+use std::convert::TryFrom;
+use std::os::fd::AsFd;
+use std::os::unix::ffi::OsStrExt;
+
+use self::nix::poll::{PollFd, PollFlags, PollTimeout};
+
+// Tells the service manager that started this process how it is doing,
+// through the datagram socket in NOTIFY_SOCKET, as sd_notify does. Nothing is
+// sent if it was not started by one.
+fn notify(state: &str) {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    let path = match std::env::var_os("NOTIFY_SOCKET") {
+        Some(p) => p,
+        None => return,
+    };
+
+    // A leading @ names a socket in the abstract namespace.
+    let address = match path.as_bytes().strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name),
+        None => SocketAddr::from_pathname(&path),
+    };
+
+    let sent = address.and_then(|a| {
+        UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &a)
+    });
+
+    if let Err(e) = sent {
+        error!(SERVICE, "could not notify the service manager through {}: {}",
+               path.to_string_lossy(), e);
+    }
+}
+
+// Tells the service manager that the daemon is up, and what it is doing.
+pub fn ready(status: &str) {
+    notify(&format!("READY=1\nSTATUS={}", status));
+}
+
+// Half the interval within which the service manager expects to hear from
+// this process, if it watches it with WatchdogSec=, so that a ping sent late
+// still arrives in time.
+pub fn watchdog_interval() -> Option<std::time::Duration> {
+    // Inherited by the processes it starts, which are not watched.
+    let pid = std::env::var("WATCHDOG_PID").ok()
+                                           .and_then(|p| p.parse::<u32>().ok());
+
+    if pid.is_some_and(|p| p != std::process::id()) {
+        return None;
+    }
+
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+
+    (usec > 0).then(|| std::time::Duration::from_micros(usec / 2))
+}
+
+// Tells the watchdog that the main loop still turns. A daemon whose loop
+// wedges stops calling this, and is restarted.
+pub fn alive() {
+    if watchdog_interval().is_some() {
+        notify("WATCHDOG=1");
+    }
+}
+
+// Waits until fd can be read, but no longer than the watchdog allows, and
+// returns whether it can be read. Without a watchdog, waits as long as it
+// takes.
+pub fn wait<F: AsFd>(fd: &F) -> std::io::Result<bool> {
+    let timeout = match watchdog_interval() {
+        Some(i) => PollTimeout::try_from(i).unwrap_or(PollTimeout::MAX),
+        None => PollTimeout::NONE,
+    };
+    let mut fds = [PollFd::new(fd.as_fd(), PollFlags::POLLIN)];
+
+    match nix::poll::poll(&mut fds, timeout) {
+        Ok(n) => Ok(n > 0),
+        Err(nix::errno::Errno::EINTR) => Ok(false),
+        Err(e) => Err(std::io::Error::from(e)),
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+use super::filesystem;
+
+pub struct Package {
+    pub name: String,
+    pub version: String,
+}
+
+impl std::fmt::Display for Package {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "{} {}", self.name, self.version)
+    }
+}
+
+// Maps files to the packages of the system package manager that own them.
+pub trait Provider {
+    fn owner(&self, path: &std::path::Path) -> Option<Package>;
+
+    fn is_installed(&self, package: &str) -> bool;
+}
+
+type Index = std::collections::HashMap<std::path::PathBuf, String>;
+type Versions = std::collections::HashMap<String, String>;
+
+pub static ALPM_DB: &str = "/var/lib/pacman/local";
+pub static DPKG_DB: &str = "/var/lib/dpkg";
+pub static RPM_DB: &str = "/var/lib/rpm";
+
+// Picks the package manager whose database exists below root.
+pub fn detect<P: std::convert::AsRef<std::path::Path>>(root: P)
+-> Option<Box<dyn Provider>> {
+    let root = root.as_ref();
+
+    if filesystem::under(root, ALPM_DB).is_dir() {
+        match Alpm::load(root) {
+            Ok(p) => return Some(Box::new(p)),
+            Err(e) => error!(SOURCE, "could not read the \
+                                     pacman database: {}", e),
+        }
+    }
+
+    if filesystem::under(root, DPKG_DB).join("status").is_file() {
+        match Dpkg::load(root) {
+            Ok(p) => return Some(Box::new(p)),
+            Err(e) => error!(SOURCE, "could not read the dpkg \
+                                     database: {}", e),
+        }
+    }
+
+    if filesystem::under(root, RPM_DB).is_dir() {
+        return Some(Box::new(Rpm{ root: root.to_path_buf() }));
+    }
+
+    None
+}
+
+// Falls back to the resolved path, since targets are often symlinks into
+// package directories, such as /usr/lib/jvm.
+fn lookup(index: &Index, versions: &Versions, root: &std::path::Path,
+          path: &std::path::Path) -> Option<Package> {
+    let name = index.get(path).or_else(|| {
+        let resolved = filesystem::under(root, path).canonicalize().ok()?;
+
+        index.get(&std::path::Path::new("/")
+                      .join(resolved.strip_prefix(root).ok()?))
+    })?;
+
+    Some(Package{ name: name.clone(),
+                  version: versions.get(name).cloned().unwrap_or_default() })
+}
+
+pub struct Alpm {
+    root: std::path::PathBuf,
+    index: Index,
+    versions: Versions,
+}
+
+impl Alpm {
+    // Every directory of the local database holds a desc and a files entry.
+    fn load(root: &std::path::Path) -> std::io::Result<Alpm> {
+        let mut index = Index::new();
+        let mut versions = Versions::new();
+
+        for entry in filesystem::under(root, ALPM_DB).read_dir()? {
+            let dir = entry?.path();
+            let desc = match filesystem::read(dir.join("desc")) {
+                Ok(d) => d,
+                Err(_) => continue,
+            };
+
+            let name = match alpm_field(&desc, "%NAME%") {
+                Some(n) => n.to_string(),
+                None => continue,
+            };
+
+            let version = alpm_field(&desc, "%VERSION%").unwrap_or_default();
+            versions.insert(name.clone(), version.to_string());
+
+            let files = filesystem::read(dir.join("files")).unwrap_or_default();
+
+            for file in files.lines()
+                             .skip_while(|l| *l != "%FILES%")
+                             .skip(1)
+                             .take_while(|l| !l.is_empty()) {
+                index.insert(std::path::Path::new("/").join(file),
+                             name.clone());
+            }
+        }
+
+        Ok(Alpm{ root: root.to_path_buf(), index, versions })
+    }
+}
+
+fn alpm_field<'a>(desc: &'a str, field: &str) -> Option<&'a str> {
+    let mut lines = desc.lines();
+
+    lines.find(|l| *l == field)?;
+
+    lines.next()
+}
+
+impl Provider for Alpm {
+    fn owner(&self, path: &std::path::Path) -> Option<Package> {
+        lookup(&self.index, &self.versions, &self.root, path)
+    }
+
+    fn is_installed(&self, package: &str) -> bool {
+        self.versions.contains_key(package)
+    }
+}
+
+pub struct Dpkg {
+    root: std::path::PathBuf,
+    index: Index,
+    versions: Versions,
+}
+
+impl Dpkg {
+    // The status file lists the installed versions, info/PKG[:ARCH].list the
+    // files of every package.
+    fn load(root: &std::path::Path) -> std::io::Result<Dpkg> {
+        let db = filesystem::under(root, DPKG_DB);
+        let status = filesystem::read(db.join("status"))?;
+        let mut versions = Versions::new();
+
+        for paragraph in status.split("\n\n") {
+            let field = |name: &str| paragraph.lines().find_map(|l| {
+                l.strip_prefix(name)?.strip_prefix(": ")
+            });
+
+            let installed = field("Status").is_some_and(|s| {
+                s.ends_with(" installed")
+            });
+
+            if let (Some(name), Some(version), true) =
+                (field("Package"), field("Version"), installed) {
+                versions.insert(name.to_string(), version.to_string());
+            }
+        }
+
+        let mut index = Index::new();
+
+        for entry in db.join("info").read_dir()? {
+            let path = entry?.path();
+
+            if path.extension().is_none_or(|e| e != "list") {
+                continue;
+            }
+
+            let name = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(s) => s.split(':').next().unwrap_or(s).to_string(),
+                None => continue,
+            };
+
+            for file in filesystem::read(&path).unwrap_or_default().lines() {
+                index.insert(std::path::PathBuf::from(file), name.clone());
+            }
+        }
+
+        Ok(Dpkg{ root: root.to_path_buf(), index, versions })
+    }
+}
+
+impl Provider for Dpkg {
+    fn owner(&self, path: &std::path::Path) -> Option<Package> {
+        lookup(&self.index, &self.versions, &self.root, path)
+    }
+
+    fn is_installed(&self, package: &str) -> bool {
+        self.versions.contains_key(package)
+    }
+}
+
+// The rpm database is a SQLite or Berkeley DB file, so it is queried through
+// rpm itself.
+pub struct Rpm {
+    root: std::path::PathBuf,
+}
+
+impl Rpm {
+    fn query(&self, args: &[&std::ffi::OsStr]) -> Option<String> {
+        let output = std::process::Command::new("rpm")
+            .arg("--root").arg(&self.root)
+            .arg("--query")
+            .args(args)
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl Provider for Rpm {
+    fn owner(&self, path: &std::path::Path) -> Option<Package> {
+        let output = self.query(&["--queryformat".as_ref(),
+                                  "%{NAME}\t%{VERSION}-%{RELEASE}\n".as_ref(),
+                                  "--file".as_ref(), path.as_os_str()])?;
+        let (name, version) = output.lines().next()?.split_once('\t')?;
+
+        Some(Package{ name: name.to_string(), version: version.to_string() })
+    }
+
+    fn is_installed(&self, package: &str) -> bool {
+        self.query(&[package.as_ref()]).is_some()
+    }
+}
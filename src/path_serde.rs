@@ -0,0 +1,119 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate serde;
+
+// Paths are written as JSON strings when they are valid UTF-8, which they
+// nearly always are, and as arrays of their raw bytes otherwise, so that no
+// path fails to serialize or comes back changed.
+
+#[cfg(any(unix, target_os = "wasi"))]
+pub fn serialize<P, S>(path: &P, serializer: S) -> Result<S::Ok, S::Error>
+where P: std::convert::AsRef<std::path::Path>, S: serde::Serializer {
+    #[cfg(unix)]
+    use std::os::unix::ffi::OsStrExt;
+    #[cfg(target_os = "wasi")]
+    use std::os::wasi::ffi::OsStrExt;
+
+    let path = path.as_ref();
+
+    match path.to_str() {
+        Some(s) => serializer.serialize_str(s),
+        None => serializer.serialize_bytes(path.as_os_str().as_bytes()),
+    }
+}
+
+#[cfg(windows)]
+pub fn serialize<P, S>(path: &P, serializer: S) -> Result<S::Ok, S::Error>
+where P: std::convert::AsRef<std::path::Path>, S: serde::Serializer {
+    serializer.serialize_str(&path.as_ref().to_string_lossy())
+}
+
+pub fn serialize_option<P, S>(path: &Option<P>, serializer: S)
+-> Result<S::Ok, S::Error>
+where P: std::convert::AsRef<std::path::Path>, S: serde::Serializer {
+    match *path {
+        Some(ref p) => serialize(p, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn deserialize<'de, D>(deserializer: D)
+-> Result<std::path::PathBuf, D::Error> where D: serde::Deserializer<'de> {
+    deserializer.deserialize_any(PathVisitor)
+}
+
+struct PathVisitor;
+
+impl<'de> serde::de::Visitor<'de> for PathVisitor {
+    type Value = std::path::PathBuf;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter)
+    -> std::fmt::Result {
+        formatter.write_str("a path as a string or an array of bytes")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str)
+    -> Result<Self::Value, E> {
+        Ok(std::path::PathBuf::from(value))
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, value: &[u8])
+    -> Result<Self::Value, E> {
+        from_bytes(value.to_vec())
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A)
+    -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+        while let Some(b) = seq.next_element::<u8>()? {
+            bytes.push(b);
+        }
+
+        from_bytes(bytes)
+    }
+}
+
+#[cfg(any(unix, target_os = "wasi"))]
+fn from_bytes<E: serde::de::Error>(bytes: Vec<u8>)
+-> Result<std::path::PathBuf, E> {
+    #[cfg(unix)]
+    use std::os::unix::ffi::OsStringExt;
+    #[cfg(target_os = "wasi")]
+    use std::os::wasi::ffi::OsStringExt;
+
+    Ok(std::path::PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+}
+
+#[cfg(windows)]
+fn from_bytes<E: serde::de::Error>(bytes: Vec<u8>)
+-> Result<std::path::PathBuf, E> {
+    String::from_utf8(bytes).map(std::path::PathBuf::from)
+                            .map_err(E::custom)
+}
@@ -0,0 +1,144 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+extern crate nix;
+extern crate regex;
+
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+
+// Environment of the probed program; nothing else is passed on.
+const PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+// How much of what the program prints is looked at.
+const OUTPUT_LIMIT: u64 = 64 * 1024;
+
+const POLL_INTERVAL: std::time::Duration =
+    std::time::Duration::from_millis(10);
+
+// Runs target with args and returns the version it reports. The program runs
+// as nobody if we are root, with an empty environment, no input, and in its
+// own process group, which is killed after timeout.
+pub fn version(target: &std::path::Path, args: &[String],
+               timeout: std::time::Duration) -> std::io::Result<String> {
+    let mut command = std::process::Command::new(target);
+    command.args(args)
+           .env_clear()
+           .env("PATH", PATH)
+           .env("LC_ALL", "C")
+           .current_dir("/")
+           .stdin(std::process::Stdio::null())
+           .stdout(std::process::Stdio::piped())
+           .stderr(std::process::Stdio::piped())
+           .process_group(0);
+
+    if nix::unistd::geteuid().is_root() {
+        let nobody = nix::unistd::User::from_name("nobody")?.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound,
+                                "no user nobody to run as")
+        })?;
+
+        command.uid(nobody.uid.as_raw()).gid(nobody.gid.as_raw());
+    }
+
+    let mut child = command.spawn()?;
+    let deadline = std::time::Instant::now() + timeout;
+
+    // A program that writes more than a pipe holds would block until it is
+    // read, so both are read while we wait.
+    let stdout = drain(child.stdout.take().unwrap());
+    let stderr = drain(child.stderr.take().unwrap());
+
+    while child.try_wait()?.is_none() {
+        if std::time::Instant::now() >= deadline {
+            let group = nix::unistd::Pid::from_raw(child.id() as i32);
+            let _ = nix::sys::signal::killpg(group,
+                                             nix::sys::signal::Signal::SIGKILL);
+            let _ = child.wait();
+
+            return Err(std::io::Error::new(std::io::ErrorKind::TimedOut,
+                                           "timed out"));
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    // Some programs, such as java -version, report on stderr.
+    let mut output = collect(stdout, deadline)?;
+
+    if output.trim().is_empty() {
+        output = collect(stderr, deadline)?;
+    }
+
+    parse(&output).ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData,
+                            "no version in output")
+    })
+}
+
+// Reads pipe to its end on a thread of its own, keeping the first
+// OUTPUT_LIMIT bytes and discarding the rest.
+fn drain<R: Read + Send + 'static>(pipe: R)
+-> std::sync::mpsc::Receiver<std::io::Result<String>> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    std::thread::spawn(move || {
+        let mut pipe = pipe;
+        let mut output = Vec::new();
+        let result = (&mut pipe).take(OUTPUT_LIMIT)
+                                .read_to_end(&mut output)
+                                .and_then(|_| {
+            std::io::copy(&mut pipe, &mut std::io::sink())
+        });
+
+        let _ = sender.send(result.map(|_| {
+            String::from_utf8_lossy(&output).into_owned()
+        }));
+    });
+
+    receiver
+}
+
+// What a drained pipe held. A program that left a child of its own behind
+// with the pipe open does not get to hold us past deadline.
+fn collect(pipe: std::sync::mpsc::Receiver<std::io::Result<String>>,
+           deadline: std::time::Instant) -> std::io::Result<String> {
+    let left = deadline.saturating_duration_since(std::time::Instant::now());
+
+    pipe.recv_timeout(left).unwrap_or_else(|_| {
+        Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out"))
+    })
+}
+
+// The first version-like word of the first line, or else that line.
+fn parse(output: &str) -> Option<String> {
+    let line = output.lines().map(|l| l.trim()).find(|l| !l.is_empty())?;
+    let pattern = regex::Regex::new(r"\d+(\.\d+)+[\w.+~-]*")
+        .expect("version pattern is valid");
+
+    Some(pattern.find(line).map(|m| m.as_str()).unwrap_or(line).to_string())
+}
@@ -0,0 +1,87 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+}
+
+// Finds processes that execute target or have it mapped, as needrestart
+// does. Replaced files show up with a " (deleted)" suffix in procfs.
+pub fn using<P: std::convert::AsRef<std::path::Path>>(target: P)
+-> std::io::Result<Vec<Process>> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let target = target.as_ref().canonicalize()
+                       .unwrap_or_else(|_| target.as_ref().to_path_buf());
+    // Compared as bytes, since the target need not be valid UTF-8.
+    let target_bytes = target.as_os_str().as_bytes();
+    let deleted = [target_bytes, b" (deleted)"].concat();
+    let mut found = Vec::new();
+
+    for entry in std::fs::read_dir("/proc")? {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+
+        let pid: u32 = match entry.file_name().to_str()
+                                  .and_then(|n| n.parse().ok()) {
+            Some(p) => p,
+            None => continue,
+        };
+
+        let dir = entry.path();
+        let exe = dir.join("exe").read_link().ok();
+        let runs = exe.is_some_and(|e| {
+            let e = e.as_os_str().as_bytes();
+
+            e == target_bytes || e == deleted.as_slice()
+        });
+
+        let maps = !runs && std::fs::read(dir.join("maps"))
+            .map(|m| m.split(|&b| b == b'\n').any(|l| {
+                let path = l.split(|&b| b == b' ').filter(|f| !f.is_empty())
+                            .skip(5).collect::<Vec<_>>().join(&b' ');
+
+                path == target_bytes || path == deleted
+            }))
+            .unwrap_or(false);
+
+        if runs || maps {
+            let name = std::fs::read_to_string(dir.join("comm"))
+                .map(|c| c.trim().to_string())
+                .unwrap_or_default();
+
+            found.push(Process{ pid, name });
+        }
+    }
+
+    found.sort_by_key(|p| p.pid);
+
+    Ok(found)
+}
@@ -0,0 +1,140 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+use super::alternative_db::AlternativeDb;
+use super::alternative_list::AlternativeList;
+use super::filesystem;
+
+// Login shells source every *.sh file here.
+pub static PROFILE_DIR: &str = "/etc/profile.d";
+
+const SNIPPET_PREFIX: &str = "update-alternatives-";
+
+// Where the variables of name are exported.
+pub fn snippet_path(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(PROFILE_DIR).join(format!("{}{}.sh", SNIPPET_PREFIX,
+                                                   name))
+}
+
+// Replaces <target> by target, <target-dir> by the directory it is in, and
+// <target-root> by the one above, such as /usr/lib/jvm/java-21 for
+// /usr/lib/jvm/java-21/bin/java.
+pub fn expand(template: &str, target: &std::path::Path) -> String {
+    let dir = target.parent().unwrap_or(target);
+    let root = dir.parent().unwrap_or(dir);
+
+    template.replace("<target-root>", &root.display().to_string())
+            .replace("<target-dir>", &dir.display().to_string())
+            .replace("<target>", &target.display().to_string())
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// The contents of the snippet of name, or None if it should not exist.
+pub fn snippet(name: &str, list: &AlternativeList) -> Option<String> {
+    if list.environment().is_empty() {
+        return None;
+    }
+
+    let target = list.selected()?.target();
+    let mut contents = format!("# Generated by update-alternatives for {}; \
+                                changes are overwritten.\n", name);
+
+    for (variable, template) in list.environment() {
+        contents.push_str(&format!("export {}={}\n", variable,
+                                   shell_quote(&expand(template, target))));
+    }
+
+    Some(contents)
+}
+
+// The snippets that differ from what the selections call for, with their new
+// contents, or None for those to remove.
+pub fn pending(db: &AlternativeDb) -> Vec<(std::path::PathBuf, Option<String>)> {
+    let dir = filesystem::under(db.root(), PROFILE_DIR);
+    let mut changes: Vec<(std::path::PathBuf, Option<String>)> = db.iter()
+        .filter(|(name, _)| db.writes(name))
+        .filter_map(|(name, list)| {
+            let path = filesystem::under(db.root(), snippet_path(name));
+            let contents = snippet(name, list);
+            let current = filesystem::read(&path).ok();
+
+            (current != contents).then_some((path, contents))
+        })
+        .collect();
+
+    // Snippets of groups that were removed since.
+    if let Ok(entries) = std::fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let name = file_name.to_str()
+                                .and_then(|n| n.strip_prefix(SNIPPET_PREFIX))
+                                .and_then(|n| n.strip_suffix(".sh"));
+
+            if name.is_some_and(|n| db.writes(n) && !db.has_alternatives(n)) {
+                changes.push((entry.path(), None));
+            }
+        }
+    }
+
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    changes
+}
+
+// Brings the snippets in line with the selections and returns the paths
+// written or removed.
+pub fn write(db: &AlternativeDb)
+-> std::io::Result<Vec<(std::path::PathBuf, bool)>> {
+    let changes = pending(db);
+    let mut written = Vec::new();
+
+    if changes.iter().any(|(_, c)| c.is_some()) {
+        let dir = filesystem::under(db.root(), PROFILE_DIR);
+
+        if !dir.is_dir() {
+            filesystem::create_dir(&dir)?;
+        }
+    }
+
+    for (path, contents) in changes {
+        match contents {
+            Some(c) => {
+                filesystem::write_atomic(c, &path)?;
+                written.push((path, true));
+            },
+            None => {
+                filesystem::remove(&path)?;
+                written.push((path, false));
+            },
+        }
+    }
+
+    Ok(written)
+}
@@ -0,0 +1,131 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+use super::config::Config;
+
+// Always means the running system, unless the configuration says otherwise.
+pub static HOST: &str = "host";
+
+// The roots called names in the configuration, in the order given.
+pub fn resolve(config: &Config, names: &[String])
+-> Result<Vec<(String, std::path::PathBuf)>, String> {
+    names.iter().map(|name| {
+        match config.roots.get(name) {
+            Some(path) => Ok((name.clone(), path.clone())),
+            None if name == HOST => Ok((name.clone(),
+                                        std::path::PathBuf::from("/"))),
+            None => {
+                let mut known: Vec<&str> = config.roots.keys()
+                                                 .map(|k| k.as_str())
+                                                 .collect();
+
+                if !known.contains(&HOST) {
+                    known.insert(0, HOST);
+                }
+
+                Err(format!("no root named {}; known are {}", name,
+                            known.join(", ")))
+            },
+        }
+    }).collect()
+}
+
+// The arguments of this invocation without --on and its value.
+fn without_on() -> Vec<std::ffi::OsString> {
+    let mut args = Vec::new();
+    let mut skip = false;
+    let mut options = true;
+
+    for arg in std::env::args_os().skip(1) {
+        if skip {
+            skip = false;
+        } else if !options || arg == "--" {
+            options = false;
+            args.push(arg);
+        } else if arg == "--on" {
+            skip = true;
+        } else if !arg.to_str().is_some_and(|a| a.starts_with("--on=")) {
+            args.push(arg);
+        }
+    }
+
+    args
+}
+
+// Runs this invocation once below each root, one after the other, and
+// reports how each went. Returns the exit status: 0 if all succeeded.
+pub fn run_on(roots: &[(String, std::path::PathBuf)]) -> i32 {
+    let exe = match std::env::current_exe() {
+        Ok(e) => e,
+        Err(e) => {
+            error!(IO, "could not find this program: {}", e);
+
+            return 1;
+        },
+    };
+    let args = without_on();
+    let mut failed = Vec::new();
+
+    for (name, path) in roots {
+        println!("update-alternatives: on {} ({}):", name, path.display());
+
+        // Nothing would stop the command from creating a root that is not
+        // mounted, or was misspelled.
+        if !path.is_dir() {
+            error!(NOT_A_ROOT, "{} is not a directory", path.display());
+            failed.push(name.clone());
+
+            continue;
+        }
+
+        let mut command = std::process::Command::new(&exe);
+
+        if path != std::path::Path::new("/") {
+            command.arg("--root").arg(path);
+        }
+
+        // DESTDIR would otherwise stand in for the host.
+        let status = command.args(&args).env_remove("DESTDIR").status();
+
+        match status {
+            Ok(s) if s.success() => (),
+            Ok(s) => failed.push(format!("{} ({})", name, s)),
+            Err(e) => failed.push(format!("{} ({})", name, e)),
+        }
+    }
+
+    println!("update-alternatives: {} of {} roots succeeded",
+             roots.len() - failed.len(), roots.len());
+
+    if failed.is_empty() {
+        return 0;
+    }
+
+    error!(PROBLEMS, "failed on {}", failed.join(", "));
+
+    1
+}
@@ -0,0 +1,270 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate serde;
+extern crate serde_json;
+
+use std::io::{BufRead, Write};
+
+use self::serde_json::Value;
+
+use super::alternative::Alternative;
+use super::alternative_db::{AlternativeDb, check_addition};
+use super::audit::AuditLog;
+use super::errors::{self, Error};
+use super::freeze;
+use super::view::GroupView;
+
+#[derive(Deserialize)]
+pub struct Request {
+    #[serde(default)]
+    id: Value,
+    pub method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct AddParams {
+    name: String,
+    target: String,
+    priority: i64,
+}
+
+#[derive(Deserialize)]
+struct TargetParams {
+    name: String,
+    target: String,
+}
+
+#[derive(Deserialize)]
+struct NameParams {
+    name: Option<String>,
+}
+
+// Errors carry the id of their kind, which is sent as data.id next to the
+// JSON-RPC code.
+pub type RpcResult = Result<Value, (i64, Error)>;
+
+pub fn run<R: BufRead, W: Write>(db: &mut AlternativeDb, log: &mut AuditLog,
+                                 input: R, output: W) -> bool {
+    let mut mutated = false;
+
+    answer(input, output, |request| dispatch(db, log, request, &mut mutated));
+
+    mutated
+}
+
+// Answers every request read from input with one line of output, until input
+// ends or output is closed.
+pub fn answer<R: BufRead, W: Write, F: FnMut(&Request) -> RpcResult>(
+    input: R, mut output: W, mut handle: F
+) {
+    for line in input.lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                error!(PROTOCOL, "could not read request: {}", e);
+
+                break;
+            },
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (id, result) = match serde_json::from_str::<Request>(&line) {
+            Ok(r) => (r.id.clone(), handle(&r)),
+            Err(e) => (Value::Null, Err((-32700, Error::new(&errors::PROTOCOL,
+                                                             e.to_string())))),
+        };
+
+        let response = match result {
+            Ok(r) => serde_json::json!({ "jsonrpc": "2.0", "id": id,
+                                         "result": r }),
+            Err((code, e)) => serde_json::json!({
+                "jsonrpc": "2.0", "id": id,
+                "error": { "code": code, "message": e.message,
+                           "data": { "id": e.code.id } },
+            }),
+        };
+
+        if writeln!(output, "{}", response).and_then(|_| output.flush())
+                                           .is_err() {
+            break;
+        }
+    }
+}
+
+pub fn dispatch(db: &mut AlternativeDb, log: &mut AuditLog, request: &Request,
+            mutated: &mut bool) -> RpcResult {
+    match request.method.as_str() {
+        "add" => {
+            let p: AddParams = params(&request.params)?;
+
+            check_addition(&p.name, p.target.as_ref(), p.priority)
+                .map_err(|e| (-32602, e))?;
+
+            let changed = db.add_alternative(
+                &p.name, Alternative::from_parts(&p.target, p.priority)
+            ).map_err(|e| (-32602, e))?;
+
+            if changed {
+                log.record(format!("add {} {} {}", p.name, p.target,
+                                   p.priority));
+                *mutated = true;
+            }
+
+            Ok(serde_json::json!({ "changed": changed }))
+        },
+        "remove" => {
+            let p: TargetParams = params(&request.params)?;
+            let changed = db.remove_alternative(&p.name, &p.target);
+
+            if changed {
+                log.record(format!("remove {} {}", p.name, p.target));
+                *mutated = true;
+            }
+
+            Ok(serde_json::json!({ "changed": changed }))
+        },
+        "list" => {
+            let p: NameParams = if request.params.is_null() {
+                NameParams{ name: None }
+            } else {
+                params(&request.params)?
+            };
+
+            let value = match p.name {
+                Some(name) => match db.alternatives(&name) {
+                    Some(list) => serde_json::to_value(GroupView::new(&name,
+                                                                      list)),
+                    None => return Err((-32602, Error::new(
+                        &errors::NOT_FOUND,
+                        format!("no alternatives found for {}", name)
+                    ))),
+                },
+                None => {
+                    let mut groups: Vec<GroupView> =
+                        db.iter().map(|(n, l)| GroupView::new(n, l)).collect();
+                    groups.sort_by(|a, b| a.name.cmp(b.name));
+
+                    serde_json::to_value(groups)
+                },
+            };
+
+            value.map_err(|e| (-32603, Error::new(&errors::IO,
+                                                  e.to_string())))
+        },
+        "set" => {
+            let p: TargetParams = params(&request.params)?;
+
+            db.check_selection(&p.name, p.target.as_ref())
+              .map_err(|e| (-32602, e))?;
+
+            let changed = db.select(&p.name, &p.target, None);
+
+            if changed {
+                log.record(format!("set {} {}", p.name, p.target));
+                *mutated = true;
+            }
+
+            Ok(serde_json::json!({ "changed": changed }))
+        },
+        "sync" => {
+            let p: NameParams = if request.params.is_null() {
+                NameParams{ name: None }
+            } else {
+                params(&request.params)?
+            };
+
+            freeze::check(db.root()).map_err(|e| {
+                (-32603, Error::new(&errors::FROZEN, e.to_string()))
+            })?;
+
+            let changed = match p.name {
+                Some(ref name) => match db.alternatives(name) {
+                    Some(list) => list.make_symlink(),
+                    None => return Err((-32602, Error::new(
+                        &errors::NOT_FOUND,
+                        format!("no alternatives found for {}", name)
+                    ))),
+                },
+                None => db.write_links().map(|c| !c.is_empty()),
+            }.map_err(|e| (-32603, Error::new(
+                &errors::COMMIT, format!("could not write symlinks: {}", e)
+            )))?;
+
+            log.record(match p.name {
+                Some(name) => format!("sync {}", name),
+                None => String::from("sync"),
+            });
+
+            Ok(serde_json::json!({ "changed": changed }))
+        },
+        // As sync for one group, but preferring targets that exist, for
+        // watch.
+        "repair" => {
+            let p: NameParams = params(&request.params)?;
+
+            freeze::check(db.root()).map_err(|e| {
+                (-32603, Error::new(&errors::FROZEN, e.to_string()))
+            })?;
+
+            let name = p.name.ok_or_else(|| {
+                (-32602, Error::new(&errors::PROTOCOL, "repair needs a name"))
+            })?;
+            let list = db.alternatives(&name).ok_or_else(|| {
+                (-32602, Error::new(&errors::NOT_FOUND,
+                                    format!("no alternatives found for {}",
+                                            name)))
+            })?;
+            let changed = list.repair_symlink().map_err(|e| {
+                (-32603, Error::new(&errors::COMMIT,
+                                    format!("could not write symlinks: {}",
+                                            e)))
+            })?;
+
+            if changed {
+                log.record(format!("repair {}", name));
+            }
+
+            Ok(serde_json::json!({ "changed": changed }))
+        },
+        m => Err((-32601, Error::new(&errors::PROTOCOL,
+                                     format!("unknown method {}", m)))),
+    }
+}
+
+fn params<T: serde::de::DeserializeOwned>(params: &Value)
+-> Result<T, (i64, Error)> {
+    serde_json::from_value(params.clone()).map_err(|e| {
+        (-32602, Error::new(&errors::PROTOCOL, e.to_string()))
+    })
+}
@@ -0,0 +1,100 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+use super::version;
+
+pub struct Candidate {
+    pub name: String,
+    pub link: std::path::PathBuf,
+    pub target: std::path::PathBuf,
+    pub priority: i64,
+}
+
+static BREW_PREFIXES: [&str; 3] = [
+    "/opt/homebrew", "/usr/local", "/home/linuxbrew/.linuxbrew",
+];
+
+// The active prefix is the one `brew --prefix` would report.
+pub fn brew_prefix() -> Option<std::path::PathBuf> {
+    if let Some(prefix) = std::env::var_os("HOMEBREW_PREFIX") {
+        if !prefix.is_empty() {
+            return Some(std::path::PathBuf::from(prefix));
+        }
+    }
+
+    BREW_PREFIXES.iter()
+                 .map(std::path::Path::new)
+                 .find(|p| p.join("Cellar").is_dir())
+                 .map(std::path::Path::to_path_buf)
+}
+
+// Proposes a group for every executable that at least two versioned
+// formulae (python@3.12, llvm@18) provide. Targets go through the opt
+// prefix so that they survive `brew upgrade`.
+pub fn brew(prefix: &std::path::Path) -> std::io::Result<Vec<Candidate>> {
+    let opt = prefix.join("opt");
+    let mut found: std::collections::BTreeMap<String, Vec<Candidate>> =
+        std::collections::BTreeMap::new();
+
+    for entry in opt.read_dir()? {
+        let keg = entry?.path();
+        let formula = match keg.file_name().and_then(|n| n.to_str()) {
+            Some(f) => f.to_string(),
+            None => continue,
+        };
+
+        let priority = match formula.split_once('@')
+                                    .and_then(|(_, v)| version::priority(v)) {
+            Some(p) => p,
+            None => continue,
+        };
+
+        let bin = match keg.join("bin").read_dir() {
+            Ok(b) => b,
+            Err(_) => continue,
+        };
+
+        for executable in bin.filter_map(Result::ok) {
+            let name = executable.file_name().to_string_lossy().into_owned();
+
+            found.entry(name.clone()).or_default().push(Candidate{
+                link: prefix.join("bin").join(&name),
+                target: opt.join(&formula).join("bin").join(&name),
+                name,
+                priority,
+            });
+        }
+    }
+
+    Ok(found.into_iter()
+            .filter(|(_, c)| c.len() > 1)
+            .flat_map(|(_, mut c)| {
+                c.sort_by_key(|c| c.priority);
+                c
+            })
+            .collect())
+}
@@ -0,0 +1,434 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate serde;
+extern crate serde_json;
+
+use std::io::{BufRead, Read, Write};
+
+use super::alternative::Alternative;
+use super::alternative_db::{self, AlternativeDb, check_addition};
+use super::audit::AuditLog;
+use super::errors;
+use super::filesystem;
+use super::freeze;
+use super::notify;
+use super::signals;
+use super::view::GroupView;
+
+pub static TOKEN_PATH: &str = "/var/lib/update-alternatives/serve-token";
+
+static INDEX_HTML: &str = include_str!("serve.html");
+
+struct Request {
+    method: String,
+    path: String,
+    token: Option<String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+#[derive(Deserialize)]
+struct AddRequest {
+    target: String,
+    priority: i64,
+}
+
+#[derive(Deserialize)]
+struct TargetRequest {
+    target: String,
+}
+
+pub fn run<P: std::convert::AsRef<std::path::Path>>(
+    folder: P, listen: &str, token: &str, log: &mut AuditLog
+) -> std::io::Result<()> {
+    let listener = std::net::TcpListener::bind(listen)?;
+
+    if !listener.local_addr()?.ip().is_loopback() {
+        eprintln!("update-alternatives: warning: serving on non-loopback \
+                  address {}", listen);
+    }
+
+    let status = format!("serving on http://{}/", listen);
+
+    println!("update-alternatives: {}", status);
+    notify::ready(&status);
+
+    loop {
+        notify::alive();
+
+        if !notify::wait(&listener)? {
+            continue;
+        }
+
+        let mut stream = match listener.accept() {
+            Ok((s, _)) => s,
+            Err(e) => {
+                error!(SERVICE, "serve: could not accept connection: {}", e);
+
+                continue;
+            },
+        };
+
+        if let Err(e) = stream.set_read_timeout(
+            Some(std::time::Duration::from_secs(5))
+        ) {
+            error!(SERVICE, "serve: could not set a timeout on connection: {}",
+                   e);
+
+            continue;
+        }
+
+        let response = match read_request(&mut stream) {
+            Ok(r) => handle(folder.as_ref(), token, log, &r),
+            Err(e) => error(400, &errors::PROTOCOL,
+                            &format!("malformed request: {}", e)),
+        };
+
+        if let Err(e) = write_response(&mut stream, &response) {
+            error!(SERVICE, "serve: could not write response: {}", e);
+        }
+
+        if let Err(e) = log.flush() {
+            error!(AUDIT, "could not write to audit log: {}", e);
+        }
+
+        signals::exit_if_pending();
+    }
+}
+
+pub fn load_or_create_token<P: std::convert::AsRef<std::path::Path>>(path: P)
+-> std::io::Result<String> {
+    let token_path = path.as_ref();
+
+    if token_path.exists() {
+        return Ok(filesystem::read(token_path)?.trim().to_string());
+    }
+
+    let mut bytes = [0u8; 24];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    if let Some(parent) = token_path.parent() {
+        if !parent.exists() {
+            filesystem::create_dir(parent)?;
+        }
+    }
+
+    filesystem::write(format!("{}\n", token), token_path)?;
+    filesystem::set_mode(token_path, 0o600)?;
+
+    println!("update-alternatives: wrote new API token to {}",
+             token_path.display());
+
+    Ok(token)
+}
+
+fn handle(folder: &std::path::Path, token: &str, log: &mut AuditLog,
+          request: &Request) -> Response {
+    let segments: Vec<String> = request.path
+                                       .split('?')
+                                       .next()
+                                       .unwrap_or("")
+                                       .split('/')
+                                       .filter(|s| !s.is_empty())
+                                       .map(percent_decode)
+                                       .collect();
+    let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+
+    if request.method == "GET" && segments.is_empty() {
+        return Response{ status: 200, content_type: "text/html; charset=utf-8",
+                         body: INDEX_HTML.to_string() };
+    }
+
+    // For probes, which have no token. Tells only whether the database can be
+    // read.
+    if request.method == "GET" && segments == ["healthz"] {
+        return match folder.read_dir() {
+            Ok(_) => json(200, &Health{ status: "ok" }),
+            Err(e) => error(503, &errors::BAD_DATABASE,
+                            &format!("could not read {}: {}",
+                                     folder.display(), e)),
+        };
+    }
+
+    if segments.first() != Some(&"api") {
+        return error(404, &errors::PROTOCOL, "not found");
+    }
+
+    if !request.token.as_ref().is_some_and(|t| constant_time_eq(t, token)) {
+        return error(401, &errors::DENIED, "missing or invalid bearer token");
+    }
+
+    // Names become file names, as of the default link in the link
+    // directory, so they are checked before anything is looked up.
+    if let ["groups", name, ..] = segments[1..] {
+        if let Err(e) = alternative_db::check_name(name) {
+            return error(400, &errors::BAD_ARGUMENT, &e);
+        }
+    }
+
+    let mut db = match AlternativeDb::from_folder(folder) {
+        Ok(d) => d,
+        Err(e) => return error(500, &errors::BAD_DATABASE,
+                               &format!("could not read {}: {}",
+                                        folder.display(), e)),
+    };
+
+    match (request.method.as_str(), &segments[1..]) {
+        ("GET", ["groups"]) => {
+            let mut groups: Vec<GroupView> =
+                db.iter().map(|(n, l)| GroupView::new(n, l)).collect();
+            groups.sort_by(|a, b| a.name.cmp(b.name));
+
+            json(200, &groups)
+        },
+        ("GET", ["groups", name]) => match db.alternatives(name) {
+            Some(list) => json(200, &GroupView::new(name, list)),
+            None => error(404, &errors::NOT_FOUND,
+                          &format!("no alternatives for {}", name)),
+        },
+        ("POST", ["groups", name, "alternatives"]) => {
+            let add: AddRequest = match serde_json::from_slice(&request.body) {
+                Ok(a) => a,
+                Err(e) => return error(400, &errors::PROTOCOL, &e.to_string()),
+            };
+
+            if let Err(e) = check_addition(name, add.target.as_ref(),
+                                           add.priority) {
+                return error(400, e.code, &e.message);
+            }
+
+            let changed = match db.add_alternative(
+                name, Alternative::from_parts(&add.target, add.priority)
+            ) {
+                Ok(c) => c,
+                Err(e) => return error(400, e.code, &e.message),
+            };
+
+            if changed {
+                log.record(format!("serve: add {} {} {}", name, add.target,
+                                   add.priority));
+            }
+
+            commit_if(&db, changed)
+        },
+        ("DELETE", ["groups", name, "alternatives"]) => {
+            let remove: TargetRequest =
+                match serde_json::from_slice(&request.body) {
+                    Ok(r) => r,
+                    Err(e) => return error(400, &errors::PROTOCOL,
+                                           &e.to_string()),
+                };
+
+            let changed = db.remove_alternative(name, &remove.target);
+
+            if changed {
+                log.record(format!("serve: remove {} {}", name,
+                                   remove.target));
+            }
+
+            commit_if(&db, changed)
+        },
+        ("PUT", ["groups", name, "current"]) => {
+            let set: TargetRequest = match serde_json::from_slice(&request.body) {
+                Ok(s) => s,
+                Err(e) => return error(400, &errors::PROTOCOL,
+                                       &e.to_string()),
+            };
+
+            if !db.has_alternatives(name) {
+                return error(404, &errors::NOT_FOUND,
+                             &format!("no alternatives for {}", name));
+            }
+
+            if let Err(e) = db.check_selection(name, set.target.as_ref()) {
+                return error(400, e.code, &e.message);
+            }
+
+            let changed = db.select(name, &set.target, None);
+
+            if changed {
+                log.record(format!("serve: set {} {}", name, set.target));
+            }
+
+            commit_if(&db, changed)
+        },
+        ("POST", ["sync"]) => match freeze::check(db.root())
+                                           .and_then(|_| db.write_links()) {
+            Ok(changes) => {
+                log.record("serve: sync");
+
+                json(200, &Changed{ changed: !changes.is_empty() })
+            },
+            Err(e) => error(500, &errors::COMMIT,
+                            &format!("could not write symlinks: {}", e)),
+        },
+        _ => error(404, &errors::PROTOCOL, "not found"),
+    }
+}
+
+#[derive(Serialize)]
+struct Changed {
+    changed: bool,
+}
+
+#[derive(Serialize)]
+struct Health {
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+    id: &'a str,
+}
+
+fn commit_if(db: &AlternativeDb, changed: bool) -> Response {
+    if changed && super::commit(db).is_err() {
+        return error(500, &errors::COMMIT, "could not commit changes");
+    }
+
+    json(200, &Changed{ changed })
+}
+
+fn json<T: serde::Serialize>(status: u16, value: &T) -> Response {
+    match serde_json::to_string(value) {
+        Ok(body) => Response{ status, content_type: "application/json", body },
+        Err(e) => error(500, &errors::IO, &e.to_string()),
+    }
+}
+
+fn error(status: u16, code: &errors::Code, message: &str) -> Response {
+    let body = serde_json::to_string(&ErrorBody{ error: message,
+                                                 id: code.id })
+                          .unwrap_or_default();
+
+    Response{ status, content_type: "application/json", body }
+}
+
+fn read_request(stream: &mut std::net::TcpStream) -> std::io::Result<Request> {
+    let mut reader = std::io::BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(m), Some(p)) => (m.to_string(), p.to_string()),
+        _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData,
+                                            "invalid request line")),
+    };
+
+    let mut content_length: usize = 0;
+    let mut token = None;
+
+    loop {
+        line.clear();
+
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+
+        let (key, value) = match line.split_once(':') {
+            Some((k, v)) => (k.trim().to_ascii_lowercase(), v.trim()),
+            None => continue,
+        };
+
+        if key == "content-length" {
+            content_length = value.parse().map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData,
+                                    "invalid Content-Length")
+            })?;
+        } else if key == "authorization" {
+            token = value.strip_prefix("Bearer ").map(|t| t.to_string());
+        }
+    }
+
+    if content_length > 1 << 20 {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData,
+                                       "request body too large"));
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request{ method, path, token, body })
+}
+
+fn write_response(stream: &mut std::net::TcpStream, response: &Response)
+-> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+
+    write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: {}\r\n\
+                    Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+           response.status, reason, response.content_type,
+           response.body.len(), response.body)
+}
+
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+                       .and_then(|h| std::str::from_utf8(h).ok())
+                       .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            },
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
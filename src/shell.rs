@@ -0,0 +1,526 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+extern crate rustyline;
+
+use self::rustyline::completion::{Completer, FilenameCompleter, Pair};
+use self::rustyline::error::ReadlineError;
+use self::rustyline::highlight::Highlighter;
+use self::rustyline::hint::Hinter;
+use self::rustyline::history::DefaultHistory;
+use self::rustyline::validate::Validator;
+use self::rustyline::{Context, Editor};
+
+use super::alternative::Alternative;
+use super::alternative_db::{AlternativeDb, check_addition};
+use super::audit::AuditLog;
+use super::helper;
+use super::signals;
+
+type Entries = std::collections::BTreeSet<(String, std::path::PathBuf, i64)>;
+
+// The manual selection of every group that has one.
+type Selections = std::collections::BTreeMap<String, std::path::PathBuf>;
+
+#[derive(PartialEq)]
+struct State {
+    entries: Entries,
+    selections: Selections,
+}
+
+static COMMANDS: [&str; 9] = [
+    "list", "add", "remove", "set", "diff", "commit", "abort", "quit", "help",
+];
+
+static HELP: &str = "\
+list [NAME]                show all groups, or the alternatives of NAME
+add NAME TARGET WEIGHT     add or update an alternative
+remove NAME TARGET         remove an alternative
+set NAME TARGET            select an alternative manually
+diff                       show changes that are not committed yet
+commit                     write all changes to the database and links
+abort                      leave without writing uncommitted changes
+quit                       commit pending changes and leave
+
+Quote or backslash-escape targets that contain spaces.";
+
+struct Helper {
+    files: FilenameCompleter,
+    groups: std::collections::BTreeMap<String, Vec<String>>,
+}
+
+impl Helper {
+    fn new(db: &AlternativeDb) -> Helper {
+        let mut helper = Helper{ files: FilenameCompleter::new(),
+                                 groups: std::collections::BTreeMap::new() };
+        helper.refresh(db);
+
+        helper
+    }
+
+    fn refresh(&mut self, db: &AlternativeDb) {
+        self.groups = db.iter().map(|(name, list)| {
+            let targets = list.links().iter()
+                              .map(|a| a.target().display().to_string())
+                              .collect();
+
+            (name.clone(), targets)
+        }).collect();
+    }
+}
+
+impl Completer for Helper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context)
+    -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let mut parsed = split_words(before);
+        let (start, word) = if parsed.trailing {
+            parsed.words.pop().unwrap()
+        } else {
+            (pos, String::new())
+        };
+        let args: Vec<&str> = parsed.words.iter().map(|(_, w)| w.as_str())
+                                    .collect();
+
+        let options: Vec<String> = match (args.first().cloned(), args.len()) {
+            (None, _) => COMMANDS.iter().map(|c| c.to_string()).collect(),
+            (Some("list"), 1) | (Some("add"), 1) | (Some("remove"), 1)
+                | (Some("set"), 1) => self.groups.keys().cloned().collect(),
+            (Some("remove"), 2) | (Some("set"), 2) => {
+                self.groups.get(args[1]).cloned().unwrap_or_default()
+            },
+            (Some("add"), 2) => return self.files.complete_path(line, pos),
+            _ => Vec::new(),
+        };
+
+        let candidates = options.into_iter()
+                                .filter(|o| o.starts_with(&word))
+                                .map(|o| Pair{ replacement: escape(&o),
+                                               display: o })
+                                .collect();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Helper {
+    type Hint = String;
+}
+
+impl Highlighter for Helper {}
+
+impl Validator for Helper {}
+
+impl rustyline::Helper for Helper {}
+
+// Returns whether there are uncommitted changes left in db.
+// With via_helper, changes are committed by the privileged helper, and
+// nothing is left for the caller to commit.
+pub fn run(db: &mut AlternativeDb, log: &mut AuditLog, via_helper: bool)
+-> bool {
+    let mut editor: Editor<Helper, DefaultHistory> = match Editor::new() {
+        Ok(e) => e,
+        Err(e) => {
+            error!(IO, "could not start shell: {}", e);
+
+            return false;
+        },
+    };
+
+    editor.set_helper(Some(Helper::new(db)));
+
+    let mut committed = state(db);
+
+    loop {
+        let prompt = if state(db) == committed {
+            "update-alternatives> "
+        } else {
+            "update-alternatives*> "
+        };
+
+        let line = match editor.readline(prompt) {
+            Ok(l) => l,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                error!(IO, "could not read command: {}", e);
+
+                break;
+            },
+        };
+
+        let parsed = split_words(&line);
+
+        if parsed.open_quote {
+            error!(BAD_ARGUMENT, "unterminated quote");
+
+            continue;
+        }
+
+        let words: Vec<&str> = parsed.words.iter().map(|(_, w)| w.as_str())
+                                     .collect();
+
+        if words.is_empty() {
+            continue;
+        }
+
+        let _ = editor.add_history_entry(line.as_str());
+
+        match (words[0], &words[1..]) {
+            ("help", _) => println!("{}", HELP),
+            ("list", []) => {
+                let mut names: Vec<&String> = db.iter().map(|(n, _)| n)
+                                                .collect();
+                names.sort();
+
+                for name in names {
+                    let current = db.alternatives(name)
+                                    .and_then(|l| l.current_target())
+                                    .map(|t| t.display().to_string())
+                                    .unwrap_or_else(|| String::from("<none>"));
+
+                    println!("{} -> {}", name, current);
+                }
+            },
+            ("list", [name]) => match db.alternatives(name) {
+                Some(list) => print!("{}", list),
+                None => error!(NOT_FOUND, "no alternatives found \
+                                          for {}", name),
+            },
+            ("add", [name, target, weight]) => match weight.parse::<i64>() {
+                Ok(w) => {
+                    let added = check_addition(name, target.as_ref(), w)
+                        .and_then(|_| {
+                            db.add_alternative(name,
+                                               Alternative::from_parts(target,
+                                                                       w))
+                        });
+
+                    if let Err(e) = added {
+                        error!(e);
+                    }
+                },
+                Err(e) => error!(BAD_ARGUMENT, "could not parse {} \
+                                               as weight: {}", weight, e),
+            },
+            ("remove", [name, target]) => {
+                if !db.remove_alternative(name, target) {
+                    error!(NOT_MEMBER, "{} is not an alternative \
+                                       for {}", target, name);
+                }
+            },
+            ("set", [name, target]) => {
+                match db.check_selection(name, target.as_ref()) {
+                    Ok(()) => {
+                        db.select(name, target, None);
+                    },
+                    Err(e) => error!(e),
+                }
+            },
+            ("diff", []) => {
+                for line in diff(&committed, &state(db)) {
+                    println!("{}", line);
+                }
+            },
+            ("commit", []) => {
+                let current = state(db);
+
+                if current != committed
+                   && commit(db, log, &committed, &current, via_helper) {
+                    committed = current;
+                }
+            },
+            ("abort", []) => return false,
+            ("quit", []) | ("exit", []) => break,
+            (command, _) if COMMANDS.contains(&command) => {
+                error!(BAD_ARGUMENT, "wrong arguments for {}; try \
+                                     help", command);
+            },
+            (command, _) => {
+                error!(BAD_ARGUMENT, "unknown command {}; try help", command);
+            },
+        }
+
+        if let Some(helper) = editor.helper_mut() {
+            helper.refresh(db);
+        }
+    }
+
+    let current = state(db);
+
+    if via_helper {
+        if current != committed && !send(&committed, &current) {
+            std::process::exit(1);
+        }
+
+        return false;
+    }
+
+    record(log, &committed, &current);
+
+    current != committed
+}
+
+fn commit(db: &AlternativeDb, log: &mut AuditLog, old: &State, new: &State,
+          via_helper: bool) -> bool {
+    if via_helper {
+        return send(old, new);
+    }
+
+    if super::commit(db).is_err() {
+        return false;
+    }
+
+    record(log, old, new);
+
+    if let Err(e) = log.flush() {
+        error!(AUDIT, "could not write to audit log: {}", e);
+    }
+
+    signals::exit_if_pending();
+
+    true
+}
+
+// Hands the changes from old to new to the helper, which commits and records
+// each of them. Sending them again after a failure is harmless.
+fn send(old: &State, new: &State) -> bool {
+    let result = helper::Client::connect().and_then(|mut client| {
+        let text = |target: &std::path::Path| {
+            target.to_str().map(|t| t.to_string()).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                                    format!("{} is not valid UTF-8",
+                                            target.display()))
+            })
+        };
+
+        for (name, target, _) in old.entries.difference(&new.entries) {
+            if !new.entries.iter().any(|(n, t, _)| n == name && t == target) {
+                client.remove(name, &text(target)?)?;
+            }
+        }
+
+        for (name, target, priority) in new.entries.difference(&old.entries) {
+            client.add(name, &text(target)?, *priority)?;
+        }
+
+        for (name, target) in selected(old, new) {
+            client.set(name, &text(target)?)?;
+        }
+
+        Ok(())
+    });
+
+    if let Err(e) = result {
+        error!(COMMIT, "could not commit through the helper: {}", e);
+
+        return false;
+    }
+
+    true
+}
+
+struct Words {
+    // Each word with the byte offset it starts at.
+    words: Vec<(usize, String)>,
+    open_quote: bool,
+    // Whether the last word runs up to the end of the line.
+    trailing: bool,
+}
+
+// Splits line into words as a POSIX shell would, honouring single and double
+// quotes and backslash escapes, so that targets may contain spaces.
+fn split_words(line: &str) -> Words {
+    let mut words = Vec::new();
+    let mut word: Option<(usize, String)> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if quote.is_none() && c.is_whitespace() {
+            words.extend(word.take());
+
+            continue;
+        }
+
+        let text = &mut word.get_or_insert_with(|| (i, String::new())).1;
+
+        match (quote, c) {
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '\\') => text.extend(chars.next().map(|(_, n)| n)),
+            (Some('"'), '\\') => match chars.peek() {
+                Some(&(_, n)) if n == '"' || n == '\\' => {
+                    text.push(n);
+                    chars.next();
+                },
+                _ => text.push('\\'),
+            },
+            (_, c) => text.push(c),
+        }
+    }
+
+    let trailing = word.is_some();
+    words.extend(word);
+
+    Words{ words, open_quote: quote.is_some(), trailing }
+}
+
+// The inverse of split_words for a single word.
+fn escape(word: &str) -> String {
+    let mut escaped = String::with_capacity(word.len());
+
+    for c in word.chars() {
+        if c.is_whitespace() || c == '\'' || c == '"' || c == '\\' {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+fn state(db: &AlternativeDb) -> State {
+    let entries = db.iter().flat_map(|(name, list)| {
+        list.links().iter().map(move |a| {
+            (name.clone(), a.target().to_path_buf(), a.priority())
+        })
+    }).collect();
+    let selections = db.iter().filter_map(|(name, list)| {
+        Some((name.clone(), list.manual()?.target().to_path_buf()))
+    }).collect();
+
+    State{ entries, selections }
+}
+
+// Selections that are new or changed in new; those that merely ended went
+// with the removal of their target.
+fn selected<'a>(old: &'a State, new: &'a State)
+-> impl Iterator<Item = (&'a String, &'a std::path::PathBuf)> {
+    new.selections.iter()
+                  .filter(move |(name, target)| {
+                      old.selections.get(*name) != Some(*target)
+                  })
+}
+
+fn diff(old: &State, new: &State) -> Vec<String> {
+    let removed = old.entries.difference(&new.entries).map(|e| ('-', e));
+    let added = new.entries.difference(&old.entries).map(|e| ('+', e));
+
+    let mut lines: Vec<_> = removed.chain(added)
+        .map(|(sign, (name, target, priority))| {
+            ((name, target, sign == '+'),
+             format!("{} {} {} {}", sign, name, target.display(), priority))
+        })
+        .collect();
+    lines.sort();
+
+    lines.into_iter()
+         .map(|(_, line)| line)
+         .chain(selected(old, new).map(|(name, target)| {
+             format!("* {} {}", name, target.display())
+         }))
+         .collect()
+}
+
+fn record(log: &mut AuditLog, old: &State, new: &State) {
+    for (name, target, priority) in new.entries.difference(&old.entries) {
+        log.record(format!("add {} {} {}", name, target.display(), priority));
+    }
+
+    for (name, target, _) in old.entries.difference(&new.entries) {
+        if !new.entries.iter().any(|(n, t, _)| n == name && t == target) {
+            log.record(format!("remove {} {}", name, target.display()));
+        }
+    }
+
+    for (name, target) in selected(old, new) {
+        log.record(format!("set {} {}", name, target.display()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, split_words};
+
+    fn words(line: &str) -> Vec<String> {
+        split_words(line).words.into_iter().map(|(_, w)| w).collect()
+    }
+
+    #[test]
+    fn splits_on_unquoted_whitespace() {
+        assert_eq!(words("  add  java\t/usr/bin/java 10 "),
+                   ["add", "java", "/usr/bin/java", "10"]);
+        assert!(!split_words("add java ").trailing);
+        assert!(split_words("add java").trailing);
+    }
+
+    #[test]
+    fn keeps_quoted_spaces_and_pipes() {
+        assert_eq!(words("add tool '/opt/My Tools/bin/tool' 5"),
+                   ["add", "tool", "/opt/My Tools/bin/tool", "5"]);
+        assert_eq!(words("add tool \"/opt/a|b/tool\" 5"),
+                   ["add", "tool", "/opt/a|b/tool", "5"]);
+        assert_eq!(words("add tool /opt/My\\ Tools/bin/tool|x 5"),
+                   ["add", "tool", "/opt/My Tools/bin/tool|x", "5"]);
+    }
+
+    #[test]
+    fn honours_quotes_inside_quotes() {
+        assert_eq!(words("'it\"s' \"it's\" \"a \\\"b\\\" \\\\ \\n\""),
+                   ["it\"s", "it's", "a \"b\" \\ \\n"]);
+        assert_eq!(words("'a'\"b\"c"), ["abc"]);
+        assert_eq!(words("''"), [""]);
+    }
+
+    #[test]
+    fn keeps_newlines_in_quotes() {
+        assert_eq!(words("'/opt/new\nline' x"), ["/opt/new\nline", "x"]);
+        assert_eq!(words("a\nb"), ["a", "b"]);
+    }
+
+    #[test]
+    fn reports_open_quotes() {
+        let split = split_words("add tool '/opt/My Tools");
+
+        assert!(split.open_quote);
+        assert_eq!(split.words.last().unwrap().1, "/opt/My Tools");
+        assert_eq!(split.words.last().unwrap().0, 9);
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        for word in ["/opt/My Tools/bin/tool", "it's \"quoted\"", "a|b",
+                     "back\\slash", "new\nline", "tab\there", "  edges  ",
+                     "caf\u{e9}"] {
+            assert_eq!(words(&escape(word)), [word]);
+        }
+    }
+}
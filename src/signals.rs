@@ -0,0 +1,110 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate nix;
+
+use std::convert::TryFrom;
+
+use self::nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal};
+
+static HANDLED: [Signal; 3] = [Signal::SIGINT, Signal::SIGTERM, Signal::SIGHUP];
+
+// How many critical sections are open, and the signal that arrived during
+// one, if any.
+static CRITICAL: std::sync::atomic::AtomicUsize =
+    std::sync::atomic::AtomicUsize::new(0);
+static PENDING: std::sync::atomic::AtomicI32 =
+    std::sync::atomic::AtomicI32::new(0);
+
+// Makes SIGINT, SIGTERM, and SIGHUP wait for the end of a critical section,
+// such as a commit, instead of ending the process in the middle of it.
+// Outside of critical sections they end the process as usual.
+pub fn install() {
+    let action = SigAction::new(SigHandler::Handler(handle), SaFlags::SA_RESTART,
+                                SigSet::empty());
+
+    for &signal in HANDLED.iter() {
+        // The handler only touches atomics and calls async-signal-safe
+        // functions.
+        if let Err(e) = unsafe { nix::sys::signal::sigaction(signal, &action) } {
+            error!(IO, "could not handle {}: {}", signal, e);
+        }
+    }
+}
+
+extern "C" fn handle(signal: std::os::raw::c_int) {
+    use std::sync::atomic::Ordering;
+
+    if CRITICAL.load(Ordering::SeqCst) > 0 {
+        PENDING.store(signal, Ordering::SeqCst);
+
+        return;
+    }
+
+    if let Ok(s) = Signal::try_from(signal) {
+        // Restoring the default disposition and raising the signal again
+        // reports it to the parent as the cause of death.
+        unsafe {
+            let _ = nix::sys::signal::signal(s, SigHandler::SigDfl);
+        }
+        let _ = nix::sys::signal::raise(s);
+    }
+}
+
+pub struct Critical {
+    _private: (),
+}
+
+pub fn critical() -> Critical {
+    CRITICAL.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+    Critical{ _private: () }
+}
+
+impl Drop for Critical {
+    fn drop(&mut self) {
+        CRITICAL.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+// The signal that arrived during a critical section, which the process should
+// now exit for.
+pub fn pending() -> Option<Signal> {
+    match PENDING.load(std::sync::atomic::Ordering::SeqCst) {
+        0 => None,
+        s => Signal::try_from(s).ok(),
+    }
+}
+
+// Ends the process as the pending signal would have.
+pub fn exit_if_pending() {
+    if let Some(signal) = pending() {
+        eprintln!("update-alternatives: interrupted by {}", signal);
+        std::process::exit(128 + signal as i32);
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+use super::config;
+
+// Creates a snapper snapshot of the given type and returns its number.
+fn create(settings: &config::Snapper, args: &[&str], description: &str)
+-> std::io::Result<u32> {
+    let output = std::process::Command::new("snapper")
+        .args(["-c", &settings.config, "create", "--print-number",
+               "--cleanup-algorithm", &settings.cleanup_algorithm,
+               "--description", description])
+        .args(args)
+        .stderr(std::process::Stdio::inherit())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!("snapper exited with {}",
+                                                 output.status)));
+    }
+
+    String::from_utf8_lossy(&output.stdout).trim().parse().map_err(|_| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData,
+                            "snapper did not print a snapshot number")
+    })
+}
+
+// Where snapper keeps the snapshots of the root configuration.
+pub static SNAPSHOT_DIR: &str = "/.snapshots";
+
+// The root file system as it was in snapshot number.
+pub fn snapshot_root(number: u32) -> std::path::PathBuf {
+    std::path::Path::new(SNAPSHOT_DIR).join(number.to_string())
+                                      .join("snapshot")
+}
+
+pub fn pre(settings: &config::Snapper, description: &str)
+-> std::io::Result<u32> {
+    create(settings, &["--type", "pre"], description)
+}
+
+pub fn post(settings: &config::Snapper, pre_number: u32, description: &str)
+-> std::io::Result<u32> {
+    create(settings, &["--type", "post", "--pre-number",
+                       &pre_number.to_string()], description)
+}
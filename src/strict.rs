@@ -0,0 +1,86 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use super::alternative_db::{self, AlternativeDb};
+use super::doctor;
+
+// Set for --strict, after which the warnings below block commits and fail
+// commands that read the database.
+static STRICT: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+
+pub fn set_strict() {
+    STRICT.store(true, std::sync::atomic::Ordering::SeqCst);
+}
+
+pub fn is_strict() -> bool {
+    STRICT.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+// What is otherwise only warned about: targets that are missing or cannot be
+// run, alternatives of a group that share a priority, links that PATH does
+// not find first, and entries of the database folder that belong to neither
+// this tool nor dpkg. Groups are checked in name order.
+pub fn problems(db: &AlternativeDb) -> Vec<String> {
+    let mut groups: Vec<_> = db.iter().collect();
+    groups.sort_by_key(|(name, _)| *name);
+
+    let mut problems = Vec::new();
+
+    for (name, list) in groups {
+        for alternative in list.links() {
+            if let Some(problem) = list.problem(alternative) {
+                problems.push(format!("{}: {} is {}", name,
+                                      alternative.target().display(),
+                                      problem));
+            }
+        }
+
+        let mut by_priority: std::collections::BTreeMap<i64, Vec<String>> =
+            std::collections::BTreeMap::new();
+
+        for alternative in list.links() {
+            by_priority.entry(alternative.priority())
+                       .or_default()
+                       .push(alternative.target().display().to_string());
+        }
+
+        for (priority, targets) in by_priority.iter()
+                                              .filter(|(_, t)| t.len() > 1) {
+            problems.push(format!("{}: {} share priority {}", name,
+                                  targets.join(", "), priority));
+        }
+    }
+
+    problems.extend(doctor::check_path(db).iter().map(|p| p.to_string()));
+    problems.extend(db.foreign_entries().iter()
+        .filter(|e| e.kind != alternative_db::ForeignKind::Dpkg)
+        .map(|e| format!("foreign {} {}", e.kind, e.path.display())));
+
+    problems
+}
@@ -0,0 +1,218 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+use std::io::IsTerminal;
+
+// Columns are never truncated below this many characters.
+const MIN_WIDTH: usize = 8;
+
+const SEPARATOR: &str = "  ";
+
+// A stream holds back this many rows to size its columns.
+const HELD_ROWS: usize = 64;
+
+pub struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+    selected: Option<Vec<usize>>,
+}
+
+impl Table {
+    pub fn new(header: &[&str]) -> Table {
+        Table{ header: header.iter().map(|h| h.to_string()).collect(),
+               rows: Vec::new(), selected: None }
+    }
+
+    pub fn push(&mut self, row: Vec<String>) {
+        let row = match self.selected {
+            Some(ref indices) => indices.iter().map(|i| row[*i].clone())
+                                        .collect(),
+            None => row,
+        };
+
+        self.rows.push(row);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn columns(&self) -> Vec<String> {
+        self.header.iter().map(|h| h.to_lowercase()).collect()
+    }
+
+    // Keeps only the named columns, in the given order. Returns the first
+    // name that is not a column.
+    pub fn select<S: std::convert::AsRef<str>>(&mut self, columns: &[S])
+    -> Result<(), String> {
+        let indices = columns.iter().map(|c| {
+            self.header.iter()
+                       .position(|h| h.eq_ignore_ascii_case(c.as_ref()))
+                       .ok_or_else(|| c.as_ref().to_string())
+        }).collect::<Result<Vec<usize>, String>>()?;
+
+        for row in std::iter::once(&mut self.header)
+                              .chain(self.rows.iter_mut()) {
+            *row = indices.iter().map(|i| row[*i].clone()).collect();
+        }
+
+        // Rows pushed later are selected from as they come.
+        self.selected = Some(indices);
+
+        Ok(())
+    }
+
+    // Aligns the columns and, unless wide, truncates the widest ones from the
+    // left so that every line fits the terminal.
+    pub fn print(&self, wide: bool) {
+        let widths = self.widths(wide);
+
+        for row in std::iter::once(&self.header).chain(self.rows.iter()) {
+            print_row(row, &widths);
+        }
+    }
+
+    // Prints rows as they are pushed rather than at the end, for listings too
+    // long to hold. Columns are sized by the first rows, and widened for later
+    // ones without going back.
+    pub fn stream(self, wide: bool) -> Stream {
+        Stream{ table: self, wide, widths: None }
+    }
+
+    fn widths(&self, wide: bool) -> Vec<usize> {
+        let mut widths: Vec<usize> = self.header.iter().map(|h| len(h))
+                                                       .collect();
+
+        for row in self.rows.iter() {
+            widen(&mut widths, row);
+        }
+
+        if let (false, Some(limit)) = (wide, terminal_width()) {
+            shrink(&mut widths, limit);
+        }
+
+        widths
+    }
+}
+
+pub struct Stream {
+    table: Table,
+    wide: bool,
+    widths: Option<Vec<usize>>,
+}
+
+impl Stream {
+    pub fn push(&mut self, row: Vec<String>) {
+        self.table.push(row);
+
+        match self.widths {
+            Some(ref mut widths) => {
+                let row = self.table.rows.pop().unwrap();
+
+                widen(widths, &row);
+
+                if let (false, Some(limit)) = (self.wide, terminal_width()) {
+                    shrink(widths, limit);
+                }
+
+                print_row(&row, widths);
+            },
+            None if self.table.rows.len() >= HELD_ROWS => self.release(),
+            None => (),
+        }
+    }
+
+    // Prints whatever is still held back.
+    pub fn finish(mut self) {
+        if self.widths.is_none() {
+            self.release();
+        }
+    }
+
+    fn release(&mut self) {
+        let widths = self.table.widths(self.wide);
+
+        self.table.print(self.wide);
+        self.table.rows.clear();
+        self.widths = Some(widths);
+    }
+}
+
+fn widen(widths: &mut [usize], row: &[String]) {
+    for (width, cell) in widths.iter_mut().zip(row.iter()) {
+        *width = std::cmp::max(*width, len(cell));
+    }
+}
+
+fn print_row(row: &[String], widths: &[usize]) {
+    let cells: Vec<String> = row.iter().zip(widths.iter())
+        .map(|(cell, width)| format!("{:<1$}", fit(cell, *width), width))
+        .collect();
+
+    println!("{}", cells.join(SEPARATOR).trim_end());
+}
+
+fn len(cell: &str) -> usize {
+    cell.chars().count()
+}
+
+// Only output to a terminal is truncated.
+fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+
+    Some(std::env::var("COLUMNS").ok()
+                                 .and_then(|c| c.parse().ok())
+                                 .unwrap_or(80))
+}
+
+fn shrink(widths: &mut [usize], limit: usize) {
+    let separators = SEPARATOR.len() * widths.len().saturating_sub(1);
+
+    while widths.iter().sum::<usize>() + separators > limit {
+        let widest = match widths.iter_mut().max() {
+            Some(w) if *w > MIN_WIDTH => w,
+            _ => return,
+        };
+
+        *widest -= 1;
+    }
+}
+
+// Paths are told apart by their end, so the start is cut.
+fn fit(cell: &str, width: usize) -> String {
+    let length = len(cell);
+
+    if length <= width {
+        return cell.to_string();
+    }
+
+    let tail: String = cell.chars().skip(length - width + 1).collect();
+
+    format!("…{}", tail)
+}
@@ -0,0 +1,115 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use std::time::{Duration, Instant};
+
+// The order the phases are reported in.
+static PHASES: [&str; 4] = ["load", "validate", "serialize", "links"];
+
+static TIMINGS: std::sync::Mutex<Option<Timings>> = std::sync::Mutex::new(None);
+
+struct Timings {
+    threshold: Duration,
+    phases: [Duration; 4],
+    groups: Vec<(&'static str, String, Duration)>,
+}
+
+// Starts recording. Groups that take at least threshold in one phase are
+// reported on their own.
+pub fn enable(threshold: Duration) {
+    *lock() = Some(Timings{ threshold, phases: [Duration::ZERO; 4],
+                            groups: Vec::new() });
+}
+
+// The time to pass to group, or None when nothing is recorded.
+pub fn start() -> Option<Instant> {
+    lock().as_ref().map(|_| Instant::now())
+}
+
+// Runs f and adds the time it took to phase.
+pub fn phase<T, F: FnOnce() -> T>(phase: &'static str, f: F) -> T {
+    let started = start();
+    let result = f();
+
+    if let (Some(started), Some(t)) = (started, lock().as_mut()) {
+        t.phases[index(phase)] += started.elapsed();
+    }
+
+    result
+}
+
+// Records how long phase took for the group name since started.
+pub fn group(phase: &'static str, name: &str, started: Option<Instant>) {
+    if let (Some(started), Some(t)) = (started, lock().as_mut()) {
+        let elapsed = started.elapsed();
+
+        if elapsed >= t.threshold {
+            t.groups.push((phase, name.to_string(), elapsed));
+        }
+    }
+}
+
+// Prints the time spent in every phase, each followed by its slow groups,
+// to stderr so that machine-readable output is left alone.
+pub fn report() {
+    let t = match lock().take() {
+        Some(t) => t,
+        None => return,
+    };
+
+    eprintln!("update-alternatives: timings (groups over {}):",
+              millis(t.threshold));
+
+    for (i, phase) in PHASES.iter().enumerate() {
+        eprintln!("update-alternatives:     {:<10} {:>10}", phase,
+                  millis(t.phases[i]));
+
+        let mut groups: Vec<_> = t.groups.iter()
+                                  .filter(|(p, _, _)| p == phase)
+                                  .collect();
+        groups.sort_by_key(|(_, _, elapsed)| std::cmp::Reverse(*elapsed));
+
+        for (_, name, elapsed) in groups {
+            eprintln!("update-alternatives:         {} {}", name,
+                      millis(*elapsed));
+        }
+    }
+}
+
+fn index(phase: &str) -> usize {
+    PHASES.iter().position(|p| *p == phase)
+          .expect("timings phase is not one of PHASES")
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Timings>> {
+    TIMINGS.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,105 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+extern crate nix;
+
+use super::filesystem;
+
+pub static PENDING_PATH: &str = "/var/lib/update-alternatives/pending-sync";
+
+static OSTREE_MARKER: &str = "/run/ostree-booted";
+
+static TRANSACTIONAL_UPDATE: &str = "/usr/sbin/transactional-update";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum System {
+    Ostree,
+    TransactionalUpdate,
+    // Running inside `transactional-update run`, i.e. on the new snapshot.
+    Snapshot,
+}
+
+pub fn detect() -> Option<System> {
+    if std::env::var_os("TRANSACTIONAL_UPDATE").is_some() {
+        return Some(System::Snapshot);
+    }
+
+    if std::path::Path::new(OSTREE_MARKER).exists() {
+        return Some(System::Ostree);
+    }
+
+    if std::path::Path::new(TRANSACTIONAL_UPDATE).exists() && root_read_only() {
+        return Some(System::TransactionalUpdate);
+    }
+
+    None
+}
+
+fn root_read_only() -> bool {
+    use self::nix::sys::statvfs::{statvfs, FsFlags};
+
+    statvfs("/").map(|s| s.flags().contains(FsFlags::ST_RDONLY))
+                .unwrap_or(false)
+}
+
+// Re-runs the current invocation inside the pending (or a new) snapshot so
+// that /etc/alternatives is modified where it survives the next reboot.
+pub fn run_in_snapshot() -> std::io::Result<()> {
+    let exe = std::env::current_exe()?;
+    let args: Vec<std::ffi::OsString> = std::env::args_os().skip(1).collect();
+
+    let status = std::process::Command::new(TRANSACTIONAL_UPDATE)
+        .args(["--continue", "run"])
+        .arg(&exe)
+        .args(&args)
+        .status()?;
+
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+pub fn is_pending<P: std::convert::AsRef<std::path::Path>>(root: P) -> bool {
+    filesystem::under(root, PENDING_PATH).exists()
+}
+
+pub fn defer_links<P: std::convert::AsRef<std::path::Path>>(root: P)
+-> std::io::Result<()> {
+    let path = filesystem::under(root, PENDING_PATH);
+
+    if let Some(parent) = path.parent() {
+        if !parent.exists() {
+            filesystem::create_dir(parent)?;
+        }
+    }
+
+    filesystem::write("", &path)?;
+    filesystem::set_mode(&path, 0o644)
+}
+
+pub fn clear_pending<P: std::convert::AsRef<std::path::Path>>(root: P)
+-> std::io::Result<()> {
+    filesystem::remove(filesystem::under(root, PENDING_PATH))
+}
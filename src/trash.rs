@@ -0,0 +1,217 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+extern crate serde_json;
+
+use super::audit;
+use super::filesystem;
+use super::path_serde;
+
+// Where files in the way of a link are kept, one folder per file, named by
+// its id.
+pub static TRASH_DIR: &str = "/var/lib/update-alternatives/trash";
+
+static INFO_FILE: &str = "info.json";
+
+// Where a file in the trash came from, and why it was moved there.
+#[derive(Serialize, Deserialize)]
+pub struct Info {
+    // Where it was, below the root it was moved from.
+    #[serde(serialize_with = "path_serde::serialize",
+            deserialize_with = "path_serde::deserialize")]
+    pub path: std::path::PathBuf,
+    pub kind: String,
+    pub time: String,
+    pub user: String,
+    // The link of the group whose commit moved it.
+    #[serde(serialize_with = "path_serde::serialize",
+            deserialize_with = "path_serde::deserialize")]
+    pub link: std::path::PathBuf,
+    // The target of the symlink written in its place.
+    #[serde(serialize_with = "path_serde::serialize",
+            deserialize_with = "path_serde::deserialize")]
+    pub replaced_by: std::path::PathBuf,
+}
+
+pub struct Item {
+    pub id: String,
+    pub info: Info,
+}
+
+// Moves what is at path below root into the trash before a symlink to
+// replaced_by is written there, unless nothing or a symlink is there: those
+// are written by this tool and replaced as they always were. Returns the id it
+// was given.
+pub fn displace<P: std::convert::AsRef<std::path::Path>>(
+    root: P, path: &std::path::Path, link: &std::path::Path,
+    replaced_by: &std::path::Path
+) -> std::io::Result<Option<String>> {
+    let root = root.as_ref();
+    let concrete = filesystem::under(root, path);
+    let metadata = match concrete.symlink_metadata() {
+        Ok(m) => m,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(None);
+        },
+        Err(e) => return Err(e),
+    };
+
+    if metadata.file_type().is_symlink() {
+        return Ok(None);
+    }
+
+    let kind = if metadata.is_dir() {
+        "directory"
+    } else if metadata.is_file() {
+        "file"
+    } else {
+        "special file"
+    };
+    let now = std::time::SystemTime::now();
+    let (id, folder) = make_folder(root, now)?;
+    let info = Info{ path: path.to_path_buf(), kind: kind.to_string(),
+                     time: audit::timestamp(now),
+                     user: audit::invoking_user(), link: link.to_path_buf(),
+                     replaced_by: replaced_by.to_path_buf() };
+
+    // The information goes first, so that nothing is in the trash without
+    // it.
+    filesystem::write(serde_json::to_string_pretty(&info)?,
+                      folder.join(INFO_FILE))?;
+    filesystem::move_tree(&concrete, folder.join(entry_name(path)))?;
+
+    println!("update-alternatives: moved {} {} out of the way of the link to \
+             {}; 'update-alternatives trash restore {}' brings it back", kind,
+             concrete.display(), replaced_by.display(), id);
+
+    Ok(Some(id))
+}
+
+// The files in the trash below root, oldest first. Folders without readable
+// information are left out; whatever they hold is still there.
+pub fn list<P: std::convert::AsRef<std::path::Path>>(root: P)
+-> std::io::Result<Vec<Item>> {
+    let dir = filesystem::under(root, TRASH_DIR);
+    let children = match std::fs::read_dir(&dir) {
+        Ok(c) => c,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Vec::new());
+        },
+        Err(e) => return Err(e),
+    };
+    let mut items = Vec::new();
+
+    for child in children {
+        let child = child?;
+        let info = filesystem::read(child.path().join(INFO_FILE))
+            .ok()
+            .and_then(|c| serde_json::from_str::<Info>(&c).ok());
+
+        if let Some(info) = info {
+            items.push(Item{ id: child.file_name().to_string_lossy()
+                                                  .into_owned(),
+                             info });
+        }
+    }
+
+    items.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(items)
+}
+
+// Moves the file with the given id back where it was, or to the path to, and
+// empties its folder. Nothing is overwritten. Returns where it went.
+pub fn restore<P: std::convert::AsRef<std::path::Path>>(
+    root: P, id: &str, to: Option<&std::path::Path>
+) -> std::io::Result<std::path::PathBuf> {
+    let root = root.as_ref();
+    let item = list(root)?.into_iter().find(|i| i.id == id).ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound,
+                            format!("{} is not in the trash", id))
+    })?;
+    let folder = filesystem::under(root, TRASH_DIR).join(id);
+    let destination = filesystem::under(root, to.unwrap_or(&item.info.path));
+
+    if destination.symlink_metadata().is_ok() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} exists", destination.display())
+        ));
+    }
+
+    if let Some(parent) = destination.parent() {
+        if !parent.exists() {
+            filesystem::create_dir(parent)?;
+        }
+    }
+
+    filesystem::move_tree(folder.join(entry_name(&item.info.path)),
+                          &destination)?;
+    filesystem::remove(&folder)?;
+
+    Ok(destination)
+}
+
+// Creates a folder for a new file in the trash, named after the time, with a
+// number added if another was moved there in the same second.
+fn make_folder(root: &std::path::Path, now: std::time::SystemTime)
+-> std::io::Result<(String, std::path::PathBuf)> {
+    filesystem::check_writable()?;
+
+    let dir = filesystem::under(root, TRASH_DIR);
+
+    if !dir.exists() {
+        filesystem::create_dir(&dir)?;
+        filesystem::set_mode(&dir, 0o700)?;
+    }
+
+    let stamp: String = audit::timestamp(now).chars()
+                                             .filter(|c| !"-:".contains(*c))
+                                             .collect();
+
+    for n in 1.. {
+        let id = match n {
+            1 => stamp.clone(),
+            _ => format!("{}-{}", stamp, n),
+        };
+        let folder = dir.join(&id);
+
+        match std::fs::create_dir(&folder) {
+            Ok(()) => return Ok((id, folder)),
+            Err(ref e) if e.kind() == std::io::ErrorKind::AlreadyExists => (),
+            Err(e) => return Err(e),
+        }
+    }
+
+    unreachable!("ids never run out")
+}
+
+// The name a file is kept under in its folder.
+fn entry_name(path: &std::path::Path) -> std::ffi::OsString {
+    path.file_name().map(|n| n.to_os_string())
+        .unwrap_or_else(|| std::ffi::OsString::from("entry"))
+}
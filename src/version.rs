@@ -0,0 +1,110 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+// Derives a priority from the leading numeric part of a version, so that
+// newer versions win: 3.12 becomes 31200, 3.12.1 becomes 31201 and 18
+// becomes 180000. Components of 100 or more are not supported.
+pub fn priority(version: &str) -> Option<i64> {
+    let numeric: &str = match version.find(|c: char| !c.is_ascii_digit()
+                                                     && c != '.') {
+        Some(end) => &version[..end],
+        None => version,
+    };
+
+    let mut priority: i64 = 0;
+    let mut parts = numeric.trim_end_matches('.').split('.');
+
+    for (i, scale) in [10000, 100, 1].iter().enumerate() {
+        let part: i64 = match parts.next() {
+            Some(p) => p.parse().ok()?,
+            None if i > 0 => 0,
+            None => return None,
+        };
+
+        if i > 0 && part >= 100 {
+            return None;
+        }
+
+        priority = priority.checked_add(part.checked_mul(*scale)?)?;
+    }
+
+    Some(priority)
+}
+
+// Names of architectures, which end up in paths such as
+// /usr/lib/jvm/java-17-openjdk-amd64 but are no versions.
+static ARCHITECTURES: &[&str] = &["x86_64", "x86-64", "amd64", "arm64",
+                                  "aarch64", "armhf", "armel", "i386",
+                                  "i686", "ppc64el", "ppc64le", "ppc64",
+                                  "s390x", "riscv64", "mips64el", "loong64"];
+
+// The first version-like word, such as 3.12 in python3.12 or 17 in
+// java-17-openjdk-amd64, of the innermost component of path that has one.
+pub fn from_path(path: &std::path::Path) -> Option<String> {
+    path.iter().rev().find_map(|component| {
+        let mut component = component.to_string_lossy().into_owned();
+
+        for architecture in ARCHITECTURES {
+            component = component.replace(architecture, "-");
+        }
+
+        let start = component.find(|c: char| c.is_ascii_digit())?;
+        let word = &component[start..];
+        let end = word.find(|c: char| !c.is_ascii_digit() && c != '.')
+                      .unwrap_or(word.len());
+
+        Some(word[..end].trim_end_matches('.').to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::from_path;
+
+    fn version(path: &str) -> Option<String> {
+        from_path(std::path::Path::new(path))
+    }
+
+    #[test]
+    fn from_path_takes_the_first_version() {
+        assert_eq!(version("/usr/bin/python3.12").as_deref(), Some("3.12"));
+        assert_eq!(version("/usr/lib/jvm/java-21.0.2/bin/java").as_deref(),
+                   Some("21.0.2"));
+        assert_eq!(version("/opt/gcc-12-r2/bin/gcc").as_deref(), Some("12"));
+    }
+
+    #[test]
+    fn from_path_skips_architectures() {
+        assert_eq!(version("/usr/lib/jvm/java-17-openjdk-amd64/bin/java")
+                       .as_deref(), Some("17"));
+        assert_eq!(version("/usr/lib/jvm/java-11-openjdk-arm64/bin/java")
+                       .as_deref(), Some("11"));
+        assert_eq!(version("/usr/lib/x86_64-linux-gnu/llvm-15/bin/clang")
+                       .as_deref(), Some("15"));
+        assert_eq!(version("/usr/lib/x86_64-linux-gnu/bin/cc"), None);
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use super::alternative::Alternative;
+use super::alternative_list::AlternativeList;
+use super::path_serde;
+
+#[derive(Serialize)]
+pub struct GroupView<'a> {
+    pub name: &'a str,
+    #[serde(serialize_with = "path_serde::serialize")]
+    pub link: &'a std::path::Path,
+    pub mode: &'static str,
+    #[serde(serialize_with = "path_serde::serialize_option")]
+    pub current: Option<&'a std::path::Path>,
+    pub alternatives: &'a [Alternative],
+    // Only with list --archived.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<&'a [Alternative]>,
+}
+
+impl<'a> GroupView<'a> {
+    pub fn new(name: &'a str, list: &'a AlternativeList) -> GroupView<'a> {
+        GroupView{ name, link: list.path(), mode: list.mode(),
+                   current: list.current_target(), alternatives: list.links(),
+                   archived: None }
+    }
+}
+
+// The result of add, for --format json.
+#[derive(Serialize)]
+pub struct AddView<'a> {
+    pub name: &'a str,
+    #[serde(serialize_with = "path_serde::serialize")]
+    pub target: &'a std::path::Path,
+    pub priority: i64,
+    pub outcome: &'static str,
+    pub changed: bool,
+}
@@ -0,0 +1,264 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate nix;
+
+use self::nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify,
+                              WatchDescriptor};
+
+use super::alternative_db::AlternativeDb;
+use super::audit::AuditLog;
+use super::errors;
+use super::events;
+use super::freeze;
+use super::helper;
+use super::lock;
+use super::notify;
+use super::signals;
+
+type Watches = std::collections::HashMap<WatchDescriptor, std::path::PathBuf>;
+
+// With via_helper, links are rewritten by the privileged helper, which
+// records the repairs itself.
+pub fn run<P: std::convert::AsRef<std::path::Path>>(folder: P,
+                                                   log: &mut AuditLog,
+                                                   via_helper: bool)
+-> std::io::Result<()> {
+    let folder_path = folder.as_ref();
+    let mut db = AlternativeDb::from_folder(folder_path)?;
+    let groups = db.iter().map(|(n, _)| n.clone()).collect();
+
+    repair(&mut db, groups, log, via_helper);
+
+    let inotify = Inotify::init(InitFlags::IN_CLOEXEC)?;
+    let mut watches = Watches::new();
+
+    loop {
+        update_watches(&inotify, &mut watches, folder_path, &db);
+
+        let status = format!("watching {} directories for {} alternatives",
+                             watches.len(), db.num_alternatives());
+
+        println!("update-alternatives: {}", status);
+        notify::ready(&status);
+
+        let mut affected: Vec<String> = Vec::new();
+        let mut reload = false;
+
+        while affected.is_empty() && !reload {
+            notify::alive();
+
+            if !notify::wait(&inotify)? {
+                continue;
+            }
+
+            for event in inotify.read_events()? {
+                // The kernel drops the watch of a directory that is gone.
+                if event.mask.contains(AddWatchFlags::IN_IGNORED) {
+                    watches.remove(&event.wd);
+
+                    continue;
+                }
+
+                let (dir, name) = match (watches.get(&event.wd), event.name) {
+                    (Some(d), Some(n)) => (d, n),
+                    _ => continue,
+                };
+                let path = dir.join(name);
+
+                if dir == folder_path {
+                    reload |= path.extension().is_some_and(|e| e == "json");
+
+                    continue;
+                }
+
+                for (group, list) in db.iter() {
+                    let touched = list.path() == path
+                        || list.links().iter().any(|l| l.target() == path);
+
+                    if touched && !affected.contains(group) {
+                        affected.push(group.clone());
+                    }
+                }
+            }
+        }
+
+        // Give package managers a moment to finish moving files into place.
+        std::thread::sleep(std::time::Duration::from_millis(250));
+
+        if reload {
+            match AlternativeDb::from_folder(folder_path) {
+                Ok(d) => db = d,
+                Err(e) => {
+                    error!(BAD_DATABASE, "watch: could not reload {}: \
+                                         {}", folder_path.display(), e);
+
+                    continue;
+                },
+            }
+
+            affected = db.iter().map(|(n, _)| n.clone()).collect();
+        }
+
+        repair(&mut db, affected, log, via_helper);
+    }
+}
+
+// Watches the directories of the links and targets of db, and the folder
+// of the database, and stops watching those it no longer has.
+fn update_watches(inotify: &Inotify, watches: &mut Watches,
+                  folder: &std::path::Path, db: &AlternativeDb) {
+    let mut dirs: Vec<&std::path::Path> = vec![folder];
+
+    for (_, list) in db.iter() {
+        let paths = std::iter::once(list.path())
+            .chain(list.links().iter().map(|l| l.target()));
+
+        for dir in paths.filter_map(|p| p.parent()) {
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+
+    let unwanted: Vec<WatchDescriptor> =
+        watches.iter()
+               .filter(|(_, d)| !dirs.contains(&d.as_path()))
+               .map(|(wd, _)| *wd)
+               .collect();
+
+    for wd in unwanted {
+        watches.remove(&wd);
+
+        let _ = inotify.rm_watch(wd);
+    }
+
+    let flags = AddWatchFlags::IN_CREATE | AddWatchFlags::IN_DELETE
+        | AddWatchFlags::IN_MOVED_FROM | AddWatchFlags::IN_MOVED_TO
+        | AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_ATTRIB
+        | AddWatchFlags::IN_ONLYDIR;
+
+    for dir in dirs {
+        if watches.values().any(|d| d == dir) {
+            continue;
+        }
+
+        match inotify.add_watch(dir, flags) {
+            Ok(wd) => {
+                watches.insert(wd, dir.to_path_buf());
+            },
+            Err(e) => {
+                error!(SERVICE, "watch: cannot watch {}: {}",
+                       dir.display(), e);
+            },
+        }
+    }
+}
+
+fn repair(db: &mut AlternativeDb, groups: Vec<String>, log: &mut AuditLog,
+          via_helper: bool) {
+    if let Some(f) = freeze::frozen(db.root()) {
+        println!("update-alternatives: not repairing {} groups: the database \
+                 is {}", groups.len(), f);
+
+        return;
+    }
+
+    for group in groups {
+        // An instance that holds the lock of the group writes its link when
+        // it commits. The group may have changed since it was read.
+        let _lock = if via_helper {
+            None
+        } else {
+            match lock::try_acquire_groups(db.root(), &[&group]) {
+                Some(l) => Some(l),
+                None => {
+                    println!("update-alternatives: watch: not repairing {}: \
+                             another instance is changing it", group);
+
+                    continue;
+                },
+            }
+        };
+
+        if !via_helper {
+            if let Err(e) = db.reload(&group) {
+                error!(BAD_DATABASE, "watch: could not reload {}: {}", group,
+                       e);
+
+                continue;
+            }
+        }
+
+        let list = match db.alternatives(&group) {
+            Some(l) => l,
+            None => continue,
+        };
+
+        let old = list.linked_target();
+        let relinked = if via_helper {
+            helper::Client::connect().and_then(|mut c| c.repair(&group))
+        } else {
+            list.repair_symlink()
+        };
+        let repaired = list.repaired().map(|a| a.target());
+
+        match relinked {
+            Ok(true) => {
+                let target = repaired.map(|t| t.display().to_string())
+                                     .unwrap_or_default();
+
+                if !via_helper {
+                    log.record(format!("watch: linked {} to {} for {}",
+                                       list.path().display(), target, group));
+                }
+
+                if let Some(new) = repaired {
+                    events::emit(events::Event::LinkRepaired{
+                        group: &group, link: list.path(),
+                        old: old.as_deref(), new,
+                    });
+                }
+            },
+            Ok(false) => (),
+            Err(e) => {
+                let message = format!("watch: could not rewrite link {}: {}",
+                                      list.path().display(), e);
+
+                error!(COMMIT, "{}", message);
+                events::error(&errors::COMMIT, &message);
+            },
+        }
+    }
+
+    if let Err(e) = log.flush() {
+        error!(AUDIT, "could not write to audit log: {}", e);
+    }
+
+    signals::exit_if_pending();
+}
@@ -0,0 +1,159 @@
+// Copyright (c) 2026, Fabian Thomys
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+extern crate std;
+
+use super::filesystem;
+
+// Read by every desktop for every user that has not chosen an application
+// themselves.
+pub static MIMEAPPS_PATH: &str = "/etc/xdg/mimeapps.list";
+
+const DEFAULTS_SECTION: &str = "[Default Applications]";
+
+// Groups sharing a file are written by different threads; each change
+// reads and rewrites all of it.
+static EDITS: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+// The desktop file ID of a target such as
+// /usr/share/applications/firefox.desktop.
+pub fn desktop_id(target: &std::path::Path) -> Option<&str> {
+    target.file_name().and_then(|n| n.to_str())
+}
+
+// The ID of the preferred application for mime_type in the mimeapps.list at
+// path.
+pub fn default_application(path: &std::path::Path, mime_type: &str)
+-> Option<String> {
+    let contents = filesystem::read(path).ok()?;
+    let mut in_defaults = false;
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            in_defaults = line == DEFAULTS_SECTION;
+        } else if let (true, Some((key, value))) = (in_defaults,
+                                                    line.split_once('=')) {
+            if key.trim() == mime_type {
+                return value.split(';').map(str::trim)
+                            .find(|id| !id.is_empty())
+                            .map(String::from);
+            }
+        }
+    }
+
+    None
+}
+
+// Makes id the preferred application for every one of mime_types, ahead of
+// those listed already, and leaves everything else in the file as it is.
+// Returns whether the file changed.
+pub fn set_default_application(path: &std::path::Path, mime_types: &[String],
+                               id: &str) -> std::io::Result<bool> {
+    let _edit = EDITS.lock().unwrap_or_else(|e| e.into_inner());
+
+    let contents = match filesystem::read(path) {
+        Ok(c) => c,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    let entry = |mime_type: &str, value: &str| {
+        let others = value.split(';').map(str::trim)
+                          .filter(|i| !i.is_empty() && *i != id);
+        let ids: Vec<&str> = std::iter::once(id).chain(others).collect();
+
+        format!("{}={};", mime_type, ids.join(";"))
+    };
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut missing: Vec<&String> = mime_types.iter().collect();
+    let mut defaults_end = None;
+    let mut in_defaults = false;
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with('[') {
+            if in_defaults {
+                defaults_end = Some(lines.len());
+            }
+
+            in_defaults = trimmed == DEFAULTS_SECTION;
+        } else if in_defaults {
+            let key = trimmed.split_once('=').map(|(k, v)| (k.trim(), v));
+
+            if let Some((key, value)) = key {
+                if let Some(i) = missing.iter().position(|m| *m == key) {
+                    missing.remove(i);
+                    lines.push(entry(key, value));
+
+                    continue;
+                }
+            }
+        }
+
+        lines.push(line.to_string());
+    }
+
+    if in_defaults {
+        defaults_end = Some(lines.len());
+    }
+
+    let added = missing.iter().map(|m| entry(m, ""));
+
+    match defaults_end {
+        Some(mut end) => {
+            // After the last entry of the section, not the blank lines that
+            // separate it from the next.
+            while lines[end - 1].trim().is_empty() {
+                end -= 1;
+            }
+
+            let added: Vec<String> = added.collect();
+            lines.splice(end..end, added);
+        },
+        None => {
+            if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                lines.push(String::new());
+            }
+
+            lines.push(DEFAULTS_SECTION.to_string());
+            lines.extend(added);
+        },
+    }
+
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+
+    if updated == contents {
+        return Ok(false);
+    }
+
+    filesystem::write_atomic(updated, path)?;
+
+    Ok(true)
+}